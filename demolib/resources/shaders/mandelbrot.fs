@@ -1,31 +1,247 @@
 #version 450
 
-#define LOOP_COUNT (360)
+// FRACTAL_MANDELBROT, FRACTAL_JULIA, FRACTAL_MULTIBROT, FRACTAL_BURNING_SHIP, FRACTAL_TRICORN
+// のどれか 1 つを定義してコンパイルする
 
 layout(location = 0) out vec4 o_Color;
 layout(location = 0) in vec2 v_NormalizedFragCoord;
 
+layout(binding = 0) uniform Params
+{
+    // 中心座標とスケールは double-float (hi + lo) で渡される
+    vec2 u_CenterHigh;
+    vec2 u_CenterLow;
+    float u_ScaleHigh;
+    float u_ScaleLow;
+    uint u_MaxIterations;
+    // 0: 反復回数, 1: スムーズ, 2: 距離推定
+    uint u_ColoringMode;
+    uint u_UseDoubleFloat;
+    // マルチブロ集合の次数
+    float u_Exponent;
+    // ジュリア集合の定数
+    vec2 u_JuliaC;
+    // パレットの参照位置 t * u_PaletteCycles + u_PaletteOffset
+    float u_PaletteOffset;
+    float u_PaletteCycles;
+    // 描画先の幅 / 高さ
+    float u_Aspect;
+};
+
+// ユーザーが編集したグラデーション
+layout(binding = 1) uniform texture1D u_PaletteTexture;
+layout(binding = 2) uniform sampler u_PaletteSampler;
+
+// スムーズカラーリングのために脱出半径を大きめにとる
+const float BAILOUT_RADIUS = 256.0;
+
+struct Orbit
+{
+    uint count;
+    // 脱出した時点の z とその c (ジュリア集合は z_0) についての微分
+    vec2 z;
+    vec2 dz;
+};
+
+// double-float 演算
+// 2 つの float の和 (hi + lo) で 1 つの値を表して f32 の精度より深くズームできるようにする
+// 参考: Andrew Thall, "Extended-Precision Floating-Point Numbers for GPU Computation"
+vec2 df_add(vec2 a, vec2 b)
+{
+    float s = a.x + b.x;
+    float v = s - a.x;
+    float e = (a.x - (s - v)) + (b.x - v);
+    e += a.y + b.y;
+    float hi = s + e;
+    return vec2(hi, e - (hi - s));
+}
+
+vec2 df_split(float a)
+{
+    float t = a * 4097.0;
+    float hi = t - (t - a);
+    return vec2(hi, a - hi);
+}
+
+vec2 df_two_prod(float a, float b)
+{
+    float p = a * b;
+    vec2 a_split = df_split(a);
+    vec2 b_split = df_split(b);
+    float error = ((a_split.x * b_split.x - p) + a_split.x * b_split.y + a_split.y * b_split.x) + a_split.y * b_split.y;
+    return vec2(p, error);
+}
+
+vec2 df_mul(vec2 a, vec2 b)
+{
+    vec2 p = df_two_prod(a.x, b.x);
+    p.y += a.x * b.y + a.y * b.x;
+    float hi = p.x + p.y;
+    return vec2(hi, p.y - (hi - p.x));
+}
+
+vec2 complex_mul(vec2 a, vec2 b)
+{
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// z_{n+1} = f(z_n) + c
+vec2 next_z(vec2 z, vec2 c)
+{
+#if defined(FRACTAL_MULTIBROT)
+    float r = pow(length(z), u_Exponent);
+    float theta = atan(z.y, z.x) * u_Exponent;
+    return r * vec2(cos(theta), sin(theta)) + c;
+#else
+#if defined(FRACTAL_BURNING_SHIP)
+    z = abs(z);
+#endif
+#if defined(FRACTAL_TRICORN)
+    z.y = -z.y;
+#endif
+    return vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+#endif
+}
+
+// 距離推定用の微分 dz_{n+1} = f'(z_n) dz_n (+ 1)
+// バーニングシップとトライコーンは正則ではないので z^2 と同じ式で近似する
+vec2 next_dz(vec2 z, vec2 dz)
+{
+#if defined(FRACTAL_MULTIBROT)
+    float r = pow(length(z), u_Exponent - 1.0);
+    float theta = atan(z.y, z.x) * (u_Exponent - 1.0);
+    vec2 derivative = u_Exponent * r * vec2(cos(theta), sin(theta));
+#else
+    vec2 derivative = 2.0 * z;
+#endif
+
+#if defined(FRACTAL_JULIA)
+    return complex_mul(derivative, dz);
+#else
+    return complex_mul(derivative, dz) + vec2(1.0, 0.0);
+#endif
+}
+
+float degree()
+{
+#if defined(FRACTAL_MULTIBROT)
+    return u_Exponent;
+#else
+    return 2.0;
+#endif
+}
+
+Orbit iterate(vec2 position)
+{
+#if defined(FRACTAL_JULIA)
+    vec2 z = position;
+    vec2 c = u_JuliaC;
+    vec2 dz = vec2(1.0, 0.0);
+#else
+    vec2 z = vec2(0.0);
+    vec2 c = position;
+    vec2 dz = vec2(0.0);
+#endif
+
+    uint count = 0;
+    for (uint i = 0; i < u_MaxIterations; ++i) {
+        ++count;
+        if (dot(z, z) > BAILOUT_RADIUS * BAILOUT_RADIUS) {
+            break;
+        }
+
+        dz = next_dz(z, dz);
+        z = next_z(z, c);
+    }
+    return Orbit(count, z, dz);
+}
+
+// マルチブロ集合は double-float に対応していない
+// 微分は精度が要らないので f32 で計算する
+Orbit iterate_double_float(vec2 offset)
+{
+    vec2 scale = vec2(u_ScaleHigh, u_ScaleLow);
+    vec2 x = df_add(vec2(u_CenterHigh.x, u_CenterLow.x), df_mul(vec2(offset.x, 0.0), scale));
+    vec2 y = df_add(vec2(u_CenterHigh.y, u_CenterLow.y), df_mul(vec2(offset.y, 0.0), scale));
+
+#if defined(FRACTAL_JULIA)
+    vec2 zx = x;
+    vec2 zy = y;
+    vec2 cx = vec2(u_JuliaC.x, 0.0);
+    vec2 cy = vec2(u_JuliaC.y, 0.0);
+    vec2 dz = vec2(1.0, 0.0);
+#else
+    vec2 zx = vec2(0.0);
+    vec2 zy = vec2(0.0);
+    vec2 cx = x;
+    vec2 cy = y;
+    vec2 dz = vec2(0.0);
+#endif
+
+    uint count = 0;
+    for (uint i = 0; i < u_MaxIterations; ++i) {
+        ++count;
+        if (zx.x * zx.x + zy.x * zy.x > BAILOUT_RADIUS * BAILOUT_RADIUS) {
+            break;
+        }
+
+        dz = next_dz(vec2(zx.x, zy.x), dz);
+
+#if defined(FRACTAL_BURNING_SHIP)
+        zx = zx.x < 0.0 ? -zx : zx;
+        zy = zy.x < 0.0 ? -zy : zy;
+#endif
+#if defined(FRACTAL_TRICORN)
+        zy = -zy;
+#endif
+        vec2 zxzy = df_mul(zx, zy);
+        zx = df_add(df_add(df_mul(zx, zx), -df_mul(zy, zy)), cx);
+        zy = df_add(df_add(zxzy, zxzy), cy);
+    }
+    return Orbit(count, vec2(zx.x, zy.x), dz);
+}
+
+vec3 palette(float t)
+{
+    // textureLod は分岐の中でも使える
+    return textureLod(sampler1D(u_PaletteTexture, u_PaletteSampler), t * u_PaletteCycles + u_PaletteOffset, 0.0).rgb;
+}
+
 void main() {
-  int count = 0;
-  vec2 offset = vec2(-0.5, 0.0) + v_NormalizedFragCoord;
-  vec2 z = vec2(0.0);
-
-  for (int i = 0; i < LOOP_COUNT; ++i) {
-    ++count;
-    if (length(z) > 2.0) {
-      break;
+    // 微分は一様な制御フローの中で取る
+    float pixel_size = fwidth(v_NormalizedFragCoord.y) * u_ScaleHigh;
+
+    // 高さを [-1, 1] として横はアスペクト比のぶん広げる
+    vec2 position = v_NormalizedFragCoord * vec2(u_Aspect, 1.0);
+
+    Orbit orbit;
+    if (u_UseDoubleFloat != 0) {
+        orbit = iterate_double_float(position);
+    } else {
+        orbit = iterate(u_CenterHigh + position * u_ScaleHigh);
+    }
+
+    if (orbit.count >= u_MaxIterations) {
+        o_Color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    if (u_ColoringMode == 0) {
+        float t = log(float(orbit.count) / float(u_MaxIterations));
+        o_Color = vec4(palette(t), 1.0);
+        return;
     }
 
-    z = vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + offset;
-  }
-
-  float h = log(float(count) / LOOP_COUNT);
-  float s = 0.9;
-  float v = 0.7;
-  vec3 color =
-      ((clamp(abs(fract(h + vec3(0, 2, 1) / 3.) * 6. - 3.) - 1., 0., 1.) - 1.) *
-           s +
-       1.) *
-      v;
-  o_Color = vec4(color, 1.0);
+    // 正規化反復回数 (normalized iteration count)
+    float z_length = length(orbit.z);
+    float smooth_count = float(orbit.count) + 1.0 - log(log(z_length)) / log(degree());
+    float t = log(max(smooth_count, 1.0) / float(u_MaxIterations));
+    vec3 color = palette(t);
+
+    if (u_ColoringMode == 2) {
+        // 境界までの距離が 1 ピクセルより近いところを暗くする
+        float distance = 0.5 * z_length * log(z_length) / length(orbit.dz);
+        color *= clamp(sqrt(distance / pixel_size), 0.0, 1.0);
+    }
+    o_Color = vec4(color, 1.0);
 }
@@ -1,9 +1,227 @@
 #version 450
 
 layout(location = 0) out vec4 o_Color;
-layout(location = 0) in vec3 v_Normal;
+layout(location = 0) in vec3 v_WorldPosition;
+layout(location = 1) in vec3 v_Normal;
+
+layout(set = 0, binding = 0) uniform View
+{
+    vec4 u_ViewProjection[4];
+    vec3 u_CameraPosition;
+    uint u_LightCount;
+    vec3 u_Ambient;
+};
+
+const uint LIGHT_DIRECTIONAL = 0;
+const uint LIGHT_POINT = 1;
+const uint LIGHT_SPOT = 2;
+const uint MAX_LIGHTS = 8;
+
+struct Light
+{
+    vec3 position;
+    uint kind;
+    // 光が進む向き
+    vec3 direction;
+    // 0 なら打ち切らない
+    float range;
+    // 強さを掛けたリニアの色
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+layout(set = 0, binding = 1) uniform Lights
+{
+    Light u_Lights[MAX_LIGHTS];
+};
+
+layout(set = 1, binding = 0) uniform Object
+{
+    vec4 u_Model[4];
+    vec4 u_NormalMatrix[3];
+    vec4 u_BaseColor;
+    vec3 u_Emissive;
+    float u_Metallic;
+    float u_Roughness;
+};
+
+// 平行光源 1 つぶんのシャドウマップ
+layout(set = 2, binding = 0) uniform Shadow
+{
+    // カスケードごとのライトのビュー射影行列
+    vec4 u_LightViewProjection[16];
+    // 各カスケードがおおうビュー空間の深度の上限
+    vec4 u_CascadeSplits;
+    vec3 u_CameraForward;
+    uint u_CascadeCount;
+    vec3 u_LightDirection;
+    // 影を落とすライトの番号 (MAX_LIGHTS なら影なし)
+    uint u_ShadowLight;
+    float u_DepthBias;
+    float u_SlopeBias;
+    int u_PcfRadius;
+    int u_DebugCascade;
+    // シャドウマップの 1 辺のテクセル数
+    uint u_Resolution;
+};
+
+layout(set = 2, binding = 1) uniform texture2DArray u_ShadowMap;
+layout(set = 2, binding = 2) uniform samplerShadow u_ShadowSampler;
+
+const float PI = 3.14159265;
+
+// Cook-Torrance (GGX, Smith, Schlick)
+// 参考: Brian Karis, "Real Shading in Unreal Engine 4"
+float distribution_ggx(float n_dot_h, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float k)
+{
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return geometry_schlick_ggx(n_dot_v, k) * geometry_schlick_ggx(n_dot_l, k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0)
+{
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// 表面からライトへの向きと届く光の量
+vec3 light_radiance(Light light, vec3 position, out vec3 l)
+{
+    if (light.kind == LIGHT_DIRECTIONAL)
+    {
+        l = -normalize(light.direction);
+        return light.color;
+    }
+
+    vec3 to_light = light.position - position;
+    float distance = length(to_light);
+    l = to_light / max(distance, 0.0001);
+
+    // 逆二乗に range で 0 になる窓関数を掛ける
+    float attenuation = 1.0 / max(distance * distance, 0.0001);
+    if (light.range > 0.0)
+    {
+        float ratio = distance / light.range;
+        float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+
+    if (light.kind == LIGHT_SPOT)
+    {
+        float cos_angle = dot(-l, normalize(light.direction));
+        attenuation *= smoothstep(light.cos_outer, light.cos_inner, cos_angle);
+    }
+    return light.color * attenuation;
+}
+
+#if SHADOWS
+// 光が当たっていれば 1
+// 比較サンプルは一様な制御フローで呼ぶ必要があるので、分岐せずに計算する
+float shadow_factor(vec3 position, vec3 n)
+{
+    float depth = dot(position - u_CameraPosition, u_CameraForward);
+    uint cascade = 0;
+    for (uint index = 0; index + 1 < u_CascadeCount; ++index)
+    {
+        cascade += uint(step(u_CascadeSplits[index], depth));
+    }
+
+    vec4 world_position = vec4(position, 1.0);
+    vec4 light_position = vec4(
+        dot(u_LightViewProjection[cascade * 4 + 0], world_position),
+        dot(u_LightViewProjection[cascade * 4 + 1], world_position),
+        dot(u_LightViewProjection[cascade * 4 + 2], world_position),
+        dot(u_LightViewProjection[cascade * 4 + 3], world_position));
+    vec3 ndc = light_position.xyz / light_position.w;
+    vec2 texcoord = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+
+    // 斜めに光が当たる面ほどバイアスを大きくする
+    float cos_theta = clamp(dot(n, -u_LightDirection), 0.0, 1.0);
+    float tan_theta = sqrt(1.0 - cos_theta * cos_theta) / max(cos_theta, 0.05);
+    float reference = ndc.z - u_DepthBias - u_SlopeBias * min(tan_theta, 10.0);
+
+    // PCF
+    vec2 texel_size = vec2(1.0 / float(u_Resolution));
+    float lit = 0.0;
+    for (int y = -u_PcfRadius; y <= u_PcfRadius; ++y)
+    {
+        for (int x = -u_PcfRadius; x <= u_PcfRadius; ++x)
+        {
+            vec2 offset = vec2(float(x), float(y)) * texel_size;
+            lit += texture(sampler2DArrayShadow(u_ShadowMap, u_ShadowSampler), vec4(texcoord + offset, float(cascade), reference));
+        }
+    }
+    float kernel_width = float(u_PcfRadius * 2 + 1);
+    lit /= kernel_width * kernel_width;
+
+    // シャドウマップやカスケードの範囲の外は光が当たっているものとする
+    vec3 inside = step(vec3(0.0), vec3(texcoord, ndc.z)) * step(vec3(texcoord, ndc.z), vec3(1.0));
+    float is_inside = inside.x * inside.y * inside.z * (1.0 - step(u_CascadeSplits[cascade], depth));
+    return mix(1.0, lit, is_inside);
+}
+#endif
 
 void main()
 {
-    o_Color = vec4(normalize(v_Normal), 1.0);
+    vec3 n = normalize(v_Normal);
+    // カリングしていないので裏面は法線を反転する
+    if (!gl_FrontFacing)
+    {
+        n = -n;
+    }
+#if SHADOWS
+    float shadow = shadow_factor(v_WorldPosition, n);
+#else
+    // 影を無効にしたときはシャドウマップを読まない
+    float shadow = 1.0;
+#endif
+    vec3 v = normalize(u_CameraPosition - v_WorldPosition);
+    float n_dot_v = max(dot(n, v), 0.0001);
+
+    vec3 base_color = u_BaseColor.rgb;
+    float roughness = clamp(u_Roughness, 0.04, 1.0);
+    vec3 f0 = mix(vec3(0.04), base_color, u_Metallic);
+
+    vec3 color = vec3(0.0);
+    for (uint index = 0; index < min(u_LightCount, MAX_LIGHTS); ++index)
+    {
+        vec3 l;
+        vec3 radiance = light_radiance(u_Lights[index], v_WorldPosition, l);
+        if (index == u_ShadowLight)
+        {
+            radiance *= shadow;
+        }
+        float n_dot_l = dot(n, l);
+        if (n_dot_l <= 0.0)
+        {
+            continue;
+        }
+
+        vec3 h = normalize(v + l);
+        float n_dot_h = max(dot(n, h), 0.0);
+        vec3 f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+        vec3 specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * f
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - f) * (1.0 - u_Metallic) * base_color / PI;
+        color += (diffuse + specular) * radiance * n_dot_l;
+    }
+    color += u_Ambient * base_color + u_Emissive;
+
+    // Reinhard でトーンマップしてからガンマ補正する (描画先は Unorm)
+    color = color / (color + 1.0);
+    o_Color = vec4(pow(color, vec3(1.0 / 2.2)), u_BaseColor.a);
 }
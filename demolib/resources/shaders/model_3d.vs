@@ -1,22 +1,48 @@
 #version 450
 
-layout(location = 0) out vec3 v_Normal;
+layout(location = 0) out vec3 v_WorldPosition;
+layout(location = 1) out vec3 v_Normal;
 
 layout(location = 0) in vec3 i_Position;
 layout(location = 1) in vec3 i_Normal;
 
-layout(binding = 0) uniform  View
+layout(set = 0, binding = 0) uniform View
 {
-    vec4 u_Mvp[4];
+    vec4 u_ViewProjection[4];
+    vec3 u_CameraPosition;
+    uint u_LightCount;
+    vec3 u_Ambient;
+};
+
+// メッシュごとの値 (動的オフセットで切り替える)
+layout(set = 1, binding = 0) uniform Object
+{
+    vec4 u_Model[4];
+    // モデル行列の左上 3x3 の逆転置
+    vec4 u_NormalMatrix[3];
+    vec4 u_BaseColor;
+    vec3 u_Emissive;
+    float u_Metallic;
+    float u_Roughness;
 };
 
 void main()
 {
-    vec4 position = vec4(
-        dot(u_Mvp[0], vec4(i_Position, 1.0)),
-        dot(u_Mvp[1], vec4(i_Position, 1.0)),
-        dot(u_Mvp[2], vec4(i_Position, 1.0)),
-        dot(u_Mvp[3], vec4(i_Position, 1.0)));
-    gl_Position = position;
-    v_Normal = i_Normal;
+    vec4 position = vec4(i_Position, 1.0);
+    vec4 world_position = vec4(
+        dot(u_Model[0], position),
+        dot(u_Model[1], position),
+        dot(u_Model[2], position),
+        dot(u_Model[3], position));
+    gl_Position = vec4(
+        dot(u_ViewProjection[0], world_position),
+        dot(u_ViewProjection[1], world_position),
+        dot(u_ViewProjection[2], world_position),
+        dot(u_ViewProjection[3], world_position));
+
+    v_WorldPosition = world_position.xyz / world_position.w;
+    v_Normal = vec3(
+        dot(u_NormalMatrix[0].xyz, i_Normal),
+        dot(u_NormalMatrix[1].xyz, i_Normal),
+        dot(u_NormalMatrix[2].xyz, i_Normal));
 }
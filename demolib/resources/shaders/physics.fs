@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) out vec4 o_Color;
+
+layout(location = 0) in vec3 v_Normal;
+layout(location = 1) in vec3 v_Color;
+
+void main()
+{
+    vec3 light_direction = normalize(vec3(0.4, 1.0, -0.3));
+    float diffuse = max(dot(normalize(v_Normal), light_direction), 0.0);
+    o_Color = vec4(v_Color * (0.25 + 0.75 * diffuse), 1.0);
+}
@@ -0,0 +1,36 @@
+#version 450
+
+layout(location = 0) out vec3 v_Normal;
+layout(location = 1) out vec3 v_Color;
+
+layout(location = 0) in vec3 i_Position;
+layout(location = 1) in vec3 i_Normal;
+layout(location = 2) in vec4 i_Translation;
+layout(location = 3) in vec4 i_Scale;
+layout(location = 4) in vec4 i_Color;
+layout(location = 5) in vec4 i_Rotation;
+
+layout(binding = 0) uniform View
+{
+    vec4 u_ViewProjection[4];
+};
+
+// 単位クォータニオン q で v を回転する
+vec3 rotate(vec4 q, vec3 v)
+{
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+void main()
+{
+    vec4 position = vec4(rotate(i_Rotation, i_Position * i_Scale.xyz) + i_Translation.xyz, 1.0);
+    gl_Position = vec4(
+        dot(u_ViewProjection[0], position),
+        dot(u_ViewProjection[1], position),
+        dot(u_ViewProjection[2], position),
+        dot(u_ViewProjection[3], position));
+
+    // 非一様スケールでも法線が面に垂直になるようにスケールの逆数をかける
+    v_Normal = rotate(i_Rotation, i_Normal / i_Scale.xyz);
+    v_Color = i_Color.rgb;
+}
@@ -0,0 +1,34 @@
+#version 450
+
+layout(location = 0) in vec3 i_Position;
+
+// カスケード 1 つぶんのライトのビュー射影行列 (動的オフセットで切り替える)
+layout(set = 0, binding = 0) uniform Cascade
+{
+    vec4 u_LightViewProjection[4];
+};
+
+layout(set = 1, binding = 0) uniform Object
+{
+    vec4 u_Model[4];
+    vec4 u_NormalMatrix[3];
+    vec4 u_BaseColor;
+    vec3 u_Emissive;
+    float u_Metallic;
+    float u_Roughness;
+};
+
+void main()
+{
+    vec4 position = vec4(i_Position, 1.0);
+    vec4 world_position = vec4(
+        dot(u_Model[0], position),
+        dot(u_Model[1], position),
+        dot(u_Model[2], position),
+        dot(u_Model[3], position));
+    gl_Position = vec4(
+        dot(u_LightViewProjection[0], world_position),
+        dot(u_LightViewProjection[1], world_position),
+        dot(u_LightViewProjection[2], world_position),
+        dot(u_LightViewProjection[3], world_position));
+}
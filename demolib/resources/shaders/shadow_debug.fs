@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) out vec4 o_Color;
+layout(location = 0) in vec2 v_Texcoord;
+
+layout(set = 0, binding = 0) uniform Shadow
+{
+    vec4 u_LightViewProjection[16];
+    vec4 u_CascadeSplits;
+    vec3 u_CameraForward;
+    uint u_CascadeCount;
+    vec3 u_LightDirection;
+    uint u_ShadowLight;
+    float u_DepthBias;
+    float u_SlopeBias;
+    int u_PcfRadius;
+    int u_DebugCascade;
+    // シャドウマップの 1 辺のテクセル数
+    uint u_Resolution;
+};
+
+layout(set = 0, binding = 1) uniform texture2DArray u_ShadowMap;
+layout(set = 0, binding = 2) uniform sampler u_Sampler;
+
+void main()
+{
+    ivec2 size = ivec2(u_Resolution);
+    ivec2 texel = min(ivec2(v_Texcoord * vec2(size)), size - 1);
+    float depth = texelFetch(sampler2DArray(u_ShadowMap, u_Sampler), ivec3(texel, max(u_DebugCascade, 0)), 0).r;
+    o_Color = vec4(vec3(depth), 1.0);
+}
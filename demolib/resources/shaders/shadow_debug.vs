@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) out vec2 v_Texcoord;
+
+// 画面の左下に四角形を出す
+const vec2 POSITIONS[6] = vec2[](
+    vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+    vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0));
+
+void main()
+{
+    vec2 position = POSITIONS[gl_VertexIndex];
+    gl_Position = vec4(position * 0.8 - 1.0, 0.0, 1.0);
+    v_Texcoord = vec2(position.x, 1.0 - position.y);
+}
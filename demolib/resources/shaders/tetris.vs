@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) out vec4 v_Color;
+
+layout(location = 0) in vec2 i_Position;
+layout(location = 1) in vec4 i_Rect;
+layout(location = 2) in vec4 i_Color;
+
+void main()
+{
+    gl_Position = vec4(i_Rect.xy + i_Position * i_Rect.zw, 0.0, 1.0);
+    v_Color = i_Color;
+}
@@ -0,0 +1,182 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+// 真上や真下を向くと look_at の上方向と一致してしまうので手前で止める
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+const MIN_DISTANCE: f32 = 0.01;
+
+// 注視点のまわりを回るカメラ
+// Z-up の左手系
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrbitCamera {
+    pub target: [f32; 3],
+    pub distance: f32,
+    // ラジアン
+    pub yaw: f32,
+    pub pitch: f32,
+    // 縦方向の画角 (度)
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self::look_at([2.0, 2.0, 1.5], [0.0, 0.0, 0.0])
+    }
+}
+
+impl OrbitCamera {
+    pub fn look_at(eye: [f32; 3], target: [f32; 3]) -> Self {
+        let offset = glm::Vec3::from(eye) - glm::Vec3::from(target);
+        let distance = offset.norm().max(MIN_DISTANCE);
+        Self {
+            target,
+            distance,
+            yaw: offset.y.atan2(offset.x).rem_euclid(std::f32::consts::TAU),
+            pitch: (offset.z / distance).clamp(-1.0, 1.0).asin(),
+            fov_y: 60.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn eye(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        [
+            self.target[0] + self.distance * cos_pitch * cos_yaw,
+            self.target[1] + self.distance * cos_pitch * sin_yaw,
+            self.target[2] + self.distance * sin_pitch,
+        ]
+    }
+
+    // 角度はラジアン
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        self.yaw = (self.yaw + delta_yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + delta_pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // delta は画面の高さを 1 とした移動量 (右下が正)
+    // カーソルの下の点が追従するように注視点を動かす
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        let (right, up) = self.right_and_up();
+        let height = 2.0 * self.distance * (self.fov_y.to_radians() * 0.5).tan();
+        let target =
+            glm::Vec3::from(self.target) - right * delta[0] * height + up * delta[1] * height;
+        self.target = target.into();
+    }
+
+    // factor < 1 で近づく
+    pub fn dolly(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(MIN_DISTANCE, self.far);
+    }
+
+    // バウンディングボックス全体が画面に収まるようにする
+    pub fn frame(&mut self, min: [f32; 3], max: [f32; 3]) {
+        let min = glm::Vec3::from(min);
+        let max = glm::Vec3::from(max);
+        let radius = ((max - min).norm() * 0.5).max(MIN_DISTANCE);
+        self.target = ((min + max) * 0.5).into();
+        self.distance = radius / (self.fov_y.to_radians() * 0.5).sin();
+        self.near = (self.distance - radius).max(self.distance * 0.01);
+        self.far = self.distance + radius * 2.0;
+    }
+
+    pub fn view_matrix(&self) -> glm::Mat4 {
+        glm::look_at_lh(
+            &glm::Vec3::from(self.eye()),
+            &glm::Vec3::from(self.target),
+            &glm::Vec3::z(),
+        )
+    }
+
+    pub fn projection_matrix(&self, aspect: f32) -> glm::Mat4 {
+        glm::perspective_lh_zo(aspect, self.fov_y.to_radians(), self.near, self.far)
+    }
+
+    pub fn view_projection_matrix(&self, aspect: f32) -> glm::Mat4 {
+        self.projection_matrix(aspect) * self.view_matrix()
+    }
+
+    pub fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        use egui::{DragValue, Slider};
+
+        ui.horizontal(|ui| {
+            ui.label("Target");
+            for value in &mut self.target {
+                ui.add(DragValue::new(value).speed(0.01));
+            }
+        });
+        ui.add(
+            Slider::new(&mut self.distance, MIN_DISTANCE..=self.far)
+                .text("Distance")
+                .logarithmic(true),
+        );
+        let mut yaw = self.yaw.to_degrees();
+        let mut pitch = self.pitch.to_degrees();
+        ui.add(Slider::new(&mut yaw, 0.0..=360.0).text("Yaw"));
+        ui.add(
+            Slider::new(&mut pitch, -MAX_PITCH.to_degrees()..=MAX_PITCH.to_degrees()).text("Pitch"),
+        );
+        self.yaw = yaw.to_radians();
+        self.pitch = pitch.to_radians();
+
+        ui.add(Slider::new(&mut self.fov_y, 10.0..=120.0).text("FOV"));
+        ui.horizontal(|ui| {
+            ui.label("Near");
+            ui.add(
+                DragValue::new(&mut self.near)
+                    .speed(0.01)
+                    .clamp_range(0.001..=self.far),
+            );
+            ui.label("Far");
+            ui.add(
+                DragValue::new(&mut self.far)
+                    .speed(1.0)
+                    .clamp_range(self.near..=10000.0),
+            );
+        });
+
+        ui.label("Drag: Orbit");
+        ui.label("Right drag / Shift + Drag: Pan");
+        ui.label("Scroll: Dolly");
+    }
+
+    pub fn interact(&mut self, response: &egui::Response) {
+        let height = response.rect.height().max(1.0);
+        let drag_delta = response.drag_delta();
+        if drag_delta != egui::Vec2::ZERO {
+            let is_shift_pressed = response.ctx.input(|input| input.modifiers.shift);
+            if response.dragged_by(egui::PointerButton::Secondary)
+                || response.dragged_by(egui::PointerButton::Middle)
+                || is_shift_pressed
+            {
+                self.pan([drag_delta.x / height, drag_delta.y / height]);
+            } else {
+                // 画面の高さぶんドラッグで半周
+                let scale = std::f32::consts::PI / height;
+                self.orbit(-drag_delta.x * scale, drag_delta.y * scale);
+            }
+        }
+
+        if response.hovered() {
+            let (scroll_delta, zoom_delta) = response
+                .ctx
+                .input(|input| (input.scroll_delta.y, input.zoom_delta()));
+            let factor = (-scroll_delta * 0.002).exp() / zoom_delta;
+            if factor != 1.0 {
+                self.dolly(factor);
+            }
+        }
+    }
+
+    // ビュー空間の右方向と上方向 (ワールド座標)
+    fn right_and_up(&self) -> (glm::Vec3, glm::Vec3) {
+        let forward = (glm::Vec3::from(self.target) - glm::Vec3::from(self.eye())).normalize();
+        let right = glm::Vec3::z().cross(&forward).normalize();
+        let up = forward.cross(&right);
+        (right, up)
+    }
+}
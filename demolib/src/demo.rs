@@ -0,0 +1,79 @@
+use std::any::Any;
+
+use crate::{DemoState, StateError};
+
+// デモごとのパラメーター
+// ワークスペース (UI スレッド側) が保持して、プロパティパネルやキャンバスへの入力で編集される
+pub trait DemoParams: Any + Send {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    // プロパティパネルに表示する UI
+    fn draw_properties(&mut self, _ui: &mut egui::Ui) {}
+
+    // デモが選択されている間、キャンバスの Response とともに毎フレーム呼ばれる
+    fn interact(&mut self, _response: &egui::Response) {}
+
+    // 時間で動くデモ (物理シミュレーション、テトリス) を dt 秒進める
+    // ウィンドウでは interact のあとに毎フレーム、オフライン描画ではフレームの間隔ずつ呼ばれる
+    fn advance(&mut self, _dt: f32) {}
+
+    // セッションやプロジェクトファイルに残したい状態
+    // 保存するものがなければ None
+    fn save_state(&self) -> Option<DemoState> {
+        None
+    }
+
+    // save_state で保存した状態から復元する
+    // 失敗したときはパラメーターを変えない
+    fn load_state(&mut self, _state: DemoState) -> Result<(), StateError> {
+        Ok(())
+    }
+}
+
+// 描画を担当するデモの共通インターフェース
+// DemoManager に登録して使う
+pub trait Demo: Send + Sync {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self
+    where
+        Self: Sized;
+
+    fn name(&self) -> &'static str;
+
+    fn create_params(&self) -> Box<dyn DemoParams>;
+
+    fn is_depth_required(&self) -> bool {
+        false
+    }
+
+    // 描画先の大きさ (物理ピクセル) が変わったときに呼ばれる
+    // 登録した直後にも 1 回呼ばれる
+    fn resize(&mut self, _device: &wgpu::Device, _width: u32, _height: u32) {}
+
+    // メッシュの差し替えなどでリソースを作り直すときは device を使う
+    fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams);
+
+    // メインのレンダーパスより前に追加のパスを積みたいとき用
+    fn encode(&self, _command_encoder: &mut wgpu::CommandEncoder) {}
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>);
+}
+
+// パラメーターを持たないデモ用
+#[derive(Default)]
+pub struct EmptyParams;
+
+impl DemoParams for EmptyParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        ui.label("Nothing");
+    }
+}
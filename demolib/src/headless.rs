@@ -0,0 +1,372 @@
+use std::io::Write;
+
+use crate::{Demo, DemoParams};
+
+// ヘッドレス描画で使うカラーバッファーのフォーマット
+// ポートフォリオの DemoManager と同じ
+pub const HEADLESS_COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    Map(wgpu::BufferAsyncError),
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    PngDecode(png::DecodingError),
+    Exr(exr::error::Error),
+    UnsupportedFormat(String),
+}
+
+impl std::fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no suitable adapter was found"),
+            HeadlessError::RequestDevice(error) => write!(f, "{error}"),
+            HeadlessError::Map(error) => write!(f, "{error}"),
+            HeadlessError::Io(error) => write!(f, "{error}"),
+            HeadlessError::Png(error) => write!(f, "{error}"),
+            HeadlessError::PngDecode(error) => write!(f, "{error}"),
+            HeadlessError::Exr(error) => write!(f, "{error}"),
+            HeadlessError::UnsupportedFormat(name) => write!(f, "unsupported format: {name}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<std::io::Error> for HeadlessError {
+    fn from(error: std::io::Error) -> Self {
+        HeadlessError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for HeadlessError {
+    fn from(error: png::EncodingError) -> Self {
+        HeadlessError::Png(error)
+    }
+}
+
+impl From<png::DecodingError> for HeadlessError {
+    fn from(error: png::DecodingError) -> Self {
+        HeadlessError::PngDecode(error)
+    }
+}
+
+impl From<exr::error::Error> for HeadlessError {
+    fn from(error: exr::error::Error) -> Self {
+        HeadlessError::Exr(error)
+    }
+}
+
+// 読み戻した RGBA8 の画素 (行の詰め物なし、上から下)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    // texture は COPY_SRC 付きの Rgba8Unorm
+    // 読み戻しが終わるまでブロックする
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Result<Self, HeadlessError> {
+        let width = texture.width();
+        let height = texture.height();
+
+        // バッファーへのコピーは 1 行を 256 バイト単位にそろえる必要がある
+        let unpadded_bytes_per_row = width * 4;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(alignment) * alignment;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(Some(command_encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(HeadlessError::Map)?;
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> Result<(), HeadlessError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    // 8 ビットのグレースケール、RGB、RGBA の PNG を RGBA8 にして読み込む
+    pub fn read_png(reader: impl std::io::Read) -> Result<Self, HeadlessError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+                .collect(),
+            _ => buffer
+                .iter()
+                .flat_map(|value| [*value, *value, *value, 255])
+                .collect(),
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    // デモの出力はガンマ 2.2 でエンコードされているので、リニアに戻して書き出す
+    pub fn write_exr(&self, path: impl AsRef<std::path::Path>) -> Result<(), HeadlessError> {
+        let to_linear = |value: u8| (value as f32 / 255.0).powf(2.2);
+        exr::prelude::write_rgba_file(path, self.width as usize, self.height as usize, |x, y| {
+            let index = (y * self.width as usize + x) * 4;
+            let pixel = &self.pixels[index..index + 4];
+            (
+                to_linear(pixel[0]),
+                to_linear(pixel[1]),
+                to_linear(pixel[2]),
+                pixel[3] as f32 / 255.0,
+            )
+        })?;
+        Ok(())
+    }
+
+    // 拡張子 (.png, .exr) で形式を判断する
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), HeadlessError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => self.write_png(std::io::BufWriter::new(std::fs::File::create(path)?)),
+            "exr" => self.write_exr(path),
+            _ => Err(HeadlessError::UnsupportedFormat(
+                path.to_string_lossy().to_string(),
+            )),
+        }
+    }
+}
+
+// 追加のパスとメインのレンダーパスを積む
+// ウィンドウ上の描画とヘッドレス描画で同じ手順を使う
+pub fn encode_demo(
+    demo: &dyn Demo,
+    command_encoder: &mut wgpu::CommandEncoder,
+    color_view: &wgpu::TextureView,
+    depth_view: &wgpu::TextureView,
+) {
+    demo.encode(command_encoder);
+
+    let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: color_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: if demo.is_depth_required() {
+            Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            })
+        } else {
+            None
+        },
+    });
+    demo.draw(&mut render_pass);
+}
+
+// ウィンドウなしでデモを描画して画素を読み戻す
+#[cfg(not(target_arch = "wasm32"))]
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HeadlessRenderer {
+    pub fn new() -> Result<Self, HeadlessError> {
+        Self::with_adapter(
+            wgpu::Backends::all(),
+            wgpu::PowerPreference::default(),
+            false,
+        )
+    }
+
+    // コマンドラインで指定したバックエンドと電力の設定のアダプターを使う
+    pub fn with_options(
+        backends: wgpu::Backends,
+        power_preference: wgpu::PowerPreference,
+    ) -> Result<Self, HeadlessError> {
+        Self::with_adapter(backends, power_preference, false)
+    }
+
+    // ソフトウェアのアダプターがあればそれを使う
+    // 環境によって結果が変わりにくいので、画像を比較するテスト向け
+    pub fn new_fallback() -> Result<Self, HeadlessError> {
+        let power_preference = wgpu::PowerPreference::default();
+        Self::with_adapter(wgpu::Backends::all(), power_preference, true)
+            .or_else(|_| Self::with_adapter(wgpu::Backends::all(), power_preference, false))
+    }
+
+    fn with_adapter(
+        backends: wgpu::Backends,
+        power_preference: wgpu::PowerPreference,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let adapter =
+            futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference,
+                force_fallback_adapter,
+                compatible_surface: None,
+            }))
+            .ok_or(HeadlessError::NoAdapter)?;
+        let (device, queue) = futures::executor::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits:
+                    wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        ))
+        .map_err(HeadlessError::RequestDevice)?;
+        Ok(Self { device, queue })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    // 初期パラメーターで描画する
+    pub fn render<T: Demo>(&self, width: u32, height: u32) -> Result<Image, HeadlessError> {
+        let mut demo = T::create(&self.device, HEADLESS_COLOR_FORMAT);
+        let params = demo.create_params();
+        self.render_demo(&mut demo, params.as_ref(), width, height)
+    }
+
+    pub fn render_demo(
+        &self,
+        demo: &mut dyn Demo,
+        params: &dyn DemoParams,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, HeadlessError> {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let color_buffer = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HEADLESS_COLOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let depth_buffer = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        demo.resize(&self.device, size.width, size.height);
+        demo.update(&self.device, &self.queue, params);
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encode_demo(
+            demo,
+            &mut command_encoder,
+            &color_buffer.create_view(&wgpu::TextureViewDescriptor::default()),
+            &depth_buffer.create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+        self.queue.submit(Some(command_encoder.finish()));
+
+        Image::from_texture(&self.device, &self.queue, &color_buffer)
+    }
+}
@@ -1,7 +1,54 @@
+mod camera;
+mod demo;
+mod headless;
+mod lighting;
 mod mandelbrot;
 mod model_3d;
+mod palette;
+pub mod physics;
+mod pipeline_cache;
+mod random;
+pub mod reference;
+pub mod scene;
+mod shaders;
+mod state;
+mod tetris;
 mod triangle;
+mod uniform;
 
-pub use mandelbrot::Mandelbrot;
-pub use model_3d::Model3d;
+pub use camera::OrbitCamera;
+pub use demo::{Demo, DemoParams, EmptyParams};
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::HeadlessRenderer;
+pub use headless::{encode_demo, HeadlessError, Image, HEADLESS_COLOR_FORMAT};
+pub use lighting::{Light, LightKind, Lighting, MAX_LIGHTS};
+pub use mandelbrot::{ColoringMode, FractalType, Mandelbrot, MandelbrotParams};
+pub use model_3d::{Model3d, Model3dParams, ShadowSettings, MAX_CASCADES};
+pub use palette::{ColorStop, Palette};
+pub use physics::{Physics, PhysicsParams};
+pub use pipeline_cache::PipelineCache;
+pub use shaders::{
+    bind_group_layout_entries, create_bind_group_layout, layouts, shader_entry, shader_source,
+    shader_variant, ShaderEntry, ShaderResource, VertexLayout, SHADERS,
+};
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+pub use shaders::{record_shaders, set_shader_override, ShaderReloader, ShaderUpdate};
+pub use state::{DemoState, Migration, StateError};
+pub use tetris::{
+    Piece, Rotation, Tetris, TetrisGame, TetrisInput, TetrisParams, TetrominoKind, BOARD_HEIGHT,
+    BOARD_WIDTH,
+};
 pub use triangle::{Triangle, TriangleParams};
+pub use uniform::{Std140, Std430};
+
+pub use demolib_derive::{Std140, Std430};
+
+// derive(Std140) が生成するコードは demolib の中でも ::demolib を参照する
+extern crate self as demolib;
+
+// derive(Std140) と derive(Std430) が生成するコードから使う
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::uniform::{std140_array, std430_array, LayoutRule, StructLayout};
+    pub use bytemuck;
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use crate::scene::Material;
+
+// シェーダーの Lights の配列の長さ
+pub const MAX_LIGHTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot,
+}
+
+impl LightKind {
+    const ALL: [LightKind; 3] = [LightKind::Directional, LightKind::Point, LightKind::Spot];
+
+    fn name(self) -> &'static str {
+        match self {
+            LightKind::Directional => "Directional",
+            LightKind::Point => "Point",
+            LightKind::Spot => "Spot",
+        }
+    }
+}
+
+// Z-up のワールド座標
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    pub kind: LightKind,
+    // リニア
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // Point と Spot で使う
+    pub position: [f32; 3],
+    // 光が進む向き (Directional と Spot で使う)
+    pub direction: [f32; 3],
+    // 0 なら減衰は距離の逆二乗だけ
+    pub range: f32,
+    // スポットの内側と外側の半角 (度)
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub enabled: bool,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self::directional([1.0; 3], 3.0, [-1.0, -0.5, -1.0])
+    }
+}
+
+impl Light {
+    pub fn directional(color: [f32; 3], intensity: f32, direction: [f32; 3]) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            color,
+            intensity,
+            position: [0.0; 3],
+            direction,
+            range: 0.0,
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            enabled: true,
+        }
+    }
+
+    pub fn point(color: [f32; 3], intensity: f32, position: [f32; 3]) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            ..Self::directional(color, intensity, [0.0, 0.0, -1.0])
+        }
+    }
+
+    pub fn spot(color: [f32; 3], intensity: f32, position: [f32; 3], direction: [f32; 3]) -> Self {
+        Self {
+            kind: LightKind::Spot,
+            position,
+            ..Self::directional(color, intensity, direction)
+        }
+    }
+
+    // 編集されたら true を返す
+    pub fn draw_editor(&mut self, ui: &mut egui::Ui) -> bool {
+        use egui::{DragValue, Slider};
+
+        let mut is_changed = false;
+        ui.horizontal(|ui| {
+            is_changed |= ui.checkbox(&mut self.enabled, "Enabled").changed();
+            egui::ComboBox::from_id_source(ui.id().with("kind"))
+                .selected_text(self.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in LightKind::ALL {
+                        is_changed |= ui
+                            .selectable_value(&mut self.kind, kind, kind.name())
+                            .changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Color");
+            is_changed |= ui.color_edit_button_rgb(&mut self.color).changed();
+        });
+        is_changed |= ui
+            .add(
+                Slider::new(&mut self.intensity, 0.0..=100.0)
+                    .text("Intensity")
+                    .logarithmic(true),
+            )
+            .changed();
+
+        if self.kind != LightKind::Directional {
+            ui.horizontal(|ui| {
+                ui.label("Position");
+                for value in &mut self.position {
+                    is_changed |= ui.add(DragValue::new(value).speed(0.05)).changed();
+                }
+            });
+            is_changed |= ui
+                .add(
+                    DragValue::new(&mut self.range)
+                        .speed(0.1)
+                        .clamp_range(0.0..=1000.0)
+                        .prefix("Range: "),
+                )
+                .changed();
+        }
+        if self.kind != LightKind::Point {
+            ui.horizontal(|ui| {
+                ui.label("Direction");
+                for value in &mut self.direction {
+                    is_changed |= ui.add(DragValue::new(value).speed(0.01)).changed();
+                }
+            });
+        }
+        if self.kind == LightKind::Spot {
+            is_changed |= ui
+                .add(Slider::new(&mut self.outer_angle, 1.0..=89.0).text("Outer angle"))
+                .changed();
+            is_changed |= ui
+                .add(Slider::new(&mut self.inner_angle, 0.0..=self.outer_angle).text("Inner angle"))
+                .changed();
+        }
+        is_changed
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lighting {
+    pub ambient: [f32; 3],
+    pub lights: Vec<Light>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: [0.03; 3],
+            lights: vec![
+                Light::directional([1.0, 0.96, 0.9], 3.0, [-1.0, -0.5, -1.0]),
+                Light::point([0.6, 0.7, 1.0], 10.0, [-2.0, 2.0, 2.0]),
+            ],
+        }
+    }
+}
+
+impl Lighting {
+    pub fn draw_editor(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut is_changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Ambient");
+            is_changed |= ui.color_edit_button_rgb(&mut self.ambient).changed();
+        });
+
+        let mut remove_index = None;
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.separator();
+                is_changed |= light.draw_editor(ui);
+                if ui.button("Remove light").clicked() {
+                    remove_index = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove_index {
+            self.lights.remove(index);
+            is_changed = true;
+        }
+
+        ui.separator();
+        if ui
+            .add_enabled(
+                self.lights.len() < MAX_LIGHTS,
+                egui::Button::new("Add light"),
+            )
+            .clicked()
+        {
+            self.lights.push(Light::default());
+            is_changed = true;
+        }
+        is_changed
+    }
+}
+
+// 編集されたら true を返す
+pub fn draw_material_editor(material: &mut Material, ui: &mut egui::Ui) -> bool {
+    use egui::Slider;
+
+    let mut is_changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Base color");
+        is_changed |= ui
+            .color_edit_button_rgba_unmultiplied(&mut material.base_color)
+            .changed();
+    });
+    is_changed |= ui
+        .add(Slider::new(&mut material.metallic, 0.0..=1.0).text("Metallic"))
+        .changed();
+    is_changed |= ui
+        .add(Slider::new(&mut material.roughness, 0.0..=1.0).text("Roughness"))
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Emissive");
+        is_changed |= ui.color_edit_button_rgb(&mut material.emissive).changed();
+    });
+    is_changed
+}
@@ -1,60 +1,554 @@
-use std::borrow::Cow;
+use std::{any::Any, mem::size_of};
 
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
+use crate::{
+    create_bind_group_layout,
+    palette::PALETTE_TEXTURE_WIDTH,
+    shaders::{assert_std140, create_shader_module, shader_variant},
+    ColorStop, Demo, DemoParams, DemoState, Migration, Palette, PipelineCache, StateError, Std140,
+    VertexLayout,
+};
+
+// これより拡大したら f32 では精度が足りないので double-float で計算する
+const DOUBLE_FLOAT_SCALE_THRESHOLD: f64 = 1.0e-4;
+
+// double-float でも精度が足りなくなる手前で止める
+const MIN_SCALE: f64 = 1.0e-12;
+// double-float を使わないフラクタルは f32 の精度で止める
+const MIN_SCALE_F32: f64 = 1.0e-5;
+const MAX_SCALE: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FractalType {
+    Mandelbrot,
+    Julia,
+    Multibrot,
+    BurningShip,
+    Tricorn,
+}
+
+impl FractalType {
+    pub const ALL: [FractalType; 5] = [
+        FractalType::Mandelbrot,
+        FractalType::Julia,
+        FractalType::Multibrot,
+        FractalType::BurningShip,
+        FractalType::Tricorn,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FractalType::Mandelbrot => "Mandelbrot",
+            FractalType::Julia => "Julia",
+            FractalType::Multibrot => "Multibrot",
+            FractalType::BurningShip => "Burning Ship",
+            FractalType::Tricorn => "Tricorn",
+        }
+    }
+
+    // 全体が収まる表示範囲 (中心, スケール)
+    pub fn default_view(&self) -> ([f64; 2], f64) {
+        match self {
+            FractalType::Mandelbrot => ([-0.5, 0.0], 1.0),
+            FractalType::Julia => ([0.0, 0.0], 1.5),
+            FractalType::Multibrot => ([0.0, 0.0], 1.5),
+            FractalType::BurningShip => ([-0.5, -0.5], 1.2),
+            FractalType::Tricorn => ([-0.3, 0.0], 1.5),
+        }
+    }
+
+    // Multibrot は実数乗のべき乗を double-float で計算できないので f32 のまま
+    pub fn supports_double_float(&self) -> bool {
+        *self != FractalType::Multibrot
+    }
+
+    // これ以上拡大すると画像が崩れる
+    pub fn min_scale(&self) -> f64 {
+        if self.supports_double_float() {
+            MIN_SCALE
+        } else {
+            MIN_SCALE_F32
+        }
+    }
+
+    // mandelbrot.fs で漸化式を切り替えるマクロ
+    fn define(&self) -> &'static str {
+        match self {
+            FractalType::Mandelbrot => "FRACTAL_MANDELBROT",
+            FractalType::Julia => "FRACTAL_JULIA",
+            FractalType::Multibrot => "FRACTAL_MULTIBROT",
+            FractalType::BurningShip => "FRACTAL_BURNING_SHIP",
+            FractalType::Tricorn => "FRACTAL_TRICORN",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColoringMode {
+    // 反復回数をそのまま使う (バンディングが出る)
+    Iteration,
+    // 正規化反復回数で連続的に塗る
+    Smooth,
+    // スムーズカラーリングに距離推定の陰影を乗せる
+    DistanceEstimation,
+}
+
+impl ColoringMode {
+    pub const ALL: [ColoringMode; 3] = [
+        ColoringMode::Iteration,
+        ColoringMode::Smooth,
+        ColoringMode::DistanceEstimation,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColoringMode::Iteration => "Iteration",
+            ColoringMode::Smooth => "Smooth",
+            ColoringMode::DistanceEstimation => "Distance estimation",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MandelbrotParams {
+    pub fractal_type: FractalType,
+    pub center: [f64; 2],
+    // 描画範囲の高さの半分
+    pub scale: f64,
+    pub max_iterations: u32,
+    pub coloring_mode: ColoringMode,
+    // 描画に使うパレット
+    pub palette: Palette,
+    // 保存されたパレットの一覧
+    pub palettes: Vec<Palette>,
+    pub palette_offset: f32,
+    pub palette_cycles: f32,
+    // マルチブロ集合の次数
+    pub exponent: f32,
+    // ジュリア集合の定数
+    pub julia_c: [f64; 2],
+}
+
+impl Default for MandelbrotParams {
+    fn default() -> Self {
+        Self {
+            fractal_type: FractalType::Mandelbrot,
+            center: [-0.5, 0.0],
+            scale: 1.0,
+            max_iterations: 360,
+            coloring_mode: ColoringMode::Smooth,
+            palette: Palette::presets().remove(0),
+            palettes: Palette::presets(),
+            palette_offset: 0.0,
+            palette_cycles: 1.0,
+            exponent: 3.0,
+            julia_c: [-0.8, 0.156],
+        }
+    }
+}
+
+impl MandelbrotParams {
+    pub fn is_double_float_required(&self) -> bool {
+        self.scale < DOUBLE_FLOAT_SCALE_THRESHOLD && self.fractal_type.supports_double_float()
+    }
+
+    pub fn set_fractal_type(&mut self, fractal_type: FractalType) {
+        if self.fractal_type == fractal_type {
+            return;
+        }
+        self.fractal_type = fractal_type;
+        self.reset_view();
+    }
+
+    pub fn reset_view(&mut self) {
+        (self.center, self.scale) = self.fractal_type.default_view();
+    }
+
+    // position は描画範囲の高さを [-1, 1] に正規化した座標
+    pub fn to_complex(&self, position: [f64; 2]) -> [f64; 2] {
+        [
+            self.center[0] + position[0] * self.scale,
+            self.center[1] + position[1] * self.scale,
+        ]
+    }
+
+    // カーソル位置を固定したまま拡大縮小する
+    // position は描画範囲の高さを [-1, 1] に正規化した座標
+    pub fn zoom(&mut self, factor: f64, position: [f64; 2]) {
+        let scale = (self.scale * factor).clamp(self.fractal_type.min_scale(), MAX_SCALE);
+        let ratio = 1.0 - scale / self.scale;
+        self.center[0] += position[0] * self.scale * ratio;
+        self.center[1] += position[1] * self.scale * ratio;
+        self.scale = scale;
+    }
+
+    // delta は描画範囲の高さを [-1, 1] に正規化した移動量
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.center[0] -= delta[0] * self.scale;
+        self.center[1] -= delta[1] * self.scale;
+    }
+}
+
+impl MandelbrotParams {
+    fn draw_palette_properties(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Palette")
+            .selected_text(self.palette.name.clone())
+            .show_ui(ui, |ui| {
+                for palette in &self.palettes {
+                    if ui
+                        .selectable_label(self.palette == *palette, &palette.name)
+                        .clicked()
+                    {
+                        self.palette = palette.clone();
+                    }
+                }
+            });
+        ui.add(egui::Slider::new(&mut self.palette_cycles, 0.1..=8.0).text("Cycles"));
+        ui.add(egui::Slider::new(&mut self.palette_offset, 0.0..=1.0).text("Offset"));
+
+        egui::CollapsingHeader::new("Edit palette").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut self.palette.name);
+            });
+            self.palette.draw_editor(ui);
+
+            ui.horizontal(|ui| {
+                // 同じ名前のパレットがあれば上書きする
+                if ui.button("Save").clicked() {
+                    match self
+                        .palettes
+                        .iter_mut()
+                        .find(|palette| palette.name == self.palette.name)
+                    {
+                        Some(palette) => *palette = self.palette.clone(),
+                        None => self.palettes.push(self.palette.clone()),
+                    }
+                }
+                if ui.button("Delete").clicked() && self.palettes.len() > 1 {
+                    self.palettes
+                        .retain(|palette| palette.name != self.palette.name);
+                }
+                if ui.button("Restore presets").clicked() {
+                    for preset in Palette::presets() {
+                        if !self
+                            .palettes
+                            .iter()
+                            .any(|palette| palette.name == preset.name)
+                        {
+                            self.palettes.push(preset);
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
+
+// 保存した状態の古いバージョンからの変換
+const STATE_MIGRATIONS: &[Migration] = &[migrate_from_ron];
+
+// バージョン 0 はパラメーターを RON の文字列にして保存していた
+fn migrate_from_ron(params: serde_json::Value) -> Result<serde_json::Value, StateError> {
+    let serde_json::Value::String(text) = params else {
+        return Err(StateError::Migration("expected a RON string".to_string()));
+    };
+    let params: MandelbrotParams =
+        ron::from_str(&text).map_err(|error| StateError::Migration(error.to_string()))?;
+    Ok(serde_json::to_value(params)?)
+}
+
+impl DemoParams for MandelbrotParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        let mut fractal_type = self.fractal_type;
+        egui::ComboBox::from_label("Fractal")
+            .selected_text(fractal_type.name())
+            .show_ui(ui, |ui| {
+                for item in FractalType::ALL {
+                    ui.selectable_value(&mut fractal_type, item, item.name());
+                }
+            });
+        self.set_fractal_type(fractal_type);
+
+        match self.fractal_type {
+            FractalType::Julia => {
+                ui.horizontal(|ui| {
+                    ui.label("c");
+                    ui.add(egui::DragValue::new(&mut self.julia_c[0]).speed(0.001));
+                    ui.add(egui::DragValue::new(&mut self.julia_c[1]).speed(0.001));
+                });
+            }
+            FractalType::Multibrot => {
+                ui.add(egui::Slider::new(&mut self.exponent, 2.0..=8.0).text("Exponent"));
+            }
+            _ => {}
+        }
+
+        ui.separator();
+        let speed = self.scale * 0.01;
+        ui.horizontal(|ui| {
+            ui.label("Center");
+            ui.add(egui::DragValue::new(&mut self.center[0]).speed(speed));
+            ui.add(egui::DragValue::new(&mut self.center[1]).speed(speed));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Scale");
+            ui.add(
+                egui::DragValue::new(&mut self.scale)
+                    .speed(speed)
+                    .clamp_range(self.fractal_type.min_scale()..=MAX_SCALE),
+            );
+        });
+        ui.add(
+            egui::Slider::new(&mut self.max_iterations, 16..=4096)
+                .text("Iterations")
+                .logarithmic(true),
+        );
+        if ui.button("Reset view").clicked() {
+            self.reset_view();
+        }
+
+        ui.separator();
+        egui::ComboBox::from_label("Coloring")
+            .selected_text(self.coloring_mode.name())
+            .show_ui(ui, |ui| {
+                for mode in ColoringMode::ALL {
+                    ui.selectable_value(&mut self.coloring_mode, mode, mode.name());
+                }
+            });
+        self.draw_palette_properties(ui);
+
+        ui.separator();
+        ui.label(if self.is_double_float_required() {
+            "Precision: double-float"
+        } else {
+            "Precision: f32"
+        });
+        ui.label("Drag: Pan");
+        ui.label("Scroll: Zoom");
+        if self.fractal_type == FractalType::Mandelbrot {
+            ui.label("Click: Open Julia set at that point");
+        }
+    }
+
+    fn save_state(&self) -> Option<DemoState> {
+        DemoState::new(self, STATE_MIGRATIONS).ok()
+    }
+
+    fn load_state(&mut self, state: DemoState) -> Result<(), StateError> {
+        *self = state.decode(STATE_MIGRATIONS)?;
+        Ok(())
+    }
+
+    fn interact(&mut self, response: &egui::Response) {
+        // 縦横とも高さの半分を 1 とする
+        let rect = response.rect;
+        let half_height = rect.height().max(1.0) as f64 * 0.5;
+
+        // 画面は下向き、複素平面は上向きが正
+        let drag_delta = response.drag_delta();
+        if drag_delta != egui::Vec2::ZERO {
+            self.pan([
+                drag_delta.x as f64 / half_height,
+                -drag_delta.y as f64 / half_height,
+            ]);
+        }
+
+        let Some(hover_position) = response.hover_pos() else {
+            return;
+        };
+        let to_normalized = |position: egui::Pos2| {
+            let position = position - rect.center();
+            [
+                position.x as f64 / half_height,
+                -position.y as f64 / half_height,
+            ]
+        };
+
+        // マンデルブロ集合の上でクリックした点をジュリア集合の定数にする
+        if response.clicked() && self.fractal_type == FractalType::Mandelbrot {
+            self.julia_c = self.to_complex(to_normalized(hover_position));
+            self.set_fractal_type(FractalType::Julia);
+            return;
+        }
+
+        let (scroll_delta, zoom_delta) = response
+            .ctx
+            .input(|input| (input.scroll_delta.y, input.zoom_delta()));
+        let factor = (-scroll_delta as f64 * 0.002).exp() / zoom_delta as f64;
+        if factor != 1.0 {
+            self.zoom(factor, to_normalized(hover_position));
+        }
+    }
+}
+
+// mandelbrot.fs の Params と同じレイアウト
+#[derive(Std140)]
+pub struct MandelbrotUniform {
+    center_high: [f32; 2],
+    center_low: [f32; 2],
+    scale_high: f32,
+    scale_low: f32,
+    max_iterations: u32,
+    coloring_mode: u32,
+    use_double_float: bool,
+    exponent: f32,
+    julia_c: [f32; 2],
+    palette_offset: f32,
+    palette_cycles: f32,
+    aspect: f32,
+}
+
+assert_std140!(MandelbrotUniformStd140, mandelbrot_fs::params, {
+    center_high: U_CENTER_HIGH,
+    center_low: U_CENTER_LOW,
+    scale_high: U_SCALE_HIGH,
+    scale_low: U_SCALE_LOW,
+    max_iterations: U_MAX_ITERATIONS,
+    coloring_mode: U_COLORING_MODE,
+    use_double_float: U_USE_DOUBLE_FLOAT,
+    exponent: U_EXPONENT,
+    julia_c: U_JULIA_C,
+    palette_offset: U_PALETTE_OFFSET,
+    palette_cycles: U_PALETTE_CYCLES,
+    aspect: U_ASPECT,
+});
+
+impl MandelbrotUniform {
+    pub fn new(params: &MandelbrotParams, aspect: f32) -> Self {
+        // f64 を f32 の和 (hi + lo) に分解する
+        let split = |value: f64| {
+            let high = value as f32;
+            (high, (value - high as f64) as f32)
+        };
+        let (center_x_high, center_x_low) = split(params.center[0]);
+        let (center_y_high, center_y_low) = split(params.center[1]);
+        let (scale_high, scale_low) = split(params.scale);
+        Self {
+            center_high: [center_x_high, center_y_high],
+            center_low: [center_x_low, center_y_low],
+            scale_high,
+            scale_low,
+            max_iterations: params.max_iterations,
+            coloring_mode: match params.coloring_mode {
+                ColoringMode::Iteration => 0,
+                ColoringMode::Smooth => 1,
+                ColoringMode::DistanceEstimation => 2,
+            },
+            use_double_float: params.is_double_float_required(),
+            exponent: params.exponent,
+            julia_c: [params.julia_c[0] as f32, params.julia_c[1] as f32],
+            palette_offset: params.palette_offset,
+            palette_cycles: params.palette_cycles,
+            aspect,
+        }
+    }
+}
+
 pub struct Mandelbrot<'a> {
-    render_pipeline: wgpu::RenderPipeline,
+    // 選ばれたことのあるフラクタルの種類ぶんだけ作る
+    render_pipelines: PipelineCache<FractalType>,
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex_shader_module: wgpu::ShaderModule,
+    target_format: wgpu::TextureFormat,
+    fractal_type: FractalType,
+    bind_group: wgpu::BindGroup,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    constant_buffer: wgpu::Buffer,
+    palette_texture: wgpu::Texture,
+    // 最後にアップロードしたパレット
+    palette_stops: Vec<ColorStop>,
+    // 描画先の幅 / 高さ
+    aspect: f32,
     _marker: std::marker::PhantomData<&'a ()>,
 }
 
 impl<'a> Mandelbrot<'a> {
     pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
-        let vertex_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        let vertex_shader_module = create_shader_module(device, "mandelbrot.vs");
+
+        let constant_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("mandelbrot.vs.wgsl"))),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            size: size_of::<MandelbrotUniformStd140>() as u64,
+            mapped_at_creation: false,
         });
-        let pixel_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+
+        // パレットは update で書き込む
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("mandelbrot.fs.wgsl"))),
+            size: wgpu::Extent3d {
+                width: PALETTE_TEXTURE_WIDTH,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_texture_view = palette_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D1),
+            ..Default::default()
+        });
+        // パレットは周期的に繰り返す
+        let palette_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
         });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        // フラクタルの種類によってリソースは変わらない
+        let bind_group_layout =
+            create_bind_group_layout(device, &["mandelbrot.vs", "mandelbrot.fs"], 0);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
-            layout: Some(
-                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[],
-                    push_constant_ranges: &[],
-                }),
-            ),
-            vertex: wgpu::VertexState {
-                module: &vertex_shader_module,
-                entry_point: "main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: (std::mem::size_of::<f32>() * 2) as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[wgpu::VertexAttribute {
-                        format: wgpu::VertexFormat::Float32x2,
-                        offset: 0,
-                        shader_location: 0,
-                    }],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &pixel_shader_module,
-                entry_point: "main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: target_format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: Default::default(),
-            depth_stencil: Default::default(),
-            multisample: Default::default(),
-            multiview: Default::default(),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: constant_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&palette_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&palette_sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let fractal_type = FractalType::Mandelbrot;
+        let mut render_pipelines = PipelineCache::new();
+        render_pipelines.prepare(fractal_type, |fractal_type| {
+            create_render_pipeline(
+                device,
+                &pipeline_layout,
+                &vertex_shader_module,
+                target_format,
+                fractal_type,
+            )
         });
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -74,17 +568,137 @@ impl<'a> Mandelbrot<'a> {
         });
 
         Self {
-            render_pipeline,
+            render_pipelines,
+            pipeline_layout,
+            vertex_shader_module,
+            target_format,
+            fractal_type,
+            bind_group,
             vertex_buffer,
             index_buffer,
+            constant_buffer,
+            palette_texture,
+            palette_stops: Vec::new(),
+            aspect: 1.0,
             _marker: std::marker::PhantomData,
         }
     }
 
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        params: &MandelbrotParams,
+    ) {
+        self.fractal_type = params.fractal_type;
+        self.render_pipelines
+            .prepare(self.fractal_type, |fractal_type| {
+                create_render_pipeline(
+                    device,
+                    &self.pipeline_layout,
+                    &self.vertex_shader_module,
+                    self.target_format,
+                    fractal_type,
+                )
+            });
+        queue.write_buffer(
+            &self.constant_buffer,
+            0,
+            bytemuck::bytes_of(&MandelbrotUniform::new(params, self.aspect).std140()),
+        );
+
+        // パレットが編集されたときだけテクスチャーを書き換える
+        if self.palette_stops != params.palette.stops() {
+            self.palette_stops = params.palette.stops().to_vec();
+            queue.write_texture(
+                self.palette_texture.as_image_copy(),
+                &params.palette.to_rgba8(PALETTE_TEXTURE_WIDTH),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(PALETTE_TEXTURE_WIDTH * 4),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: PALETTE_TEXTURE_WIDTH,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
     pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        render_pass.set_pipeline(&self.render_pipeline);
+        let Some(render_pipeline) = self.render_pipelines.get(self.fractal_type) else {
+            return;
+        };
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         render_pass.draw_indexed(0..6, 0, 0..1);
     }
 }
+
+impl<'a> Demo for Mandelbrot<'a> {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self::new(device, target_format)
+    }
+
+    fn name(&self) -> &'static str {
+        "Mandelbrot"
+    }
+
+    fn create_params(&self) -> Box<dyn DemoParams> {
+        Box::<MandelbrotParams>::default()
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+    }
+
+    fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams) {
+        let Some(params) = params.as_any().downcast_ref::<MandelbrotParams>() else {
+            return;
+        };
+        Mandelbrot::update(self, device, queue, params);
+    }
+
+    fn draw<'b>(&'b self, render_pass: &mut wgpu::RenderPass<'b>) {
+        Mandelbrot::draw(self, render_pass);
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    vertex_shader_module: &wgpu::ShaderModule,
+    target_format: wgpu::TextureFormat,
+    fractal_type: FractalType,
+) -> wgpu::RenderPipeline {
+    // build.rs でフラクタルの種類ごとにコンパイルしたシェーダー
+    let pixel_shader_name = shader_variant("mandelbrot.fs", &[(fractal_type.define(), "1")]);
+    let pixel_shader_module = create_shader_module(device, pixel_shader_name);
+    let vertex_layout = VertexLayout::new("mandelbrot.vs", &[0]);
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: vertex_shader_module,
+            entry_point: "main",
+            buffers: &[vertex_layout.buffer_layout(wgpu::VertexStepMode::Vertex)],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &pixel_shader_module,
+            entry_point: "main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: target_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: Default::default(),
+        depth_stencil: Default::default(),
+        multisample: Default::default(),
+        multiview: Default::default(),
+    })
+}
@@ -1,215 +1,793 @@
-use std::borrow::Cow;
+mod shadow;
 
-use usd_rs::serializer::PropertyType;
+use std::{
+    any::Any,
+    ops::Range,
+    sync::{Arc, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
-#[derive(bytemuck::NoUninit, Clone, Copy)]
-#[repr(C)]
+use crate::{
+    bind_group_layout_entries, create_bind_group_layout,
+    lighting::draw_material_editor,
+    scene::{Material, Mesh, Scene},
+    shaders::{assert_std140, create_shader_module, shader_variant, with_dynamic_offsets},
+    Demo, DemoParams, DemoState, LightKind, Lighting, OrbitCamera, PipelineCache, StateError,
+    Std140, VertexLayout, MAX_LIGHTS,
+};
+
+use shadow::{ShadowCaster, ShadowRenderer};
+
+pub use shadow::{ShadowSettings, MAX_CASCADES};
+
+// 組み込みのトーラス
+// パラメーターと描画側で同じ Arc を共有して、読み込み直しを避ける
+fn default_scene() -> Arc<Scene> {
+    static SCENE: OnceLock<Arc<Scene>> = OnceLock::new();
+    SCENE
+        .get_or_init(|| {
+            let scene = Scene::from_usda(include_str!("../resources/models/torus.usda"))
+                .expect("embedded torus.usda is broken");
+            Arc::new(scene)
+        })
+        .clone()
+}
+
+#[derive(Clone, Debug)]
 pub struct Model3dParams {
-    mvp: [f32; 16],
+    pub camera: OrbitCamera,
+    pub scene: Arc<Scene>,
+    pub lighting: Lighting,
+    pub shadow: ShadowSettings,
+    // シーンのマテリアルの編集用のコピー
+    pub materials: Vec<Material>,
+    // マテリアルが割り当てられていないメッシュ用
+    pub default_material: Material,
+    scene_name: String,
+    // 読み込んだシーンのファイル (組み込みのシーンやドロップされたファイルなら None)
+    scene_path: Option<String>,
+    // ネイティブで読み込むファイルのパス
+    path: String,
+    load_error: Option<String>,
+}
+
+// 保存する Model3dParams の状態
+// シーンはファイルのパスだけを残して、復元するときに読み込み直す
+#[derive(Serialize, Deserialize)]
+struct Model3dState {
+    camera: OrbitCamera,
+    lighting: Lighting,
+    shadow: ShadowSettings,
+    materials: Vec<Material>,
+    default_material: Material,
+    scene_path: Option<String>,
+}
+
+impl Default for Model3dParams {
+    fn default() -> Self {
+        Self {
+            camera: OrbitCamera::default(),
+            scene: default_scene(),
+            lighting: Lighting::default(),
+            shadow: ShadowSettings::default(),
+            materials: Vec::new(),
+            default_material: Material::default(),
+            scene_name: "torus.usda".to_string(),
+            scene_path: None,
+            path: String::new(),
+            load_error: None,
+        }
+    }
+}
+
+impl Model3dParams {
+    pub fn set_scene(&mut self, name: &str, scene: Scene) {
+        if let Some((min, max)) = scene.bounds() {
+            self.camera.frame(min, max);
+        }
+        self.materials = scene.materials.clone();
+        self.scene = Arc::new(scene);
+        self.scene_name = name.to_string();
+        self.load_error = None;
+    }
+
+    pub fn material(&self, index: Option<usize>) -> &Material {
+        index
+            .and_then(|index| self.materials.get(index))
+            .unwrap_or(&self.default_material)
+    }
+
+    fn load_from_bytes(&mut self, name: &str, bytes: &[u8]) {
+        match Scene::from_bytes(name, bytes) {
+            Ok(scene) => {
+                self.set_scene(name, scene);
+                self.scene_path = None;
+            }
+            Err(error) => self.load_error = Some(format!("{name}: {error}")),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_from_path(&mut self, path: &std::path::Path) {
+        let name = path.to_string_lossy();
+        match Scene::load(path) {
+            Ok(scene) => {
+                self.set_scene(&name, scene);
+                self.scene_path = Some(name.into_owned());
+            }
+            Err(error) => self.load_error = Some(format!("{name}: {error}")),
+        }
+    }
+}
+
+impl DemoParams for Model3dParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn save_state(&self) -> Option<DemoState> {
+        let state = Model3dState {
+            camera: self.camera,
+            lighting: self.lighting.clone(),
+            shadow: self.shadow,
+            materials: self.materials.clone(),
+            default_material: self.default_material.clone(),
+            scene_path: self.scene_path.clone(),
+        };
+        DemoState::new(&state, &[]).ok()
+    }
+
+    fn load_state(&mut self, state: DemoState) -> Result<(), StateError> {
+        let state: Model3dState = state.decode(&[])?;
+        // 元に戻すたびにも呼ばれるので、シーンのファイルが同じなら読み込み直さない
+        if state.scene_path != self.scene_path {
+            *self = Self::default();
+            // 読み込めなければ組み込みのシーンのままエラーを表示する
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(path) = &state.scene_path {
+                self.load_from_path(std::path::Path::new(path));
+            }
+        }
+        // シーンのファイルが変わっていたらマテリアルは読み込んだものを使う
+        if state.materials.len() == self.materials.len() {
+            self.materials = state.materials;
+        }
+        self.camera = state.camera;
+        self.lighting = state.lighting;
+        self.shadow = state.shadow;
+        self.default_material = state.default_material;
+        Ok(())
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("Scene: {}", self.scene_name));
+        ui.label(format!(
+            "Meshes: {}, Triangles: {}, Materials: {}",
+            self.scene.meshes.len(),
+            self.scene.triangle_count(),
+            self.scene.materials.len()
+        ));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Load").clicked() {
+                let path = std::path::PathBuf::from(&self.path);
+                self.load_from_path(&path);
+            }
+        });
+        ui.label("Drop a .usda, .gltf, .glb or .obj file on the window to load it");
+        if ui.button("Load default").clicked() {
+            self.scene = default_scene();
+            self.materials = self.scene.materials.clone();
+            self.scene_name = "torus.usda".to_string();
+            self.scene_path = None;
+            self.load_error = None;
+            self.camera = OrbitCamera::default();
+        }
+        if let Some(error) = &self.load_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.separator();
+        self.camera.draw_properties(ui);
+        if ui.button("Reset camera").clicked() {
+            match self.scene.bounds() {
+                Some((min, max)) => self.camera.frame(min, max),
+                None => self.camera = OrbitCamera::default(),
+            }
+        }
+
+        ui.separator();
+        ui.collapsing("Lights", |ui| {
+            self.lighting.draw_editor(ui);
+        });
+        ui.collapsing("Shadows", |ui| {
+            ui.label("The first enabled directional light casts shadows");
+            self.shadow.draw_editor(ui);
+        });
+        ui.collapsing("Materials", |ui| {
+            for (index, material) in self.materials.iter_mut().enumerate() {
+                let name = if material.name.is_empty() {
+                    format!("Material {index}")
+                } else {
+                    material.name.clone()
+                };
+                ui.push_id(index, |ui| {
+                    ui.collapsing(name, |ui| {
+                        draw_material_editor(material, ui);
+                    });
+                });
+            }
+            ui.collapsing("Default", |ui| {
+                draw_material_editor(&mut self.default_material, ui);
+            });
+            if ui.button("Restore materials").clicked() {
+                self.materials = self.scene.materials.clone();
+                self.default_material = Material::default();
+            }
+        });
+    }
+
+    fn interact(&mut self, response: &egui::Response) {
+        self.camera.interact(response);
+
+        // ネイティブはパス、Web は中身が渡される
+        let dropped_files = response.ctx.input(|input| input.raw.dropped_files.clone());
+        for file in dropped_files {
+            if let Some(bytes) = &file.bytes {
+                self.load_from_bytes(&file.name, bytes);
+                continue;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(path) = &file.path {
+                self.load_from_path(path);
+            }
+        }
+    }
+}
+
+// model_3d.vs と model_3d.fs の View と同じレイアウト
+#[derive(Std140)]
+pub struct ViewUniform {
+    view_projection: [[f32; 4]; 4],
+    camera_position: [f32; 3],
+    light_count: u32,
+    ambient: [f32; 3],
+}
+
+assert_std140!(ViewUniformStd140, model_3d_vs::view, {
+    view_projection: U_VIEW_PROJECTION,
+    camera_position: U_CAMERA_POSITION,
+    light_count: U_LIGHT_COUNT,
+    ambient: U_AMBIENT,
+});
+assert_std140!(ViewUniformStd140, model_3d_fs::view, {
+    view_projection: U_VIEW_PROJECTION,
+    camera_position: U_CAMERA_POSITION,
+    light_count: U_LIGHT_COUNT,
+    ambient: U_AMBIENT,
+});
+
+impl ViewUniform {
+    pub fn new(params: &Model3dParams, aspect: f32) -> Self {
+        let pv = params.camera.view_projection_matrix(aspect);
+        Self {
+            view_projection: to_row_major(&pv),
+            camera_position: params.camera.eye(),
+            light_count: params
+                .lighting
+                .lights
+                .iter()
+                .filter(|light| light.enabled)
+                .take(MAX_LIGHTS)
+                .count() as u32,
+            ambient: params.lighting.ambient,
+        }
+    }
+}
+
+// model_3d.fs の Light と同じレイアウト
+#[derive(Std140, Clone, Copy, Default)]
+pub struct LightUniform {
+    position: [f32; 3],
+    kind: u32,
+    direction: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+assert_std140!(LightUniformStd140, model_3d_fs::light, {
+    position: POSITION,
+    kind: KIND,
+    direction: DIRECTION,
+    range: RANGE,
+    color: COLOR,
+    cos_inner: COS_INNER,
+    cos_outer: COS_OUTER,
+});
+
+// model_3d.fs の Lights と同じレイアウト
+#[derive(Std140)]
+pub struct LightsUniform {
+    lights: [LightUniform; MAX_LIGHTS],
+}
+
+assert_std140!(LightsUniformStd140, model_3d_fs::lights, { lights: U_LIGHTS });
+
+impl LightsUniform {
+    pub fn new(lighting: &Lighting) -> Self {
+        let mut lights = [LightUniform::default(); MAX_LIGHTS];
+        let enabled_lights = lighting.lights.iter().filter(|light| light.enabled);
+        for (uniform, light) in lights.iter_mut().zip(enabled_lights) {
+            *uniform = LightUniform {
+                position: light.position,
+                kind: match light.kind {
+                    LightKind::Directional => 0,
+                    LightKind::Point => 1,
+                    LightKind::Spot => 2,
+                },
+                direction: light.direction,
+                range: light.range,
+                color: light.color.map(|value| value * light.intensity),
+                cos_inner: light.inner_angle.min(light.outer_angle).to_radians().cos(),
+                cos_outer: light.outer_angle.to_radians().cos(),
+            };
+        }
+        Self { lights }
+    }
+}
+
+// model_3d.vs と model_3d.fs の Object と同じレイアウト
+#[derive(Std140)]
+pub struct ObjectUniform {
+    model: [[f32; 4]; 4],
+    // vec4 の配列と同じく 1 行ごとに 16 バイト
+    normal_matrix: [[f32; 3]; 3],
+    base_color: [f32; 4],
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+}
+
+assert_std140!(ObjectUniformStd140, model_3d_vs::object, {
+    model: U_MODEL,
+    normal_matrix: U_NORMAL_MATRIX,
+    base_color: U_BASE_COLOR,
+    emissive: U_EMISSIVE,
+    metallic: U_METALLIC,
+    roughness: U_ROUGHNESS,
+});
+assert_std140!(ObjectUniformStd140, shadow_vs::object, { model: U_MODEL });
+
+impl ObjectUniform {
+    pub fn new(mesh: &Mesh, material: &Material) -> Self {
+        let normal_matrix = mesh.normal_matrix();
+        Self {
+            model: to_row_major(&mesh.transform),
+            normal_matrix: std::array::from_fn(|row| {
+                std::array::from_fn(|column| normal_matrix[(row, column)])
+            }),
+            base_color: material.base_color,
+            emissive: material.emissive,
+            metallic: material.metallic,
+            roughness: material.roughness,
+        }
+    }
+}
+
+// Column-Major の行列を、シェーダーの vec4 u_Matrix[4] に入れる行ごとのベクトルにする
+fn to_row_major(matrix: &nalgebra_glm::Mat4) -> [[f32; 4]; 4] {
+    std::array::from_fn(|row| std::array::from_fn(|column| matrix[(row, column)]))
+}
+
+// メッシュ 1 つぶんの描画範囲
+struct MeshDraw {
+    indices: Range<u32>,
+    base_vertex: i32,
+}
+
+// model_3d.fs をコンパイルし分ける組み合わせ
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    shadows: bool,
+}
+
+impl PipelineKey {
+    fn defines(&self) -> [(&'static str, &'static str); 1] {
+        [("SHADOWS", if self.shadows { "1" } else { "0" })]
+    }
 }
 
 pub struct Model3d<'a> {
-    render_pipeline: wgpu::RenderPipeline,
+    render_pipelines: PipelineCache<PipelineKey>,
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex_shader_module: wgpu::ShaderModule,
+    target_format: wgpu::TextureFormat,
+    pipeline_key: PipelineKey,
     bind_group: wgpu::BindGroup,
+    object_bind_group_layout: wgpu::BindGroupLayout,
+    object_bind_group: wgpu::BindGroup,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
-    #[allow(dead_code)]
-    constant_buffer: wgpu::Buffer,
+    view_buffer: wgpu::Buffer,
+    light_buffer: wgpu::Buffer,
+    // メッシュごとの ObjectUniform を object_stride 間隔で並べる
+    object_buffer: wgpu::Buffer,
+    object_stride: wgpu::BufferAddress,
+    mesh_draws: Vec<MeshDraw>,
+    shadow_renderer: ShadowRenderer,
+    // アップロード済みのシーン
+    scene: Arc<Scene>,
+    scene_bounds: Option<([f32; 3], [f32; 3])>,
+    // 描画先の幅 / 高さ
+    aspect: f32,
     _marker: std::marker::PhantomData<&'a ()>,
 }
 
 impl<'a> Model3d<'a> {
     pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
-        let torus_usd =
-            usd_rs::serializer::from_str(include_str!("../resources/models/torus.usda")).unwrap();
+        let vertex_shader_module = create_shader_module(device, "model_3d.vs");
 
-        let vertex_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("model_3d.vs.wgsl"))),
-        });
-        let pixel_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("model_3d.fs.wgsl"))),
-        });
+        // 影ありのシェーダーがすべてのリソースを使う
+        let pixel_shader = shader_variant("model_3d.fs", &PipelineKey { shadows: true }.defines());
+        let bind_group_layout = create_bind_group_layout(device, &["model_3d.vs", pixel_shader], 0);
+        // メッシュごとの ObjectUniform を動的オフセットで切り替える
+        // シャドウマップを描くときも同じものを使う
+        let object_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &with_dynamic_offsets(bind_group_layout_entries(
+                    &["model_3d.vs", pixel_shader, "shadow.vs"],
+                    1,
+                )),
+            });
+        let shadow_renderer = ShadowRenderer::new(device, target_format, &object_bind_group_layout);
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            layout: Some(
-                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[&device.create_bind_group_layout(
-                        &wgpu::BindGroupLayoutDescriptor {
-                            label: None,
-                            entries: &[wgpu::BindGroupLayoutEntry {
-                                binding: 0,
-                                visibility: wgpu::ShaderStages::VERTEX,
-                                ty: wgpu::BindingType::Buffer {
-                                    ty: wgpu::BufferBindingType::Uniform,
-                                    has_dynamic_offset: false,
-                                    min_binding_size: None,
-                                },
-                                count: None,
-                            }],
-                        },
-                    )],
-                    push_constant_ranges: &[],
-                }),
-            ),
-            vertex: wgpu::VertexState {
-                module: &vertex_shader_module,
-                entry_point: "main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: (std::mem::size_of::<f32>() * 6) as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x3,
-                            offset: 0,
-                            shader_location: 0,
-                        },
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x3,
-                            offset: (std::mem::size_of::<f32>() * 3) as wgpu::BufferAddress,
-                            shader_location: 1,
-                        },
-                    ],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &pixel_shader_module,
-                entry_point: "main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: target_format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: Default::default(),
-            multiview: Default::default(),
-        });
-
-        let point_and_normal = torus_usd.definitions()[0]
-            .properties
-            .iter()
-            .filter_map(|p| match p.property {
-                PropertyType::Points(_) => Some(&p.property),
-                PropertyType::Normals(_) => Some(&p.property),
-                _ => None,
-            })
-            .collect::<Vec<&PropertyType>>();
-        let (points, normals) = match &point_and_normal[0] {
-            PropertyType::Points(points) => match &point_and_normal[1] {
-                PropertyType::Normals(normals) => (points, normals),
-                _ => panic!(),
-            },
-            PropertyType::Normals(normals) => match &point_and_normal[1] {
-                PropertyType::Points(points) => (points, normals),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        };
-        let mut vertex_data = Vec::default();
-        for index in 0..points.len() {
-            let point = &points[index];
-            // なぜか法線が 3 つ同じのが出力されてる
-            let normal = &normals[3 * index];
-            vertex_data.push(point[0]);
-            vertex_data.push(point[1]);
-            vertex_data.push(point[2]);
-            vertex_data.push(normal[0]);
-            vertex_data.push(normal[1]);
-            vertex_data.push(normal[2]);
-        }
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&vertex_data),
-            usage: wgpu::BufferUsages::VERTEX,
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &object_bind_group_layout,
+                shadow_renderer.bind_group_layout(),
+            ],
+            push_constant_ranges: &[],
         });
-
-        let index_data = torus_usd.definitions()[0]
-            .properties
-            .iter()
-            .find_map(|property| match &property.property {
-                PropertyType::FaceVertexIndicies(data) => Some(data),
-                _ => None,
-            })
-            .unwrap()
-            .iter()
-            .map(|d| *d as u32)
-            .collect::<Vec<u32>>();
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&index_data),
-            usage: wgpu::BufferUsages::INDEX,
+        let pipeline_key = PipelineKey { shadows: true };
+        let mut render_pipelines = PipelineCache::new();
+        render_pipelines.prepare(pipeline_key, |key| {
+            create_render_pipeline(
+                device,
+                &pipeline_layout,
+                &vertex_shader_module,
+                target_format,
+                key,
+            )
         });
 
-        let projection_matrix =
-            nalgebra_glm::perspective_lh_zo(1.0f32, 60f32.to_radians(), 0.1, 100.0);
-        let view_matrix = nalgebra_glm::look_at_lh(
-            &nalgebra_glm::Vec3::new(2.0, 2.0, 1.5),
-            &nalgebra_glm::Vec3::new(0.0, 0.0, 0.0),
-            &nalgebra_glm::Vec3::new(0.0, 0.0, 1.0),
-        );
-
-        let pv = projection_matrix * view_matrix;
-        // Column-Major を Row-Major にするための転置
-        let pv = pv.transpose();
-        let constant_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let params = Model3dParams::default();
+        let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            // contents: bytemuck::bytes_of(&Model3dParams {
-            //     mvp: Default::default(),
-            // }),
-            contents: bytemuck::cast_slice(pv.as_slice()),
-            usage: wgpu::BufferUsages::UNIFORM,
+            contents: bytemuck::bytes_of(&ViewUniform::new(&params, 1.0).std140()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            contents: bytemuck::bytes_of(&LightsUniform::new(&params.lighting).std140()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
             layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: constant_buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: view_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: light_buffer.as_entire_binding(),
+                },
+            ],
         });
 
+        // 動的オフセットはデバイスのアラインメントに揃える
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let object_size = std::mem::size_of::<ObjectUniformStd140>() as wgpu::BufferAddress;
+        let object_stride = object_size.div_ceil(alignment) * alignment;
+
+        let scene = params.scene.clone();
+        let (vertex_buffer, index_buffer, mesh_draws) = create_mesh_buffers(device, &scene);
+        let (object_buffer, object_bind_group) = create_object_buffer(
+            device,
+            &object_bind_group_layout,
+            object_stride,
+            mesh_draws.len(),
+        );
+
         Self {
-            render_pipeline,
+            render_pipelines,
+            pipeline_layout,
+            vertex_shader_module,
+            target_format,
+            pipeline_key,
             bind_group,
+            object_bind_group_layout,
+            object_bind_group,
             vertex_buffer,
             index_buffer,
-            constant_buffer,
+            view_buffer,
+            light_buffer,
+            object_buffer,
+            object_stride,
+            mesh_draws,
+            shadow_renderer,
+            scene_bounds: scene.bounds(),
+            scene,
+            aspect: 1.0,
             _marker: std::marker::PhantomData,
         }
     }
 
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, params: &Model3dParams) {
+        self.pipeline_key = PipelineKey {
+            shadows: params.shadow.enabled,
+        };
+        self.render_pipelines.prepare(self.pipeline_key, |key| {
+            create_render_pipeline(
+                device,
+                &self.pipeline_layout,
+                &self.vertex_shader_module,
+                self.target_format,
+                key,
+            )
+        });
+
+        if !Arc::ptr_eq(&self.scene, &params.scene) {
+            self.scene = params.scene.clone();
+            self.scene_bounds = self.scene.bounds();
+            (self.vertex_buffer, self.index_buffer, self.mesh_draws) =
+                create_mesh_buffers(device, &self.scene);
+            (self.object_buffer, self.object_bind_group) = create_object_buffer(
+                device,
+                &self.object_bind_group_layout,
+                self.object_stride,
+                self.mesh_draws.len(),
+            );
+        }
+
+        queue.write_buffer(
+            &self.view_buffer,
+            0,
+            bytemuck::bytes_of(&ViewUniform::new(params, self.aspect).std140()),
+        );
+        queue.write_buffer(
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&LightsUniform::new(&params.lighting).std140()),
+        );
+
+        // 有効なライトのうち最初の平行光源が影を落とす
+        let caster = params
+            .lighting
+            .lights
+            .iter()
+            .filter(|light| light.enabled)
+            .take(MAX_LIGHTS)
+            .enumerate()
+            .find(|(_, light)| light.kind == LightKind::Directional)
+            .map(|(index, light)| ShadowCaster {
+                light_index: index as u32,
+                direction: light.direction,
+            });
+        self.shadow_renderer.update(
+            device,
+            queue,
+            &params.shadow,
+            &params.camera,
+            caster.as_ref(),
+            self.scene_bounds,
+        );
+
+        // マテリアルは毎フレーム編集されうるのでまとめて書き直す
+        let mut object_data = vec![0; self.object_stride as usize * self.scene.meshes.len()];
+        for (mesh, data) in self
+            .scene
+            .meshes
+            .iter()
+            .zip(object_data.chunks_exact_mut(self.object_stride as usize))
+        {
+            let uniform = ObjectUniform::new(mesh, params.material(mesh.material)).std140();
+            let bytes = bytemuck::bytes_of(&uniform);
+            data[..bytes.len()].copy_from_slice(bytes);
+        }
+        if !object_data.is_empty() {
+            queue.write_buffer(&self.object_buffer, 0, &object_data);
+        }
+    }
+
+    // シャドウマップを描く
+    pub fn encode(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        self.shadow_renderer.encode(
+            command_encoder,
+            &self.vertex_buffer,
+            &self.index_buffer,
+            &self.object_bind_group,
+            self.object_stride,
+            &self.mesh_draws,
+        );
+    }
+
     pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        render_pass.set_pipeline(&self.render_pipeline);
+        let Some(render_pipeline) = self.render_pipelines.get(self.pipeline_key) else {
+            return;
+        };
+        if self.mesh_draws.is_empty() {
+            return;
+        }
+
+        render_pass.set_pipeline(render_pipeline);
         render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(2, self.shadow_renderer.bind_group(), &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..3456, 0, 0..1);
+        for (index, mesh_draw) in self.mesh_draws.iter().enumerate() {
+            let offset = (self.object_stride * index as wgpu::BufferAddress) as u32;
+            render_pass.set_bind_group(1, &self.object_bind_group, &[offset]);
+            render_pass.draw_indexed(mesh_draw.indices.clone(), mesh_draw.base_vertex, 0..1);
+        }
+
+        self.shadow_renderer.draw_debug(render_pass);
+    }
+}
+
+impl<'a> Demo for Model3d<'a> {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self::new(device, target_format)
+    }
+
+    fn name(&self) -> &'static str {
+        "Model3d"
+    }
+
+    fn create_params(&self) -> Box<dyn DemoParams> {
+        Box::<Model3dParams>::default()
+    }
+
+    fn is_depth_required(&self) -> bool {
+        true
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+        self.shadow_renderer.resize(width, height);
+    }
+
+    fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams) {
+        let Some(params) = params.as_any().downcast_ref::<Model3dParams>() else {
+            return;
+        };
+        Model3d::update(self, device, queue, params);
     }
+
+    fn encode(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        Model3d::encode(self, command_encoder);
+    }
+
+    fn draw<'b>(&'b self, render_pass: &mut wgpu::RenderPass<'b>) {
+        Model3d::draw(self, render_pass);
+    }
+}
+
+// 全メッシュのローカル座標の頂点を 1 つのバッファーにまとめる
+// ワールド座標への変換はシェーダーで ObjectUniform の行列を使う
+fn create_mesh_buffers(
+    device: &wgpu::Device,
+    scene: &Scene,
+) -> (wgpu::Buffer, wgpu::Buffer, Vec<MeshDraw>) {
+    let mut vertex_data = Vec::new();
+    let mut index_data = Vec::new();
+    let mut mesh_draws = Vec::new();
+    for mesh in &scene.meshes {
+        let base_vertex = (vertex_data.len() / 6) as i32;
+        let first_index = index_data.len() as u32;
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            vertex_data.extend_from_slice(position);
+            vertex_data.extend_from_slice(normal);
+        }
+        index_data.extend_from_slice(&mesh.indices);
+        mesh_draws.push(MeshDraw {
+            indices: first_index..index_data.len() as u32,
+            base_vertex,
+        });
+    }
+
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&vertex_data),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&index_data),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    (vertex_buffer, index_buffer, mesh_draws)
+}
+
+fn create_object_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    stride: wgpu::BufferAddress,
+    mesh_count: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    // メッシュがなくてもバインドグループを作れるように最低 1 つぶん確保する
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: stride * mesh_count.max(1) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniformStd140>() as u64),
+            }),
+        }],
+    });
+    (buffer, bind_group)
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    vertex_shader_module: &wgpu::ShaderModule,
+    target_format: wgpu::TextureFormat,
+    key: PipelineKey,
+) -> wgpu::RenderPipeline {
+    let pixel_shader_module =
+        create_shader_module(device, shader_variant("model_3d.fs", &key.defines()));
+    // create_mesh_buffers で位置と法線を交互に並べる
+    let vertex_layout = VertexLayout::new("model_3d.vs", &[0, 1]);
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: vertex_shader_module,
+            entry_point: "main",
+            buffers: &[vertex_layout.buffer_layout(wgpu::VertexStepMode::Vertex)],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &pixel_shader_module,
+            entry_point: "main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: target_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: Default::default(),
+        multiview: Default::default(),
+    })
 }
@@ -0,0 +1,748 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use super::PipelineKey;
+use super::{to_row_major, MeshDraw};
+use crate::{
+    bind_group_layout_entries, create_bind_group_layout,
+    shaders::{assert_std140, create_shader_module, shader_variant, with_dynamic_offsets},
+    OrbitCamera, Std140,
+};
+
+// シェーダーの u_LightViewProjection に入るカスケードの数
+pub const MAX_CASCADES: usize = 4;
+
+const RESOLUTIONS: [u32; 4] = [512, 1024, 2048, 4096];
+
+// 平行光源 1 つぶんのシャドウマップの設定
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub resolution: u32,
+    // ライトから見た深度 [0, 1] での値
+    pub depth_bias: f32,
+    // 光が斜めに当たる面ほど大きくするバイアス
+    pub slope_bias: f32,
+    // PCF のカーネルの半径 (テクセル)
+    pub pcf_radius: u32,
+    // false ならシーン全体を 1 枚でおおう
+    pub cascaded: bool,
+    pub cascade_count: u32,
+    // 対数分割と均等分割の混ぜ具合 (1 で対数分割)
+    pub split_lambda: f32,
+    // カスケードでおおうカメラからの距離
+    pub max_distance: f32,
+    // シャドウマップを画面の左下に表示する
+    pub debug_cascade: Option<u32>,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 2048,
+            depth_bias: 0.002,
+            slope_bias: 0.004,
+            pcf_radius: 1,
+            cascaded: false,
+            cascade_count: 3,
+            split_lambda: 0.7,
+            max_distance: 50.0,
+            debug_cascade: None,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn draw_editor(&mut self, ui: &mut egui::Ui) {
+        use egui::{DragValue, Slider};
+
+        ui.checkbox(&mut self.enabled, "Enabled");
+        egui::ComboBox::from_label("Resolution")
+            .selected_text(self.resolution.to_string())
+            .show_ui(ui, |ui| {
+                for resolution in RESOLUTIONS {
+                    ui.selectable_value(&mut self.resolution, resolution, resolution.to_string());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("Bias");
+            ui.add(
+                DragValue::new(&mut self.depth_bias)
+                    .speed(0.0001)
+                    .clamp_range(0.0..=0.05),
+            );
+            ui.label("Slope");
+            ui.add(
+                DragValue::new(&mut self.slope_bias)
+                    .speed(0.0001)
+                    .clamp_range(0.0..=0.05),
+            );
+        });
+        ui.add(Slider::new(&mut self.pcf_radius, 0..=3).text("PCF radius"));
+
+        ui.checkbox(&mut self.cascaded, "Cascaded");
+        ui.add_enabled_ui(self.cascaded, |ui| {
+            ui.add(Slider::new(&mut self.cascade_count, 1..=MAX_CASCADES as u32).text("Cascades"));
+            ui.add(Slider::new(&mut self.split_lambda, 0.0..=1.0).text("Split lambda"));
+            ui.add(
+                Slider::new(&mut self.max_distance, 1.0..=1000.0)
+                    .text("Max distance")
+                    .logarithmic(true),
+            );
+        });
+
+        let mut is_debug_view = self.debug_cascade.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut is_debug_view, "Show shadow map");
+            let mut cascade = self.debug_cascade.unwrap_or_default();
+            if is_debug_view && self.cascaded {
+                ui.add(Slider::new(&mut cascade, 0..=self.cascade_count() - 1));
+            }
+            self.debug_cascade = is_debug_view.then_some(cascade.min(self.cascade_count() - 1));
+        });
+    }
+
+    pub fn cascade_count(&self) -> u32 {
+        if self.cascaded {
+            self.cascade_count.clamp(1, MAX_CASCADES as u32)
+        } else {
+            1
+        }
+    }
+}
+
+// 影を落とす平行光源
+pub struct ShadowCaster {
+    // model_3d.fs の u_Lights の番号
+    pub light_index: u32,
+    pub direction: [f32; 3],
+}
+
+// shadow.vs の Cascade と同じレイアウト
+#[derive(Std140)]
+struct CascadeUniform {
+    light_view_projection: [[f32; 4]; 4],
+}
+
+assert_std140!(CascadeUniformStd140, shadow_vs::cascade, {
+    light_view_projection: U_LIGHT_VIEW_PROJECTION,
+});
+
+// model_3d.fs の Shadow と同じレイアウト
+#[derive(Std140)]
+struct ShadowUniform {
+    // カスケードごとに 4 行ずつ
+    light_view_projection: [[f32; 4]; 4 * MAX_CASCADES],
+    // 各カスケードがおおうビュー空間の深度の上限 (vec4 なので MAX_CASCADES は 4 まで)
+    cascade_splits: [f32; 4],
+    camera_forward: [f32; 3],
+    cascade_count: u32,
+    light_direction: [f32; 3],
+    // MAX_LIGHTS なら影なし
+    shadow_light: u32,
+    depth_bias: f32,
+    slope_bias: f32,
+    pcf_radius: i32,
+    debug_cascade: i32,
+    resolution: u32,
+}
+
+assert_std140!(ShadowUniformStd140, model_3d_fs::shadow, {
+    light_view_projection: U_LIGHT_VIEW_PROJECTION,
+    cascade_splits: U_CASCADE_SPLITS,
+    camera_forward: U_CAMERA_FORWARD,
+    cascade_count: U_CASCADE_COUNT,
+    light_direction: U_LIGHT_DIRECTION,
+    shadow_light: U_SHADOW_LIGHT,
+    depth_bias: U_DEPTH_BIAS,
+    slope_bias: U_SLOPE_BIAS,
+    pcf_radius: U_PCF_RADIUS,
+    debug_cascade: U_DEBUG_CASCADE,
+    resolution: U_RESOLUTION,
+});
+
+pub struct ShadowRenderer {
+    pipeline: wgpu::RenderPipeline,
+    debug_pipeline: wgpu::RenderPipeline,
+    // カスケードごとのライトの行列を cascade_stride 間隔で並べる
+    cascade_buffer: wgpu::Buffer,
+    cascade_stride: wgpu::BufferAddress,
+    cascade_bind_group: wgpu::BindGroup,
+    shadow_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    debug_bind_group_layout: wgpu::BindGroupLayout,
+    debug_bind_group: wgpu::BindGroup,
+    comparison_sampler: wgpu::Sampler,
+    texture: wgpu::Texture,
+    // カスケードごとの描画先
+    layer_views: Vec<wgpu::TextureView>,
+    resolution: u32,
+    // 0 ならシャドウパスを描かない
+    cascade_count: u32,
+    is_debug_view: bool,
+    // カスケードを分割するメインカメラの描画先の幅 / 高さ
+    aspect: f32,
+}
+
+impl ShadowRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        object_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let vertex_shader_module = create_shader_module(device, "shadow.vs");
+        let debug_vertex_shader_module = create_shader_module(device, "shadow_debug.vs");
+        let debug_pixel_shader_module = create_shader_module(device, "shadow_debug.fs");
+
+        // カスケードごとの行列を動的オフセットで切り替える
+        let cascade_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &with_dynamic_offsets(bind_group_layout_entries(&["shadow.vs"], 0)),
+            });
+        // メインパスの set = 2
+        let shadow_pixel_shader =
+            shader_variant("model_3d.fs", &PipelineKey { shadows: true }.defines());
+        let bind_group_layout = create_bind_group_layout(device, &[shadow_pixel_shader], 2);
+        // 比較せずに深度をそのまま読む
+        // shadow_debug.fs のサンプラーは texelFetch では使わないのでバインドしない
+        let debug_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&cascade_bind_group_layout, object_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader_module,
+                entry_point: "main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: (std::mem::size_of::<f32>() * 6) as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Float32x3,
+                        offset: 0,
+                        shader_location: 0,
+                    }],
+                }],
+            },
+            // 深度だけ書く
+            fragment: None,
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: Default::default(),
+            multiview: Default::default(),
+        });
+
+        let debug_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&debug_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &debug_vertex_shader_module,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &debug_pixel_shader_module,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // メインパスの深度バッファーには触らずに上に重ねる
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: Default::default(),
+            multiview: Default::default(),
+        });
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let matrix_size = std::mem::size_of::<CascadeUniformStd140>() as wgpu::BufferAddress;
+        let cascade_stride = matrix_size.div_ceil(alignment) * alignment;
+        let cascade_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: cascade_stride * MAX_CASCADES as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let cascade_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &cascade_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &cascade_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(matrix_size),
+                }),
+            }],
+        });
+
+        let shadow_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&ShadowUniform::disabled().std140()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // 参照値のほうが近ければ 1 (光が当たる)
+        let comparison_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let resolution = ShadowSettings::default().resolution;
+        let (texture, layer_views) = create_shadow_map(device, resolution);
+        let (bind_group, debug_bind_group) = create_bind_groups(
+            device,
+            &bind_group_layout,
+            &debug_bind_group_layout,
+            &shadow_buffer,
+            &texture,
+            &comparison_sampler,
+        );
+
+        Self {
+            pipeline,
+            debug_pipeline,
+            cascade_buffer,
+            cascade_stride,
+            cascade_bind_group,
+            shadow_buffer,
+            bind_group_layout,
+            bind_group,
+            debug_bind_group_layout,
+            debug_bind_group,
+            comparison_sampler,
+            texture,
+            layer_views,
+            resolution,
+            cascade_count: 0,
+            is_debug_view: false,
+            aspect: 1.0,
+        }
+    }
+
+    // メインのパイプラインの set = 2 に使う
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+    }
+
+    // scene_bounds はワールド座標の (最小, 最大)
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        settings: &ShadowSettings,
+        camera: &OrbitCamera,
+        caster: Option<&ShadowCaster>,
+        scene_bounds: Option<([f32; 3], [f32; 3])>,
+    ) {
+        if settings.resolution != self.resolution {
+            self.resolution = settings.resolution;
+            (self.texture, self.layer_views) = create_shadow_map(device, self.resolution);
+            (self.bind_group, self.debug_bind_group) = create_bind_groups(
+                device,
+                &self.bind_group_layout,
+                &self.debug_bind_group_layout,
+                &self.shadow_buffer,
+                &self.texture,
+                &self.comparison_sampler,
+            );
+        }
+
+        let (Some(caster), Some(scene_bounds), true) = (caster, scene_bounds, settings.enabled)
+        else {
+            self.cascade_count = 0;
+            self.is_debug_view = false;
+            queue.write_buffer(
+                &self.shadow_buffer,
+                0,
+                bytemuck::bytes_of(&ShadowUniform::disabled().std140()),
+            );
+            return;
+        };
+
+        let direction = glm::Vec3::from(caster.direction);
+        let direction = if direction.norm() > 0.0 {
+            direction.normalize()
+        } else {
+            -glm::Vec3::z()
+        };
+        let (matrices, splits) = compute_cascades(
+            settings,
+            camera,
+            self.aspect,
+            direction,
+            scene_bounds,
+            self.resolution,
+        );
+
+        let mut cascade_data = vec![0; (self.cascade_stride as usize) * MAX_CASCADES];
+        let mut light_view_projection = [[0.0; 4]; 4 * MAX_CASCADES];
+        for (index, matrix) in matrices.iter().enumerate() {
+            let matrix = to_row_major(matrix);
+            let uniform = CascadeUniform {
+                light_view_projection: matrix,
+            }
+            .std140();
+            let bytes = bytemuck::bytes_of(&uniform);
+            let offset = self.cascade_stride as usize * index;
+            cascade_data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            light_view_projection[index * 4..(index + 1) * 4].copy_from_slice(&matrix);
+        }
+        queue.write_buffer(&self.cascade_buffer, 0, &cascade_data);
+
+        let camera_forward = (glm::Vec3::from(camera.target) - glm::Vec3::from(camera.eye()))
+            .try_normalize(0.0)
+            .unwrap_or_else(glm::Vec3::x);
+        let uniform = ShadowUniform {
+            light_view_projection,
+            cascade_splits: splits,
+            camera_forward: camera_forward.into(),
+            cascade_count: matrices.len() as u32,
+            light_direction: direction.into(),
+            shadow_light: caster.light_index,
+            depth_bias: settings.depth_bias,
+            slope_bias: settings.slope_bias,
+            pcf_radius: settings.pcf_radius as i32,
+            debug_cascade: settings.debug_cascade.map_or(-1, |cascade| cascade as i32),
+            resolution: self.resolution,
+        };
+        queue.write_buffer(
+            &self.shadow_buffer,
+            0,
+            bytemuck::bytes_of(&uniform.std140()),
+        );
+
+        self.cascade_count = matrices.len() as u32;
+        self.is_debug_view = settings.debug_cascade.is_some();
+    }
+
+    // カスケードごとにメッシュの深度を描く
+    pub fn encode(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        vertex_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer,
+        object_bind_group: &wgpu::BindGroup,
+        object_stride: wgpu::BufferAddress,
+        mesh_draws: &[MeshDraw],
+    ) {
+        for (cascade, layer_view) in self
+            .layer_views
+            .iter()
+            .take(self.cascade_count as usize)
+            .enumerate()
+        {
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: layer_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            if mesh_draws.is_empty() {
+                continue;
+            }
+
+            render_pass.set_pipeline(&self.pipeline);
+            let cascade_offset = (self.cascade_stride * cascade as wgpu::BufferAddress) as u32;
+            render_pass.set_bind_group(0, &self.cascade_bind_group, &[cascade_offset]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            for (index, mesh_draw) in mesh_draws.iter().enumerate() {
+                let offset = (object_stride * index as wgpu::BufferAddress) as u32;
+                render_pass.set_bind_group(1, object_bind_group, &[offset]);
+                render_pass.draw_indexed(mesh_draw.indices.clone(), mesh_draw.base_vertex, 0..1);
+            }
+        }
+    }
+
+    // メインパスの最後に重ねる
+    pub fn draw_debug<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if !self.is_debug_view {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.debug_pipeline);
+        render_pass.set_bind_group(0, &self.debug_bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+impl ShadowUniform {
+    fn disabled() -> Self {
+        Self {
+            light_view_projection: [[0.0; 4]; 4 * MAX_CASCADES],
+            cascade_splits: [0.0; 4],
+            camera_forward: [1.0, 0.0, 0.0],
+            cascade_count: 0,
+            light_direction: [0.0, 0.0, -1.0],
+            shadow_light: crate::MAX_LIGHTS as u32,
+            depth_bias: 0.0,
+            slope_bias: 0.0,
+            pcf_radius: 0,
+            debug_cascade: -1,
+            resolution: 1,
+        }
+    }
+}
+
+fn create_shadow_map(
+    device: &wgpu::Device,
+    resolution: u32,
+) -> (wgpu::Texture, Vec<wgpu::TextureView>) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: resolution,
+            height: resolution,
+            depth_or_array_layers: MAX_CASCADES as u32,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth32Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let layer_views = (0..MAX_CASCADES as u32)
+        .map(|layer| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+    (texture, layer_views)
+}
+
+// (メインパス用, デバッグ表示用)
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    debug_layout: &wgpu::BindGroupLayout,
+    shadow_buffer: &wgpu::Buffer,
+    texture: &wgpu::Texture,
+    comparison_sampler: &wgpu::Sampler,
+) -> (wgpu::BindGroup, wgpu::BindGroup) {
+    let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: shadow_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(comparison_sampler),
+            },
+        ],
+    });
+    let debug_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: debug_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: shadow_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&texture_view),
+            },
+        ],
+    });
+    (bind_group, debug_bind_group)
+}
+
+// カスケードごとのライトのビュー射影行列と、そのカスケードがおおう深度の上限
+fn compute_cascades(
+    settings: &ShadowSettings,
+    camera: &OrbitCamera,
+    aspect: f32,
+    direction: glm::Vec3,
+    scene_bounds: ([f32; 3], [f32; 3]),
+    resolution: u32,
+) -> (Vec<glm::Mat4>, [f32; MAX_CASCADES]) {
+    let scene_min = glm::Vec3::from(scene_bounds.0);
+    let scene_max = glm::Vec3::from(scene_bounds.1);
+    let scene_center = (scene_min + scene_max) * 0.5;
+    let scene_radius = ((scene_max - scene_min).norm() * 0.5).max(0.01);
+
+    let mut splits = [f32::MAX; MAX_CASCADES];
+    if !settings.cascaded {
+        let matrix = light_view_projection(
+            direction,
+            scene_center,
+            scene_radius,
+            scene_center,
+            scene_radius,
+            resolution,
+        );
+        return (vec![matrix], splits);
+    }
+
+    // Practical Split Scheme
+    // 参考: Zhang et al., "Parallel-Split Shadow Maps for Large-scale Virtual Environments"
+    let count = settings.cascade_count();
+    let near = camera.near;
+    let far = camera.far.min(settings.max_distance).max(near * 1.01);
+    let mut matrices = Vec::new();
+    let mut begin = near;
+    for index in 0..count {
+        let ratio = (index + 1) as f32 / count as f32;
+        let logarithmic = near * (far / near).powf(ratio);
+        let uniform = near + (far - near) * ratio;
+        let end = settings.split_lambda * logarithmic + (1.0 - settings.split_lambda) * uniform;
+
+        let slice = OrbitCamera {
+            near: begin,
+            far: end,
+            ..*camera
+        };
+        let inverse = slice
+            .view_projection_matrix(aspect)
+            .try_inverse()
+            .unwrap_or_else(glm::Mat4::identity);
+        let corners = [-1.0, 1.0]
+            .into_iter()
+            .flat_map(|x| [-1.0, 1.0].into_iter().map(move |y| (x, y)))
+            .flat_map(|(x, y)| [0.0, 1.0].into_iter().map(move |z| (x, y, z)))
+            .map(|(x, y, z)| {
+                let corner = inverse * glm::vec4(x, y, z, 1.0);
+                corner.xyz() / corner.w
+            })
+            .collect::<Vec<_>>();
+        let center = corners.iter().sum::<glm::Vec3>() / corners.len() as f32;
+        // 球で囲むとカメラが回転してもシャドウマップの大きさが変わらない
+        let radius = corners
+            .iter()
+            .map(|corner| (corner - center).norm())
+            .fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        matrices.push(light_view_projection(
+            direction,
+            center,
+            radius,
+            scene_center,
+            scene_radius,
+            resolution,
+        ));
+        splits[index as usize] = end;
+        begin = end;
+    }
+    (matrices, splits)
+}
+
+// center を中心とする半径 radius の球を正射影でおおう
+// 球の外にある物体の影も落ちるように、シーン全体が入るところまでライトを下げる
+fn light_view_projection(
+    direction: glm::Vec3,
+    center: glm::Vec3,
+    radius: f32,
+    scene_center: glm::Vec3,
+    scene_radius: f32,
+    resolution: u32,
+) -> glm::Mat4 {
+    let up = if direction.z.abs() > 0.99 {
+        glm::Vec3::y()
+    } else {
+        glm::Vec3::z()
+    };
+    let back = radius + scene_radius + (scene_center - center).norm();
+    let eye = center - direction * back;
+    let view = glm::look_at_lh(&eye, &center, &up);
+    let mut projection = glm::ortho_lh_zo(-radius, radius, -radius, radius, 0.0, back + radius);
+
+    // カメラが動いたときに影のふちがちらつかないように原点をテクセルにそろえる
+    let half_resolution = resolution as f32 * 0.5;
+    let origin = projection * view * glm::vec4(0.0, 0.0, 0.0, 1.0);
+    let texel = origin.xy() * half_resolution;
+    let offset = (texel.map(f32::round) - texel) / half_resolution;
+    projection[(0, 3)] += offset.x;
+    projection[(1, 3)] += offset.y;
+
+    projection * view
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+// GPU にアップロードする 1D テクスチャーの幅
+pub const PALETTE_TEXTURE_WIDTH: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    // [0, 1]
+    pub position: f32,
+    pub color: [f32; 3],
+}
+
+// ユーザーが編集できるグラデーション
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    stops: Vec<ColorStop>,
+}
+
+impl Palette {
+    pub fn new(name: &str, stops: &[ColorStop]) -> Self {
+        let mut palette = Self {
+            name: name.to_string(),
+            stops: stops.to_vec(),
+        };
+        palette.sort_stops();
+        palette
+    }
+
+    pub fn presets() -> Vec<Palette> {
+        let stop = |position: f32, color: [f32; 3]| ColorStop { position, color };
+        vec![
+            Palette::new(
+                "Rainbow",
+                &[
+                    stop(0.0, [0.7, 0.07, 0.07]),
+                    stop(0.17, [0.7, 0.7, 0.07]),
+                    stop(0.33, [0.07, 0.7, 0.07]),
+                    stop(0.5, [0.07, 0.7, 0.7]),
+                    stop(0.67, [0.07, 0.07, 0.7]),
+                    stop(0.83, [0.7, 0.07, 0.7]),
+                    stop(1.0, [0.7, 0.07, 0.07]),
+                ],
+            ),
+            Palette::new(
+                "Grayscale",
+                &[stop(0.0, [0.0, 0.0, 0.0]), stop(1.0, [1.0, 1.0, 1.0])],
+            ),
+            Palette::new(
+                "Fire",
+                &[
+                    stop(0.0, [0.0, 0.0, 0.0]),
+                    stop(0.33, [1.0, 0.0, 0.0]),
+                    stop(0.67, [1.0, 1.0, 0.0]),
+                    stop(1.0, [1.0, 1.0, 1.0]),
+                ],
+            ),
+            Palette::new(
+                "Ultra Fractal",
+                &[
+                    stop(0.0, [0.0, 0.03, 0.39]),
+                    stop(0.16, [0.13, 0.42, 0.8]),
+                    stop(0.42, [0.93, 1.0, 1.0]),
+                    stop(0.64, [1.0, 0.67, 0.0]),
+                    stop(0.86, [0.0, 0.01, 0.0]),
+                    stop(1.0, [0.0, 0.03, 0.39]),
+                ],
+            ),
+        ]
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    // t は [0, 1] に折り返される
+    pub fn sample(&self, t: f32) -> [f32; 3] {
+        sample_sorted(&self.sorted_stops(), t)
+    }
+
+    // 1D テクスチャー用の RGBA8 の画素列
+    pub fn to_rgba8(&self, width: u32) -> Vec<u8> {
+        let stops = self.sorted_stops();
+        (0..width)
+            .flat_map(|x| {
+                let [r, g, b] = sample_sorted(&stops, (x as f32 + 0.5) / width as f32);
+                let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                [to_u8(r), to_u8(g), to_u8(b), 255]
+            })
+            .collect()
+    }
+
+    // 編集されたら true を返す
+    pub fn draw_editor(&mut self, ui: &mut egui::Ui) -> bool {
+        self.draw_preview(ui);
+
+        let mut is_changed = false;
+        let mut remove_index = None;
+        let is_removable = self.stops.len() > 2;
+        for (index, stop) in self.stops.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                is_changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut stop.position)
+                            .speed(0.005)
+                            .clamp_range(0.0..=1.0),
+                    )
+                    .changed();
+                is_changed |= ui.color_edit_button_rgb(&mut stop.color).changed();
+                if ui
+                    .add_enabled(is_removable, egui::Button::new("Remove"))
+                    .clicked()
+                {
+                    remove_index = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = remove_index {
+            self.stops.remove(index);
+            is_changed = true;
+        }
+        if ui.button("Add stop").clicked() {
+            self.add_stop();
+            is_changed = true;
+        }
+
+        // ドラッグ中に並び替えると操作中の行が入れ替わってしまうので離してから
+        if !ui.ctx().memory(|memory| memory.is_anything_being_dragged()) {
+            self.sort_stops();
+        }
+        is_changed
+    }
+
+    fn draw_preview(&self, ui: &mut egui::Ui) {
+        let stops = self.sorted_stops();
+        let (rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+        let count = 64;
+        let width = rect.width() / count as f32;
+        for index in 0..count {
+            let [r, g, b] = sample_sorted(&stops, (index as f32 + 0.5) / count as f32);
+            let min = egui::pos2(rect.left() + width * index as f32, rect.top());
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size(min, egui::vec2(width + 0.5, rect.height())),
+                0.0,
+                egui::Rgba::from_rgb(r, g, b),
+            );
+        }
+    }
+
+    // いちばん間隔が空いているところに追加する
+    fn add_stop(&mut self) {
+        let stops = self.sorted_stops();
+        let Some((begin, end)) = stops
+            .windows(2)
+            .map(|window| (window[0], window[1]))
+            .max_by(|a, b| {
+                let width_a = a.1.position - a.0.position;
+                let width_b = b.1.position - b.0.position;
+                width_a.total_cmp(&width_b)
+            })
+        else {
+            self.stops.push(ColorStop {
+                position: 1.0,
+                color: [1.0; 3],
+            });
+            return;
+        };
+
+        let position = (begin.position + end.position) * 0.5;
+        self.stops.push(ColorStop {
+            position,
+            color: self.sample(position),
+        });
+    }
+
+    fn sorted_stops(&self) -> Vec<ColorStop> {
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        stops
+    }
+
+    fn sort_stops(&mut self) {
+        self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+}
+
+// stops は position の昇順に並んでいること
+fn sample_sorted(stops: &[ColorStop], t: f32) -> [f32; 3] {
+    let t = t.rem_euclid(1.0);
+    let Some(first) = stops.first() else {
+        return [0.0; 3];
+    };
+    if t <= first.position {
+        return first.color;
+    }
+
+    for window in stops.windows(2) {
+        let (begin, end) = (window[0], window[1]);
+        if t <= end.position {
+            let width = end.position - begin.position;
+            let ratio = if width > 0.0 {
+                (t - begin.position) / width
+            } else {
+                1.0
+            };
+            return std::array::from_fn(|index| {
+                begin.color[index] + (end.color[index] - begin.color[index]) * ratio
+            });
+        }
+    }
+    stops.last().unwrap().color
+}
@@ -0,0 +1,410 @@
+mod collision;
+mod world;
+
+use std::{any::Any, mem::size_of};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    create_bind_group_layout,
+    shaders::{assert_std140, create_shader_module},
+    Demo, DemoParams, DemoState, StateError, Std140, VertexLayout,
+};
+
+pub use collision::{sweep_and_prune, Aabb, Contact};
+pub use world::{RigidBody, Shape, World, WorldSettings, MAX_BODY_COUNT};
+
+// 動く物体 + 床と壁
+const MAX_INSTANCE_COUNT: usize = MAX_BODY_COUNT + 5;
+
+const SPHERE_SLICES: u32 = 24;
+const SPHERE_STACKS: u32 = 16;
+
+pub struct PhysicsParams {
+    pub settings: WorldSettings,
+    pub world: World,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> Self {
+        let settings = WorldSettings::default();
+        Self {
+            settings,
+            world: World::new(&settings),
+        }
+    }
+}
+
+impl DemoParams for PhysicsParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    // 物体の状態は残さず、設定から作り直す
+    fn save_state(&self) -> Option<DemoState> {
+        DemoState::new(&self.settings, &[]).ok()
+    }
+
+    fn load_state(&mut self, state: DemoState) -> Result<(), StateError> {
+        self.settings = state.decode(&[])?;
+        self.world = World::new(&self.settings);
+        Ok(())
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        use egui::{DragValue, Slider};
+
+        let settings = &mut self.settings;
+        ui.horizontal(|ui| {
+            ui.label("Gravity");
+            for value in &mut settings.gravity {
+                ui.add(DragValue::new(value).speed(0.1));
+            }
+        });
+        ui.add(
+            Slider::new(&mut settings.timestep, 1.0 / 480.0..=1.0 / 30.0)
+                .text("Timestep")
+                .logarithmic(true),
+        );
+        ui.add(Slider::new(&mut settings.restitution, 0.0..=1.0).text("Restitution"));
+        ui.add(Slider::new(&mut settings.friction, 0.0..=1.0).text("Friction"));
+
+        // 物体の数とシードはリセットしたときに反映される
+        ui.separator();
+        ui.add(Slider::new(&mut settings.body_count, 1..=MAX_BODY_COUNT as u32).text("Bodies"));
+        ui.horizontal(|ui| {
+            ui.label("Seed");
+            ui.add(DragValue::new(&mut settings.seed));
+        });
+        if ui.button("Reset").clicked() {
+            self.world = World::new(&self.settings);
+        }
+
+        ui.separator();
+        ui.label(format!(
+            "Energy: {:.2}",
+            self.world.kinetic_energy() + self.world.potential_energy()
+        ));
+        ui.label(format!("Contacts: {}", self.world.contacts().len()));
+    }
+
+    fn advance(&mut self, dt: f32) {
+        self.world.set_settings(&self.settings);
+        self.world.update(dt);
+    }
+}
+
+#[derive(bytemuck::NoUninit, Clone, Copy)]
+#[repr(C)]
+struct BodyInstance {
+    translation: [f32; 4],
+    scale: [f32; 4],
+    color: [f32; 4],
+    // クォータニオン (x, y, z, w)
+    rotation: [f32; 4],
+}
+
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl Mesh {
+    fn new(device: &wgpu::Device, vertex_data: &[f32], index_data: &[u32]) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(vertex_data),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(index_data),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: index_data.len() as u32,
+        }
+    }
+
+    // 半径 1 の球
+    fn new_sphere(device: &wgpu::Device) -> Self {
+        let mut vertex_data = Vec::new();
+        for stack in 0..=SPHERE_STACKS {
+            let theta = std::f32::consts::PI * stack as f32 / SPHERE_STACKS as f32;
+            for slice in 0..=SPHERE_SLICES {
+                let phi = 2.0 * std::f32::consts::PI * slice as f32 / SPHERE_SLICES as f32;
+                let normal = [
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ];
+                vertex_data.extend_from_slice(&normal);
+                vertex_data.extend_from_slice(&normal);
+            }
+        }
+
+        let mut index_data = Vec::new();
+        for stack in 0..SPHERE_STACKS {
+            for slice in 0..SPHERE_SLICES {
+                let current = stack * (SPHERE_SLICES + 1) + slice;
+                let next = current + SPHERE_SLICES + 1;
+                index_data.extend_from_slice(&[current, current + 1, next]);
+                index_data.extend_from_slice(&[current + 1, next + 1, next]);
+            }
+        }
+        Self::new(device, &vertex_data, &index_data)
+    }
+
+    // 中心から各面までの距離が 1 の立方体
+    fn new_box(device: &wgpu::Device) -> Self {
+        let mut vertex_data = Vec::new();
+        let mut index_data = Vec::new();
+        for axis in 0..3 {
+            for sign in [1.0f32, -1.0] {
+                let mut normal = [0.0f32; 3];
+                normal[axis] = sign;
+                let u_axis = (axis + 1) % 3;
+                let v_axis = (axis + 2) % 3;
+
+                let base = (vertex_data.len() / 6) as u32;
+                for (u, v) in [(-1.0f32, -1.0f32), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                    let mut position = normal;
+                    position[u_axis] = u;
+                    position[v_axis] = v;
+                    vertex_data.extend_from_slice(&position);
+                    vertex_data.extend_from_slice(&normal);
+                }
+                index_data.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+        Self::new(device, &vertex_data, &index_data)
+    }
+}
+
+pub struct Physics<'a> {
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    sphere_mesh: Mesh,
+    box_mesh: Mesh,
+    instance_buffer: wgpu::Buffer,
+    constant_buffer: wgpu::Buffer,
+    box_count: u32,
+    sphere_count: u32,
+    // 描画先の幅 / 高さ
+    aspect: f32,
+    _marker: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Physics<'a> {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let vertex_shader_module = create_shader_module(device, "physics.vs");
+        let pixel_shader_module = create_shader_module(device, "physics.fs");
+
+        let bind_group_layout = create_bind_group_layout(device, &["physics.vs"], 0);
+        let mesh_layout = VertexLayout::new("physics.vs", &[0, 1]);
+        let instance_layout = VertexLayout::new("physics.vs", &[2, 3, 4, 5]);
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader_module,
+                entry_point: "main",
+                buffers: &[
+                    mesh_layout.buffer_layout(wgpu::VertexStepMode::Vertex),
+                    instance_layout.buffer_layout(wgpu::VertexStepMode::Instance),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &pixel_shader_module,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: Default::default(),
+            multiview: Default::default(),
+        });
+
+        let constant_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&ViewUniform::new(1.0).std140()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: constant_buffer.as_entire_binding(),
+            }],
+        });
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            size: (size_of::<BodyInstance>() * MAX_INSTANCE_COUNT) as u64,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            bind_group,
+            sphere_mesh: Mesh::new_sphere(device),
+            box_mesh: Mesh::new_box(device),
+            instance_buffer,
+            constant_buffer,
+            box_count: 0,
+            sphere_count: 0,
+            aspect: 1.0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, world: &World) {
+        let to_instance = |body: &RigidBody| {
+            let scale = match body.shape {
+                Shape::Sphere { radius } => [radius; 3],
+                Shape::Box { half_extents } => [half_extents.x, half_extents.y, half_extents.z],
+            };
+            BodyInstance {
+                translation: [body.position.x, body.position.y, body.position.z, 1.0],
+                scale: [scale[0], scale[1], scale[2], 1.0],
+                color: [body.color[0], body.color[1], body.color[2], 1.0],
+                rotation: body.orientation.coords.into(),
+            }
+        };
+
+        // 箱と球でメッシュが違うので、箱を前半、球を後半にまとめてインスタンス描画する
+        let mut instances = world
+            .bodies()
+            .iter()
+            .filter(|body| matches!(body.shape, Shape::Box { .. }))
+            .map(to_instance)
+            .collect::<Vec<BodyInstance>>();
+        let box_count = instances.len();
+        instances.extend(
+            world
+                .bodies()
+                .iter()
+                .filter(|body| matches!(body.shape, Shape::Sphere { .. }))
+                .map(to_instance),
+        );
+        instances.truncate(MAX_INSTANCE_COUNT);
+
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        queue.write_buffer(
+            &self.constant_buffer,
+            0,
+            bytemuck::bytes_of(&ViewUniform::new(self.aspect).std140()),
+        );
+        self.box_count = box_count.min(instances.len()) as u32;
+        self.sphere_count = instances.len() as u32 - self.box_count;
+    }
+
+    pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        for (mesh, instances) in [
+            (&self.box_mesh, 0..self.box_count),
+            (
+                &self.sphere_mesh,
+                self.box_count..self.box_count + self.sphere_count,
+            ),
+        ] {
+            if instances.is_empty() {
+                continue;
+            }
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.index_count, 0, instances);
+        }
+    }
+}
+
+impl<'a> Demo for Physics<'a> {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self::new(device, target_format)
+    }
+
+    fn name(&self) -> &'static str {
+        "Physics"
+    }
+
+    fn create_params(&self) -> Box<dyn DemoParams> {
+        Box::<PhysicsParams>::default()
+    }
+
+    fn is_depth_required(&self) -> bool {
+        true
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+    }
+
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams) {
+        let Some(params) = params.as_any().downcast_ref::<PhysicsParams>() else {
+            return;
+        };
+        Physics::update(self, queue, &params.world);
+    }
+
+    fn draw<'b>(&'b self, render_pass: &mut wgpu::RenderPass<'b>) {
+        Physics::draw(self, render_pass);
+    }
+}
+
+// physics.vs の View と同じレイアウト
+#[derive(Std140)]
+struct ViewUniform {
+    view_projection: [[f32; 4]; 4],
+}
+
+assert_std140!(ViewUniformStd140, physics_vs::view, {
+    view_projection: U_VIEW_PROJECTION,
+});
+
+impl ViewUniform {
+    fn new(aspect: f32) -> Self {
+        let projection_matrix =
+            nalgebra_glm::perspective_lh_zo(aspect, 45f32.to_radians(), 0.1, 100.0);
+        let view_matrix = nalgebra_glm::look_at_lh(
+            &nalgebra_glm::Vec3::new(0.0, 5.0, -9.0),
+            &nalgebra_glm::Vec3::new(0.0, 1.0, 0.0),
+            &nalgebra_glm::Vec3::new(0.0, 1.0, 0.0),
+        );
+        // シェーダーの vec4 u_ViewProjection[4] には行ごとに入れる
+        let pv = projection_matrix * view_matrix;
+        Self {
+            view_projection: std::array::from_fn(|row| {
+                std::array::from_fn(|column| pv[(row, column)])
+            }),
+        }
+    }
+}
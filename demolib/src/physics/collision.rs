@@ -0,0 +1,340 @@
+use nalgebra_glm::Vec3;
+
+use super::world::{RigidBody, Shape};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub a: usize,
+    pub b: usize,
+    // 接触点 (ワールド座標)
+    pub point: Vec3,
+    // a から b へ向かう単位ベクトル
+    pub normal: Vec3,
+    pub depth: f32,
+}
+
+// x 軸方向に Sweep and Prune して AABB が重なっているペアを列挙する
+pub fn sweep_and_prune(aabbs: &[Aabb]) -> Vec<(usize, usize)> {
+    let mut indices = (0..aabbs.len()).collect::<Vec<usize>>();
+    indices.sort_by(|a, b| aabbs[*a].min.x.total_cmp(&aabbs[*b].min.x).then(a.cmp(b)));
+
+    let mut pairs = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for index in indices {
+        let aabb = &aabbs[index];
+        active.retain(|other| aabbs[*other].max.x >= aabb.min.x);
+        for other in &active {
+            if aabbs[*other].overlaps(aabb) {
+                pairs.push(((*other).min(index), (*other).max(index)));
+            }
+        }
+        active.push(index);
+    }
+    pairs.sort();
+    pairs
+}
+
+// 面同士で接するときは接触点が複数になる
+pub fn collide(
+    a: usize,
+    b: usize,
+    body_a: &RigidBody,
+    body_b: &RigidBody,
+    contacts: &mut Vec<Contact>,
+) {
+    // (接触点, 法線, 深さ)
+    let points = match (&body_a.shape, &body_b.shape) {
+        (Shape::Sphere { radius: ra }, Shape::Sphere { radius: rb }) => {
+            sphere_sphere(&body_a.position, *ra, &body_b.position, *rb)
+                .into_iter()
+                .collect()
+        }
+        (Shape::Sphere { radius }, Shape::Box { half_extents }) => {
+            let cuboid = Cuboid::new(body_b, half_extents);
+            box_sphere(&cuboid, &body_a.position, *radius)
+                .map(|(point, normal, depth)| (point, -normal, depth))
+                .into_iter()
+                .collect()
+        }
+        (Shape::Box { half_extents }, Shape::Sphere { radius }) => {
+            let cuboid = Cuboid::new(body_a, half_extents);
+            box_sphere(&cuboid, &body_b.position, *radius)
+                .into_iter()
+                .collect()
+        }
+        (Shape::Box { half_extents: ha }, Shape::Box { half_extents: hb }) => {
+            box_box(&Cuboid::new(body_a, ha), &Cuboid::new(body_b, hb))
+        }
+    };
+    contacts.extend(points.into_iter().map(|(point, normal, depth)| Contact {
+        a,
+        b,
+        point,
+        normal,
+        depth,
+    }));
+}
+
+// 向きのある箱
+struct Cuboid {
+    center: Vec3,
+    // 物体の座標系の各軸 (ワールド座標)
+    axes: [Vec3; 3],
+    half_extents: Vec3,
+}
+
+impl Cuboid {
+    fn new(body: &RigidBody, half_extents: &Vec3) -> Self {
+        let rotation = body.rotation();
+        Self {
+            center: body.position,
+            axes: std::array::from_fn(|axis| rotation.column(axis).into_owned()),
+            half_extents: *half_extents,
+        }
+    }
+
+    // axis に射影したときの半分の長さ
+    fn projected_radius(&self, axis: &Vec3) -> f32 {
+        (0..3)
+            .map(|index| self.half_extents[index] * self.axes[index].dot(axis).abs())
+            .sum()
+    }
+
+    fn local(&self, point: &Vec3) -> Vec3 {
+        let delta = point - self.center;
+        Vec3::new(
+            delta.dot(&self.axes[0]),
+            delta.dot(&self.axes[1]),
+            delta.dot(&self.axes[2]),
+        )
+    }
+
+    fn world(&self, local: &Vec3) -> Vec3 {
+        self.center + self.axes[0] * local.x + self.axes[1] * local.y + self.axes[2] * local.z
+    }
+}
+
+fn sign(value: f32) -> f32 {
+    if value >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn sphere_sphere(pa: &Vec3, ra: f32, pb: &Vec3, rb: f32) -> Option<(Vec3, Vec3, f32)> {
+    let delta = pb - pa;
+    let distance = delta.norm();
+    let depth = ra + rb - distance;
+    if depth <= 0.0 {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON {
+        delta / distance
+    } else {
+        Vec3::y()
+    };
+    Some((pa + normal * (ra - 0.5 * depth), normal, depth))
+}
+
+// 法線は箱から球へ向かう
+fn box_sphere(cuboid: &Cuboid, sphere_position: &Vec3, radius: f32) -> Option<(Vec3, Vec3, f32)> {
+    let local = cuboid.local(sphere_position);
+    let half_extents = &cuboid.half_extents;
+    let closest = Vec3::new(
+        local.x.clamp(-half_extents.x, half_extents.x),
+        local.y.clamp(-half_extents.y, half_extents.y),
+        local.z.clamp(-half_extents.z, half_extents.z),
+    );
+
+    if closest != local {
+        let surface = cuboid.world(&closest);
+        let delta = sphere_position - surface;
+        let distance = delta.norm();
+        if distance >= radius {
+            return None;
+        }
+        let normal = delta / distance;
+        let depth = radius - distance;
+        return Some((surface - normal * (0.5 * depth), normal, depth));
+    }
+
+    // 球の中心が箱の内側にあるときは一番近い面から押し出す
+    let (axis, distance) = (0..3)
+        .map(|axis| (axis, half_extents[axis] - local[axis].abs()))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+    let normal = cuboid.axes[axis] * sign(local[axis]);
+    Some((*sphere_position, normal, distance + radius))
+}
+
+// 分離軸判定
+#[derive(Clone, Copy)]
+enum Feature {
+    FaceA(usize),
+    FaceB(usize),
+    Edges(usize, usize),
+}
+
+fn box_box(a: &Cuboid, b: &Cuboid) -> Vec<(Vec3, Vec3, f32)> {
+    let delta = b.center - a.center;
+    let separation =
+        |axis: &Vec3| a.projected_radius(axis) + b.projected_radius(axis) - delta.dot(axis).abs();
+
+    // めり込みが最も浅い軸を探す。どれかの軸で離れていれば接触しない
+    let mut face: Option<(f32, Vec3, Feature)> = None;
+    let faces = (0..3)
+        .map(|index| (a.axes[index], Feature::FaceA(index)))
+        .chain((0..3).map(|index| (b.axes[index], Feature::FaceB(index))));
+    for (axis, feature) in faces {
+        let depth = separation(&axis);
+        if depth <= 0.0 {
+            return Vec::new();
+        }
+        if face.is_none_or(|(best, ..)| depth < best) {
+            face = Some((depth, axis, feature));
+        }
+    }
+    let mut edge: Option<(f32, Vec3, Feature)> = None;
+    for i in 0..3 {
+        for j in 0..3 {
+            let axis = a.axes[i].cross(&b.axes[j]);
+            let length = axis.norm();
+            // 平行な辺の組は面の軸で判定済み
+            if length < 1.0e-4 {
+                continue;
+            }
+            let axis = axis / length;
+            let depth = separation(&axis);
+            if depth <= 0.0 {
+                return Vec::new();
+            }
+            if edge.is_none_or(|(best, ..)| depth < best) {
+                edge = Some((depth, axis, Feature::Edges(i, j)));
+            }
+        }
+    }
+
+    // 積み重なったときに接触点が揺れないように、辺同士がはっきり浅いときだけ辺を選ぶ
+    let (depth, axis, feature) = match (face, edge) {
+        (Some(face), Some(edge)) if edge.0 < 0.95 * face.0 - 0.005 => edge,
+        (Some(face), _) => face,
+        (None, _) => return Vec::new(),
+    };
+    let normal = axis * sign(delta.dot(&axis));
+    match feature {
+        Feature::FaceA(index) => clip_faces(a, b, index, &normal)
+            .into_iter()
+            .map(|(point, depth)| (point, normal, depth))
+            .collect(),
+        Feature::FaceB(index) => clip_faces(b, a, index, &-normal)
+            .into_iter()
+            .map(|(point, depth)| (point, normal, depth))
+            .collect(),
+        Feature::Edges(i, j) => vec![(edge_contact(a, b, i, j, &normal), normal, depth)],
+    }
+}
+
+// reference の面 axis に incident の最も向き合う面をクリップして、めり込んでいる頂点を接触点にする
+// normal は reference から incident へ向かう
+fn clip_faces(
+    reference: &Cuboid,
+    incident: &Cuboid,
+    axis: usize,
+    normal: &Vec3,
+) -> Vec<(Vec3, f32)> {
+    let face_normal = reference.axes[axis] * sign(reference.axes[axis].dot(normal));
+    let face_center = reference.center + face_normal * reference.half_extents[axis];
+
+    let incident_axis = (0..3)
+        .max_by(|i, j| {
+            let i = incident.axes[*i].dot(normal).abs();
+            let j = incident.axes[*j].dot(normal).abs();
+            i.total_cmp(&j)
+        })
+        .unwrap();
+    let incident_normal =
+        incident.axes[incident_axis] * -sign(incident.axes[incident_axis].dot(normal));
+    let center = incident.center + incident_normal * incident.half_extents[incident_axis];
+    let u = incident.axes[(incident_axis + 1) % 3] * incident.half_extents[(incident_axis + 1) % 3];
+    let v = incident.axes[(incident_axis + 2) % 3] * incident.half_extents[(incident_axis + 2) % 3];
+    let mut polygon = vec![
+        center + u + v,
+        center - u + v,
+        center - u - v,
+        center + u - v,
+    ];
+
+    for side in [(axis + 1) % 3, (axis + 2) % 3] {
+        for direction in [1.0, -1.0] {
+            let plane_normal = reference.axes[side] * direction;
+            let offset = plane_normal.dot(&reference.center) + reference.half_extents[side];
+            polygon = clip_polygon(&polygon, &plane_normal, offset);
+        }
+    }
+
+    polygon
+        .into_iter()
+        .filter_map(|point| {
+            let separation = face_normal.dot(&(point - face_center));
+            // 2 つの面の中間を接触点にする
+            (separation < 0.0).then(|| (point - face_normal * (0.5 * separation), -separation))
+        })
+        .collect()
+}
+
+// Sutherland–Hodgman。plane_normal · x <= offset の側を残す
+fn clip_polygon(polygon: &[Vec3], plane_normal: &Vec3, offset: f32) -> Vec<Vec3> {
+    let mut clipped = Vec::new();
+    for (index, start) in polygon.iter().enumerate() {
+        let end = &polygon[(index + 1) % polygon.len()];
+        let start_distance = plane_normal.dot(start) - offset;
+        let end_distance = plane_normal.dot(end) - offset;
+        if start_distance <= 0.0 {
+            clipped.push(*start);
+        }
+        if (start_distance < 0.0 && end_distance > 0.0)
+            || (start_distance > 0.0 && end_distance < 0.0)
+        {
+            let t = start_distance / (start_distance - end_distance);
+            clipped.push(start + (end - start) * t);
+        }
+    }
+    clipped
+}
+
+// a の辺 i と b の辺 j のうち互いに最も近いものの最近点の中間
+fn edge_contact(a: &Cuboid, b: &Cuboid, i: usize, j: usize, normal: &Vec3) -> Vec3 {
+    let mut point_a = a.center;
+    let mut point_b = b.center;
+    for k in 0..3 {
+        if k != i {
+            point_a += a.axes[k] * (a.half_extents[k] * sign(a.axes[k].dot(normal)));
+        }
+        if k != j {
+            point_b -= b.axes[k] * (b.half_extents[k] * sign(b.axes[k].dot(normal)));
+        }
+    }
+
+    // 2 直線 point_a + s da, point_b + t db の最近点
+    let (direction_a, direction_b) = (a.axes[i], b.axes[j]);
+    let r = point_a - point_b;
+    let cosine = direction_a.dot(&direction_b);
+    let (c, f) = (direction_a.dot(&r), direction_b.dot(&r));
+    let s =
+        ((cosine * f - c) / (1.0 - cosine * cosine)).clamp(-a.half_extents[i], a.half_extents[i]);
+    let t = (cosine * s + f).clamp(-b.half_extents[j], b.half_extents[j]);
+    ((point_a + direction_a * s) + (point_b + direction_b * t)) * 0.5
+}
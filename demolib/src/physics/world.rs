@@ -0,0 +1,524 @@
+use nalgebra_glm::{self as glm, Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::collision::{self, Aabb, Contact};
+use crate::random::Random;
+
+pub const MAX_BODY_COUNT: usize = 256;
+
+// 1 フレームで進める最大ステップ数。処理落ちしたときに追いつこうとして固まるのを防ぐ
+const MAX_SUB_STEPS: u32 = 8;
+
+const SOLVER_ITERATIONS: usize = 10;
+
+// 貫通の許容量と位置補正の割合
+const PENETRATION_SLOP: f32 = 0.005;
+const POSITION_CORRECTION: f32 = 0.4;
+
+const FLOOR_HALF_SIZE: f32 = 3.0;
+const WALL_HEIGHT: f32 = 0.6;
+const WALL_THICKNESS: f32 = 0.1;
+
+const SPAWN_GRID: usize = 4;
+// 傾けた箱の外接球が隣と重ならない間隔
+const SPAWN_SPACING: f32 = 1.25;
+
+const DENSITY: f32 = 1000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorldSettings {
+    pub gravity: [f32; 3],
+    // 固定タイムステップ (秒)
+    pub timestep: f32,
+    pub body_count: u32,
+    pub restitution: f32,
+    pub friction: f32,
+    pub seed: u64,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            gravity: [0.0, -9.8, 0.0],
+            timestep: 1.0 / 120.0,
+            body_count: 48,
+            restitution: 0.3,
+            friction: 0.4,
+            seed: 0,
+        }
+    }
+}
+
+// 箱の half_extents は物体の座標系での大きさ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Sphere { radius: f32 },
+    Box { half_extents: Vec3 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RigidBody {
+    pub shape: Shape,
+    pub position: Vec3,
+    // 物体の座標系からワールド座標への回転 (単位クォータニオン)
+    pub orientation: Quat,
+    pub velocity: Vec3,
+    // ワールド座標での角速度 (rad/s)
+    pub angular_velocity: Vec3,
+    // 0 のときは動かない物体
+    pub inverse_mass: f32,
+    // 物体の座標系での慣性テンソル (対角) の逆数。動かない物体は 0
+    pub inverse_inertia: Vec3,
+    pub color: [f32; 3],
+}
+
+impl RigidBody {
+    // 密度 (kg/m^3) が一様な物体。質量と慣性テンソルは形から決める
+    pub fn new(shape: Shape, position: Vec3, density: f32, color: [f32; 3]) -> Self {
+        let (mass, inertia) = match shape {
+            Shape::Sphere { radius } => {
+                let mass = density * 4.0 / 3.0 * std::f32::consts::PI * radius.powi(3);
+                (mass, Vec3::repeat(0.4 * mass * radius * radius))
+            }
+            Shape::Box { half_extents } => {
+                let mass = density * 8.0 * half_extents.x * half_extents.y * half_extents.z;
+                let square = half_extents.component_mul(&half_extents);
+                let inertia = Vec3::new(
+                    square.y + square.z,
+                    square.z + square.x,
+                    square.x + square.y,
+                ) * (mass / 3.0);
+                (mass, inertia)
+            }
+        };
+        Self {
+            shape,
+            position,
+            orientation: glm::quat_identity(),
+            velocity: Vec3::zeros(),
+            angular_velocity: Vec3::zeros(),
+            inverse_mass: 1.0 / mass,
+            inverse_inertia: inertia.map(|value| 1.0 / value),
+            color,
+        }
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    pub fn mass(&self) -> f32 {
+        if self.is_static() {
+            0.0
+        } else {
+            1.0 / self.inverse_mass
+        }
+    }
+
+    pub fn rotation(&self) -> Mat3 {
+        glm::quat_to_mat3(&self.orientation)
+    }
+
+    // ワールド座標での慣性テンソルの逆行列
+    pub fn inverse_inertia_world(&self) -> Mat3 {
+        let rotation = self.rotation();
+        rotation * Mat3::from_diagonal(&self.inverse_inertia) * rotation.transpose()
+    }
+
+    // 重心から offset (ワールド座標) だけ離れた点の速度
+    pub fn velocity_at(&self, offset: &Vec3) -> Vec3 {
+        self.velocity + self.angular_velocity.cross(offset)
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        if self.is_static() {
+            return 0.0;
+        }
+        let angular_velocity = self.rotation().transpose() * self.angular_velocity;
+        let rotational: f32 = (0..3)
+            .map(|axis| angular_velocity[axis].powi(2) / self.inverse_inertia[axis])
+            .sum();
+        0.5 * (self.mass() * self.velocity.norm_squared() + rotational)
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let half_extents = match self.shape {
+            Shape::Sphere { radius } => Vec3::repeat(radius),
+            Shape::Box { half_extents } => self.rotation().abs() * half_extents,
+        };
+        Aabb {
+            min: self.position - half_extents,
+            max: self.position + half_extents,
+        }
+    }
+
+    fn new_static(position: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            shape: Shape::Box { half_extents },
+            position,
+            orientation: glm::quat_identity(),
+            velocity: Vec3::zeros(),
+            angular_velocity: Vec3::zeros(),
+            inverse_mass: 0.0,
+            inverse_inertia: Vec3::zeros(),
+            color: [0.5, 0.5, 0.5],
+        }
+    }
+}
+
+// 接触ごとに速度の反復の前に決めておく値
+struct ContactConstraint {
+    // 重心から接触点まで
+    offset_a: Vec3,
+    offset_b: Vec3,
+    tangents: [Vec3; 2],
+    // 法線方向と接線方向の有効質量
+    normal_mass: f32,
+    tangent_masses: [f32; 2],
+    // 反発させる目標の法線方向の相対速度
+    target: f32,
+}
+
+pub struct World {
+    settings: WorldSettings,
+    bodies: Vec<RigidBody>,
+    contacts: Vec<Contact>,
+    accumulator: f32,
+}
+
+impl World {
+    pub fn new(settings: &WorldSettings) -> Self {
+        let mut bodies = vec![
+            // 床
+            RigidBody::new_static(
+                Vec3::new(0.0, -0.5, 0.0),
+                Vec3::new(FLOOR_HALF_SIZE, 0.5, FLOOR_HALF_SIZE),
+            ),
+        ];
+        // 物体がこぼれないように囲う壁
+        let offset = FLOOR_HALF_SIZE + WALL_THICKNESS;
+        for (x, z, half_x, half_z) in [
+            (offset, 0.0, WALL_THICKNESS, FLOOR_HALF_SIZE),
+            (-offset, 0.0, WALL_THICKNESS, FLOOR_HALF_SIZE),
+            (
+                0.0,
+                offset,
+                FLOOR_HALF_SIZE + 2.0 * WALL_THICKNESS,
+                WALL_THICKNESS,
+            ),
+            (
+                0.0,
+                -offset,
+                FLOOR_HALF_SIZE + 2.0 * WALL_THICKNESS,
+                WALL_THICKNESS,
+            ),
+        ] {
+            bodies.push(RigidBody::new_static(
+                Vec3::new(x, WALL_HEIGHT * 0.5, z),
+                Vec3::new(half_x, WALL_HEIGHT * 0.5, half_z),
+            ));
+        }
+
+        let mut random = Random::new(settings.seed);
+        let body_count = (settings.body_count as usize).min(MAX_BODY_COUNT);
+        for index in 0..body_count {
+            // 重ならないように格子状に積み上げて配置する
+            let size = random.next_f32_range(0.15, 0.35);
+            let cell = index % (SPAWN_GRID * SPAWN_GRID);
+            let layer = index / (SPAWN_GRID * SPAWN_GRID);
+            let center = (SPAWN_GRID as f32 - 1.0) * 0.5;
+            let position = Vec3::new(
+                ((cell % SPAWN_GRID) as f32 - center) * SPAWN_SPACING
+                    + random.next_f32_range(-0.04, 0.04),
+                1.0 + layer as f32 * SPAWN_SPACING,
+                ((cell / SPAWN_GRID) as f32 - center) * SPAWN_SPACING
+                    + random.next_f32_range(-0.04, 0.04),
+            );
+            let color = [
+                random.next_f32_range(0.3, 1.0),
+                random.next_f32_range(0.3, 1.0),
+                random.next_f32_range(0.3, 1.0),
+            ];
+            let mut body = if index % 2 == 0 {
+                RigidBody::new(Shape::Sphere { radius: size }, position, DENSITY, color)
+            } else {
+                let shape = Shape::Box {
+                    half_extents: Vec3::repeat(size),
+                };
+                RigidBody::new(shape, position, DENSITY, color)
+            };
+            // 箱は傾けて落とす
+            if matches!(body.shape, Shape::Box { .. }) {
+                let axis = Vec3::new(
+                    random.next_f32_range(-1.0, 1.0),
+                    random.next_f32_range(-1.0, 1.0),
+                    random.next_f32_range(-1.0, 1.0),
+                );
+                let angle = random.next_f32_range(0.0, std::f32::consts::PI);
+                if let Some(axis) = axis.try_normalize(1.0e-3) {
+                    body.orientation = glm::quat_angle_axis(angle, &axis);
+                }
+            }
+            bodies.push(body);
+        }
+
+        Self {
+            settings: *settings,
+            bodies,
+            contacts: Vec::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn bodies(&self) -> &[RigidBody] {
+        &self.bodies
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    // 番号を返す
+    pub fn add_body(&mut self, body: RigidBody) -> usize {
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    // 重力、タイムステップ、反発係数、摩擦係数はシミュレーションの途中でも変更できる
+    pub fn set_settings(&mut self, settings: &WorldSettings) {
+        self.settings = *settings;
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.bodies.iter().map(RigidBody::kinetic_energy).sum()
+    }
+
+    pub fn potential_energy(&self) -> f32 {
+        let gravity = Vec3::from(self.settings.gravity);
+        self.bodies
+            .iter()
+            .map(|body| -body.mass() * gravity.dot(&body.position))
+            .sum()
+    }
+
+    // 最も深くめり込んでいる接触の深さ
+    pub fn max_penetration(&self) -> f32 {
+        self.contacts
+            .iter()
+            .map(|contact| contact.depth)
+            .fold(0.0, f32::max)
+    }
+
+    // 経過時間ぶんだけ固定タイムステップでシミュレーションを進める
+    pub fn update(&mut self, delta_time: f32) {
+        self.accumulator += delta_time;
+        let mut sub_steps = 0;
+        while self.accumulator >= self.settings.timestep && sub_steps < MAX_SUB_STEPS {
+            self.step();
+            self.accumulator -= self.settings.timestep;
+            sub_steps += 1;
+        }
+        if sub_steps == MAX_SUB_STEPS {
+            self.accumulator = 0.0;
+        }
+    }
+
+    pub fn step(&mut self) {
+        let dt = self.settings.timestep;
+        let gravity = Vec3::from(self.settings.gravity);
+
+        for body in self.bodies.iter_mut().filter(|body| !body.is_static()) {
+            body.velocity += gravity * dt;
+        }
+
+        self.find_contacts();
+        self.solve_velocities(dt);
+
+        for body in self.bodies.iter_mut().filter(|body| !body.is_static()) {
+            body.position += body.velocity * dt;
+            // dq/dt = (0, ω) q / 2
+            let spin = body.angular_velocity;
+            let spin = glm::quat(spin.x, spin.y, spin.z, 0.0);
+            body.orientation =
+                glm::quat_normalize(&(body.orientation + spin * body.orientation * (0.5 * dt)));
+        }
+
+        self.find_contacts();
+        self.solve_positions();
+    }
+
+    fn find_contacts(&mut self) {
+        let aabbs = self
+            .bodies
+            .iter()
+            .map(|body| body.aabb())
+            .collect::<Vec<Aabb>>();
+        self.contacts.clear();
+        for (a, b) in collision::sweep_and_prune(&aabbs) {
+            if self.bodies[a].is_static() && self.bodies[b].is_static() {
+                continue;
+            }
+            collision::collide(a, b, &self.bodies[a], &self.bodies[b], &mut self.contacts);
+        }
+    }
+
+    // 撃力ベースの逐次インパルス法
+    // 接触点での相対速度を見て、並進と回転の両方に撃力を加える
+    fn solve_velocities(&mut self, dt: f32) {
+        let gravity = Vec3::from(self.settings.gravity);
+        // 反復のあいだは向きが変わらない
+        let inverse_inertias = self
+            .bodies
+            .iter()
+            .map(RigidBody::inverse_inertia_world)
+            .collect::<Vec<Mat3>>();
+
+        // 静止しているときに重力 1 ステップ分の速度で跳ね続けないように閾値以下は反発させない
+        let restitution_threshold = 2.0 * gravity.norm() * dt;
+        let constraints = self
+            .contacts
+            .iter()
+            .map(|contact| {
+                let (body_a, body_b) = (&self.bodies[contact.a], &self.bodies[contact.b]);
+                let offset_a = contact.point - body_a.position;
+                let offset_b = contact.point - body_b.position;
+                let effective_mass = |direction: &Vec3| {
+                    let angular_a =
+                        (inverse_inertias[contact.a] * offset_a.cross(direction)).cross(&offset_a);
+                    let angular_b =
+                        (inverse_inertias[contact.b] * offset_b.cross(direction)).cross(&offset_b);
+                    let k = body_a.inverse_mass
+                        + body_b.inverse_mass
+                        + direction.dot(&(angular_a + angular_b));
+                    if k > 0.0 {
+                        1.0 / k
+                    } else {
+                        0.0
+                    }
+                };
+                let tangents = tangent_basis(&contact.normal);
+                let normal_velocity = (body_b.velocity_at(&offset_b)
+                    - body_a.velocity_at(&offset_a))
+                .dot(&contact.normal);
+                ContactConstraint {
+                    offset_a,
+                    offset_b,
+                    tangents,
+                    normal_mass: effective_mass(&contact.normal),
+                    tangent_masses: tangents.map(|tangent| effective_mass(&tangent)),
+                    target: if normal_velocity < -restitution_threshold {
+                        -self.settings.restitution * normal_velocity
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect::<Vec<ContactConstraint>>();
+
+        let mut normal_impulses = vec![0.0f32; self.contacts.len()];
+        let mut friction_impulses = vec![[0.0f32; 2]; self.contacts.len()];
+        for _ in 0..SOLVER_ITERATIONS {
+            for (index, (contact, constraint)) in self.contacts.iter().zip(&constraints).enumerate()
+            {
+                if constraint.normal_mass == 0.0 {
+                    continue;
+                }
+
+                // 法線方向
+                let relative_velocity = self.relative_velocity(contact, constraint);
+                let impulse = (constraint.target - relative_velocity.dot(&contact.normal))
+                    * constraint.normal_mass;
+                let accumulated = (normal_impulses[index] + impulse).max(0.0);
+                let impulse = accumulated - normal_impulses[index];
+                normal_impulses[index] = accumulated;
+                apply_impulse(
+                    &mut self.bodies,
+                    contact,
+                    constraint,
+                    &inverse_inertias,
+                    &(contact.normal * impulse),
+                );
+
+                // 接線方向 (クーロン摩擦)。2 つの接線方向の撃力の合計を摩擦円の中に収める
+                let relative_velocity = self.relative_velocity(contact, constraint);
+                let previous = friction_impulses[index];
+                let mut accumulated: [f32; 2] = std::array::from_fn(|axis| {
+                    previous[axis]
+                        - relative_velocity.dot(&constraint.tangents[axis])
+                            * constraint.tangent_masses[axis]
+                });
+                let max_friction = self.settings.friction * normal_impulses[index];
+                let length = accumulated[0].hypot(accumulated[1]);
+                if length > max_friction {
+                    accumulated = accumulated.map(|value| value * max_friction / length);
+                }
+                friction_impulses[index] = accumulated;
+                let impulse = constraint.tangents[0] * (accumulated[0] - previous[0])
+                    + constraint.tangents[1] * (accumulated[1] - previous[1]);
+                apply_impulse(
+                    &mut self.bodies,
+                    contact,
+                    constraint,
+                    &inverse_inertias,
+                    &impulse,
+                );
+            }
+        }
+    }
+
+    // 接触点での b の a に対する速度
+    fn relative_velocity(&self, contact: &Contact, constraint: &ContactConstraint) -> Vec3 {
+        self.bodies[contact.b].velocity_at(&constraint.offset_b)
+            - self.bodies[contact.a].velocity_at(&constraint.offset_a)
+    }
+
+    // 速度の解決で取りきれなかっためり込みを位置を直接動かして解消する (回転は直さない)
+    // 同じ組の物体に接触点が複数あるので、先に動かした分を差し引いた深さで直す
+    fn solve_positions(&mut self) {
+        let mut corrections = vec![Vec3::zeros(); self.bodies.len()];
+        for contact in &self.contacts {
+            let inverse_mass_a = self.bodies[contact.a].inverse_mass;
+            let inverse_mass_b = self.bodies[contact.b].inverse_mass;
+            let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+            if inverse_mass_sum == 0.0 {
+                continue;
+            }
+            let depth = contact.depth
+                - (corrections[contact.b] - corrections[contact.a]).dot(&contact.normal);
+            let correction =
+                (depth - PENETRATION_SLOP).max(0.0) * POSITION_CORRECTION / inverse_mass_sum;
+            corrections[contact.a] -= contact.normal * (correction * inverse_mass_a);
+            corrections[contact.b] += contact.normal * (correction * inverse_mass_b);
+        }
+        for (body, correction) in self.bodies.iter_mut().zip(corrections) {
+            body.position += correction;
+        }
+    }
+}
+
+// b に impulse、a にその反作用を接触点で加える
+fn apply_impulse(
+    bodies: &mut [RigidBody],
+    contact: &Contact,
+    constraint: &ContactConstraint,
+    inverse_inertias: &[Mat3],
+    impulse: &Vec3,
+) {
+    let body_a = &mut bodies[contact.a];
+    body_a.velocity -= impulse * body_a.inverse_mass;
+    body_a.angular_velocity -= inverse_inertias[contact.a] * constraint.offset_a.cross(impulse);
+    let body_b = &mut bodies[contact.b];
+    body_b.velocity += impulse * body_b.inverse_mass;
+    body_b.angular_velocity += inverse_inertias[contact.b] * constraint.offset_b.cross(impulse);
+}
+
+// normal に垂直な 2 つの単位ベクトル
+fn tangent_basis(normal: &Vec3) -> [Vec3; 2] {
+    let reference = if normal.x.abs() < 0.6 {
+        Vec3::x()
+    } else {
+        Vec3::y()
+    };
+    let first = normal.cross(&reference).normalize();
+    [first, normal.cross(&first)]
+}
@@ -0,0 +1,44 @@
+use std::{collections::HashMap, hash::Hash};
+
+// シェーダーのマクロやパイプラインの設定の組み合わせをキーにして、作ったパイプラインを使い回す
+// 要求された組み合わせだけを作るので、使われない組み合わせのシェーダーはコンパイルしない
+pub struct PipelineCache<K> {
+    pipelines: HashMap<K, wgpu::RenderPipeline>,
+}
+
+impl<K> Default for PipelineCache<K> {
+    fn default() -> Self {
+        Self {
+            pipelines: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> PipelineCache<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // key のパイプラインがまだなければ create で作る
+    // draw では &self しか使えないので、update で呼んでおく
+    pub fn prepare(&mut self, key: K, create: impl FnOnce(K) -> wgpu::RenderPipeline) {
+        self.pipelines.entry(key).or_insert_with(|| create(key));
+    }
+
+    pub fn get(&self, key: K) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(&key)
+    }
+
+    // 作ったパイプラインの数
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+    }
+}
@@ -0,0 +1,636 @@
+// GPU を使わずにデモと同じ計算をする参照実装
+// シェーダーと同じ手順で CPU で描画して、GPU のない環境でもシェーダーのロジックとユニフォームのレイアウトを確かめられるようにする
+// ユニフォームはデモがアップロードするバイト列を、シェーダーから生成した layouts のオフセットで読み直して使う
+
+use nalgebra_glm as glm;
+
+use crate::{
+    mandelbrot::MandelbrotUniform,
+    model_3d::{LightsUniform, ObjectUniform, ViewUniform},
+    palette::PALETTE_TEXTURE_WIDTH,
+    shaders::layouts::{mandelbrot_fs, model_3d_fs, model_3d_vs, triangle_fs},
+    triangle::TRIANGLE_VERTICES,
+    uniform::Std140 as _,
+    FractalType, Image, MandelbrotParams, Model3dParams, TriangleParams, MAX_LIGHTS,
+};
+
+// std140 のバイト列からの読み出し
+struct Std140Reader<'a>(&'a [u8]);
+
+impl<'a> Std140Reader<'a> {
+    fn f32(&self, offset: usize) -> f32 {
+        f32::from_ne_bytes(self.0[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_ne_bytes(self.0[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn vec2(&self, offset: usize) -> glm::Vec2 {
+        glm::vec2(self.f32(offset), self.f32(offset + 4))
+    }
+
+    fn vec3(&self, offset: usize) -> glm::Vec3 {
+        glm::vec3(self.f32(offset), self.f32(offset + 4), self.f32(offset + 8))
+    }
+
+    fn vec4(&self, offset: usize) -> glm::Vec4 {
+        glm::vec4(
+            self.f32(offset),
+            self.f32(offset + 4),
+            self.f32(offset + 8),
+            self.f32(offset + 12),
+        )
+    }
+
+    // vec4 rows[N] で渡される行優先の行列
+    fn rows<const N: usize>(&self, offset: usize) -> [glm::Vec4; N] {
+        std::array::from_fn(|index| self.vec4(offset + index * 16))
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// 画素の中心の NDC 座標 (y は上向き)
+fn pixel_center(x: u32, y: u32, width: u32, height: u32) -> glm::Vec2 {
+    glm::vec2(
+        (x as f32 + 0.5) / width as f32 * 2.0 - 1.0,
+        1.0 - (y as f32 + 0.5) / height as f32 * 2.0,
+    )
+}
+
+fn create_image(width: u32, height: u32) -> Image {
+    // encode_demo と同じく不透明な黒でクリアする
+    Image {
+        width,
+        height,
+        pixels: [0, 0, 0, 255].repeat((width * height) as usize),
+    }
+}
+
+fn edge(a: glm::Vec2, b: glm::Vec2, p: glm::Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+// 画素の中心をおおう三角形の重心座標
+// 向きによらず、内側なら 3 つとも 0 以上になる
+fn barycentric(vertices: [glm::Vec2; 3], p: glm::Vec2) -> Option<[f32; 3]> {
+    let area = edge(vertices[0], vertices[1], vertices[2]);
+    if area == 0.0 {
+        return None;
+    }
+    let weights = [
+        edge(vertices[1], vertices[2], p) / area,
+        edge(vertices[2], vertices[0], p) / area,
+        edge(vertices[0], vertices[1], p) / area,
+    ];
+    weights
+        .iter()
+        .all(|weight| *weight >= 0.0)
+        .then_some(weights)
+}
+
+// triangle.vs と triangle.fs
+pub fn triangle(params: &TriangleParams, width: u32, height: u32) -> Image {
+    let uniform = params.std140();
+    let uniform = Std140Reader(bytemuck::bytes_of(&uniform));
+    let color = uniform.vec3(triangle_fs::material::U_COLOR);
+    let rgba = [to_u8(color.x), to_u8(color.y), to_u8(color.z), 255];
+
+    let vertices: [glm::Vec2; 3] = std::array::from_fn(|index| {
+        glm::vec2(
+            TRIANGLE_VERTICES[index * 2],
+            TRIANGLE_VERTICES[index * 2 + 1],
+        )
+    });
+    let mut image = create_image(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            if barycentric(vertices, pixel_center(x, y, width, height)).is_some() {
+                let index = ((y * width + x) * 4) as usize;
+                image.pixels[index..index + 4].copy_from_slice(&rgba);
+            }
+        }
+    }
+    image
+}
+
+// mandelbrot.fs の BAILOUT_RADIUS
+const BAILOUT_RADIUS: f32 = 256.0;
+
+// mandelbrot.fs の Orbit
+struct Orbit {
+    count: u32,
+    z: glm::Vec2,
+    dz: glm::Vec2,
+}
+
+fn complex_mul(a: glm::Vec2, b: glm::Vec2) -> glm::Vec2 {
+    glm::vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)
+}
+
+struct MandelbrotReference {
+    fractal_type: FractalType,
+    center_high: glm::Vec2,
+    center_low: glm::Vec2,
+    scale_high: f32,
+    scale_low: f32,
+    max_iterations: u32,
+    coloring_mode: u32,
+    use_double_float: bool,
+    exponent: f32,
+    julia_c: glm::Vec2,
+    palette_offset: f32,
+    palette_cycles: f32,
+    aspect: f32,
+    palette: Vec<u8>,
+}
+
+impl MandelbrotReference {
+    fn new(params: &MandelbrotParams, aspect: f32) -> Self {
+        use mandelbrot_fs::params as layout;
+        let uniform = MandelbrotUniform::new(params, aspect).std140();
+        let uniform = Std140Reader(bytemuck::bytes_of(&uniform));
+        Self {
+            fractal_type: params.fractal_type,
+            center_high: uniform.vec2(layout::U_CENTER_HIGH),
+            center_low: uniform.vec2(layout::U_CENTER_LOW),
+            scale_high: uniform.f32(layout::U_SCALE_HIGH),
+            scale_low: uniform.f32(layout::U_SCALE_LOW),
+            max_iterations: uniform.u32(layout::U_MAX_ITERATIONS),
+            coloring_mode: uniform.u32(layout::U_COLORING_MODE),
+            use_double_float: uniform.u32(layout::U_USE_DOUBLE_FLOAT) != 0,
+            exponent: uniform.f32(layout::U_EXPONENT),
+            julia_c: uniform.vec2(layout::U_JULIA_C),
+            palette_offset: uniform.f32(layout::U_PALETTE_OFFSET),
+            palette_cycles: uniform.f32(layout::U_PALETTE_CYCLES),
+            aspect: uniform.f32(layout::U_ASPECT),
+            palette: params.palette.to_rgba8(PALETTE_TEXTURE_WIDTH),
+        }
+    }
+
+    fn is_julia(&self) -> bool {
+        self.fractal_type == FractalType::Julia
+    }
+
+    fn degree(&self) -> f32 {
+        if self.fractal_type == FractalType::Multibrot {
+            self.exponent
+        } else {
+            2.0
+        }
+    }
+
+    fn next_z(&self, mut z: glm::Vec2, c: glm::Vec2) -> glm::Vec2 {
+        match self.fractal_type {
+            FractalType::Multibrot => {
+                let r = z.norm().powf(self.exponent);
+                let theta = z.y.atan2(z.x) * self.exponent;
+                glm::vec2(theta.cos(), theta.sin()) * r + c
+            }
+            fractal_type => {
+                if fractal_type == FractalType::BurningShip {
+                    z = z.abs();
+                }
+                if fractal_type == FractalType::Tricorn {
+                    z.y = -z.y;
+                }
+                glm::vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c
+            }
+        }
+    }
+
+    fn next_dz(&self, z: glm::Vec2, dz: glm::Vec2) -> glm::Vec2 {
+        let derivative = if self.fractal_type == FractalType::Multibrot {
+            let r = z.norm().powf(self.exponent - 1.0);
+            let theta = z.y.atan2(z.x) * (self.exponent - 1.0);
+            glm::vec2(theta.cos(), theta.sin()) * (self.exponent * r)
+        } else {
+            z * 2.0
+        };
+        if self.is_julia() {
+            complex_mul(derivative, dz)
+        } else {
+            complex_mul(derivative, dz) + glm::vec2(1.0, 0.0)
+        }
+    }
+
+    fn iterate(&self, position: glm::Vec2) -> Orbit {
+        let (mut z, c, mut dz) = if self.is_julia() {
+            (position, self.julia_c, glm::vec2(1.0, 0.0))
+        } else {
+            (glm::Vec2::zeros(), position, glm::Vec2::zeros())
+        };
+
+        let mut count = 0;
+        for _ in 0..self.max_iterations {
+            count += 1;
+            if z.dot(&z) > BAILOUT_RADIUS * BAILOUT_RADIUS {
+                break;
+            }
+            dz = self.next_dz(z, dz);
+            z = self.next_z(z, c);
+        }
+        Orbit { count, z, dz }
+    }
+
+    // double-float の代わりに f64 で計算する
+    fn iterate_double_float(&self, offset: glm::Vec2) -> Orbit {
+        let scale = self.scale_high as f64 + self.scale_low as f64;
+        let x = self.center_high.x as f64 + self.center_low.x as f64 + offset.x as f64 * scale;
+        let y = self.center_high.y as f64 + self.center_low.y as f64 + offset.y as f64 * scale;
+
+        let (mut zx, mut zy, cx, cy, mut dz) = if self.is_julia() {
+            (
+                x,
+                y,
+                self.julia_c.x as f64,
+                self.julia_c.y as f64,
+                glm::vec2(1.0, 0.0),
+            )
+        } else {
+            (0.0, 0.0, x, y, glm::Vec2::zeros())
+        };
+
+        let mut count = 0;
+        for _ in 0..self.max_iterations {
+            count += 1;
+            let (zx32, zy32) = (zx as f32, zy as f32);
+            if zx32 * zx32 + zy32 * zy32 > BAILOUT_RADIUS * BAILOUT_RADIUS {
+                break;
+            }
+            dz = self.next_dz(glm::vec2(zx32, zy32), dz);
+
+            if self.fractal_type == FractalType::BurningShip {
+                zx = zx.abs();
+                zy = zy.abs();
+            }
+            if self.fractal_type == FractalType::Tricorn {
+                zy = -zy;
+            }
+            (zx, zy) = (zx * zx - zy * zy + cx, 2.0 * zx * zy + cy);
+        }
+        Orbit {
+            count,
+            z: glm::vec2(zx as f32, zy as f32),
+            dz,
+        }
+    }
+
+    // リピートと線形補間のサンプラーで 1D テクスチャーを読むのと同じ
+    fn palette(&self, t: f32) -> glm::Vec3 {
+        let width = PALETTE_TEXTURE_WIDTH as i64;
+        let coordinate = (t * self.palette_cycles + self.palette_offset) * width as f32 - 0.5;
+        let floor = coordinate.floor();
+        let ratio = coordinate - floor;
+        let texel = |index: i64| {
+            let index = (index.rem_euclid(width) * 4) as usize;
+            glm::vec3(
+                self.palette[index] as f32,
+                self.palette[index + 1] as f32,
+                self.palette[index + 2] as f32,
+            ) / 255.0
+        };
+        glm::mix(&texel(floor as i64), &texel(floor as i64 + 1), ratio)
+    }
+
+    fn color(&self, normalized_frag_coord: glm::Vec2, pixel_size: f32) -> glm::Vec3 {
+        let position = normalized_frag_coord.component_mul(&glm::vec2(self.aspect, 1.0));
+        let orbit = if self.use_double_float {
+            self.iterate_double_float(position)
+        } else {
+            self.iterate(self.center_high + position * self.scale_high)
+        };
+
+        if orbit.count >= self.max_iterations {
+            return glm::Vec3::zeros();
+        }
+
+        if self.coloring_mode == 0 {
+            let t = (orbit.count as f32 / self.max_iterations as f32).ln();
+            return self.palette(t);
+        }
+
+        let z_length = orbit.z.norm();
+        let smooth_count = orbit.count as f32 + 1.0 - z_length.ln().ln() / self.degree().ln();
+        let t = (smooth_count.max(1.0) / self.max_iterations as f32).ln();
+        let mut color = self.palette(t);
+
+        if self.coloring_mode == 2 {
+            let distance = 0.5 * z_length * z_length.ln() / orbit.dz.norm();
+            color *= (distance / pixel_size).sqrt().clamp(0.0, 1.0);
+        }
+        color
+    }
+}
+
+// mandelbrot.vs と mandelbrot.fs
+// double-float の経路は f64 で代用するので、深いズームでは GPU と細部が一致しない
+pub fn mandelbrot(params: &MandelbrotParams, width: u32, height: u32) -> Image {
+    let reference = MandelbrotReference::new(params, width as f32 / height.max(1) as f32);
+    // fwidth(v_NormalizedFragCoord.y) は 1 画素ぶんの NDC の高さ
+    let pixel_size = 2.0 / height as f32 * reference.scale_high;
+
+    let mut image = create_image(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let color = reference.color(pixel_center(x, y, width, height), pixel_size);
+            let index = ((y * width + x) * 4) as usize;
+            image.pixels[index..index + 3].copy_from_slice(&[
+                to_u8(color.x),
+                to_u8(color.y),
+                to_u8(color.z),
+            ]);
+        }
+    }
+    image
+}
+
+// model_3d.vs の出力
+#[derive(Clone, Copy)]
+struct Model3dVertex {
+    clip_position: glm::Vec4,
+    world_position: glm::Vec3,
+    normal: glm::Vec3,
+}
+
+fn dot_rows<const N: usize>(rows: &[glm::Vec4; N], vector: &glm::Vec4) -> [f32; N] {
+    std::array::from_fn(|index| rows[index].dot(vector))
+}
+
+// メッシュごとの頂点をクリップ座標に変換する (model_3d.vs)
+pub fn model_3d_clip_positions(params: &Model3dParams, aspect: f32) -> Vec<Vec<[f32; 4]>> {
+    let view = ViewUniform::new(params, aspect).std140();
+    let view = Std140Reader(bytemuck::bytes_of(&view));
+    params
+        .scene
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let object = ObjectUniform::new(mesh, params.material(mesh.material)).std140();
+            let object = Std140Reader(bytemuck::bytes_of(&object));
+            (0..mesh.positions.len())
+                .map(|index| {
+                    let vertex = transform_vertex(&view, &object, mesh.positions[index], [0.0; 3]);
+                    vertex.clip_position.into()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn transform_vertex(
+    view: &Std140Reader,
+    object: &Std140Reader,
+    position: [f32; 3],
+    normal: [f32; 3],
+) -> Model3dVertex {
+    let position = glm::vec4(position[0], position[1], position[2], 1.0);
+    let model_rows = object.rows::<4>(model_3d_vs::object::U_MODEL);
+    let world_position = glm::Vec4::from(dot_rows(&model_rows, &position));
+    let view_projection_rows = view.rows::<4>(model_3d_vs::view::U_VIEW_PROJECTION);
+    let clip_position = glm::Vec4::from(dot_rows(&view_projection_rows, &world_position));
+    let normal_rows = object.rows::<3>(model_3d_vs::object::U_NORMAL_MATRIX);
+    let normal = glm::Vec3::from(normal);
+    Model3dVertex {
+        clip_position,
+        world_position: world_position.xyz() / world_position.w,
+        normal: glm::vec3(
+            normal_rows[0].xyz().dot(&normal),
+            normal_rows[1].xyz().dot(&normal),
+            normal_rows[2].xyz().dot(&normal),
+        ),
+    }
+}
+
+// model_3d.fs の Light
+struct LightReference {
+    position: glm::Vec3,
+    kind: u32,
+    direction: glm::Vec3,
+    range: f32,
+    color: glm::Vec3,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl LightReference {
+    // 表面からライトへの向きと届く光の量
+    fn radiance(&self, position: glm::Vec3) -> (glm::Vec3, glm::Vec3) {
+        if self.kind == 0 {
+            return (-self.direction.normalize(), self.color);
+        }
+
+        let to_light = self.position - position;
+        let distance = to_light.norm();
+        let l = to_light / distance.max(0.0001);
+
+        let mut attenuation = 1.0 / (distance * distance).max(0.0001);
+        if self.range > 0.0 {
+            let ratio = distance / self.range;
+            let window = (1.0 - ratio.powi(4)).clamp(0.0, 1.0);
+            attenuation *= window * window;
+        }
+        if self.kind == 2 {
+            let cos_angle = (-l).dot(&self.direction.normalize());
+            attenuation *= smoothstep(self.cos_outer, self.cos_inner, cos_angle);
+        }
+        (l, self.color * attenuation)
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+struct MaterialReference {
+    base_color: glm::Vec4,
+    emissive: glm::Vec3,
+    metallic: f32,
+    roughness: f32,
+}
+
+// model_3d.fs の main (影は計算しない)
+fn shade(
+    camera_position: glm::Vec3,
+    ambient: glm::Vec3,
+    lights: &[LightReference],
+    material: &MaterialReference,
+    position: glm::Vec3,
+    normal: glm::Vec3,
+) -> glm::Vec4 {
+    use std::f32::consts::PI;
+
+    let n = normal;
+    let v = (camera_position - position).normalize();
+    let n_dot_v = n.dot(&v).max(0.0001);
+
+    let base_color = material.base_color.xyz();
+    let roughness = material.roughness.clamp(0.04, 1.0);
+    let f0 = glm::mix(&glm::vec3(0.04, 0.04, 0.04), &base_color, material.metallic);
+
+    let mut color = glm::Vec3::zeros();
+    for light in lights {
+        let (l, radiance) = light.radiance(position);
+        let n_dot_l = n.dot(&l);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+
+        let h = (v + l).normalize();
+        let n_dot_h = n.dot(&h).max(0.0);
+
+        let a = roughness * roughness;
+        let a2 = a * a;
+        let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        let distribution = a2 / (PI * d * d);
+
+        let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+        let geometry_schlick_ggx = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+        let geometry = geometry_schlick_ggx(n_dot_v) * geometry_schlick_ggx(n_dot_l);
+
+        let fresnel = f0 + (glm::vec3(1.0, 1.0, 1.0) - f0) * (1.0 - h.dot(&v).max(0.0)).powi(5);
+        let specular = fresnel * (distribution * geometry / (4.0 * n_dot_v * n_dot_l + 0.0001));
+        let diffuse = (glm::vec3(1.0, 1.0, 1.0) - fresnel).component_mul(&base_color)
+            * ((1.0 - material.metallic) / PI);
+        color += (diffuse + specular).component_mul(&radiance) * n_dot_l;
+    }
+    color += ambient.component_mul(&base_color) + material.emissive;
+
+    let color = color.component_div(&color.add_scalar(1.0));
+    glm::vec4(
+        color.x.powf(1.0 / 2.2),
+        color.y.powf(1.0 / 2.2),
+        color.z.powf(1.0 / 2.2),
+        material.base_color.w,
+    )
+}
+
+// model_3d.vs と model_3d.fs
+// シャドウマップは使わないので、影が大きく落ちる構図では GPU の結果と一致しない
+// ニアクリップ面をまたぐ三角形は描かない
+pub fn model_3d(params: &Model3dParams, width: u32, height: u32) -> Image {
+    let view = ViewUniform::new(params, width as f32 / height.max(1) as f32).std140();
+    let view = Std140Reader(bytemuck::bytes_of(&view));
+    let camera_position = view.vec3(model_3d_fs::view::U_CAMERA_POSITION);
+    let light_count = (view.u32(model_3d_fs::view::U_LIGHT_COUNT) as usize).min(MAX_LIGHTS);
+    let ambient = view.vec3(model_3d_fs::view::U_AMBIENT);
+
+    let light_uniforms = LightsUniform::new(&params.lighting).std140();
+    let light_bytes = Std140Reader(bytemuck::bytes_of(&light_uniforms));
+    let lights: Vec<_> = (0..light_count)
+        .map(|index| {
+            use model_3d_fs::light as layout;
+            let offset = model_3d_fs::lights::U_LIGHTS + index * layout::SIZE;
+            LightReference {
+                position: light_bytes.vec3(offset + layout::POSITION),
+                kind: light_bytes.u32(offset + layout::KIND),
+                direction: light_bytes.vec3(offset + layout::DIRECTION),
+                range: light_bytes.f32(offset + layout::RANGE),
+                color: light_bytes.vec3(offset + layout::COLOR),
+                cos_inner: light_bytes.f32(offset + layout::COS_INNER),
+                cos_outer: light_bytes.f32(offset + layout::COS_OUTER),
+            }
+        })
+        .collect();
+
+    let mut image = create_image(width, height);
+    let mut depth_buffer = vec![1.0f32; (width * height) as usize];
+    for mesh in &params.scene.meshes {
+        let object = ObjectUniform::new(mesh, params.material(mesh.material)).std140();
+        let object = Std140Reader(bytemuck::bytes_of(&object));
+        let material = MaterialReference {
+            base_color: object.vec4(model_3d_fs::object::U_BASE_COLOR),
+            emissive: object.vec3(model_3d_fs::object::U_EMISSIVE),
+            metallic: object.f32(model_3d_fs::object::U_METALLIC),
+            roughness: object.f32(model_3d_fs::object::U_ROUGHNESS),
+        };
+
+        let vertices: Vec<_> = mesh
+            .positions
+            .iter()
+            .zip(&mesh.normals)
+            .map(|(position, normal)| transform_vertex(&view, &object, *position, *normal))
+            .collect();
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let triangle: [Model3dVertex; 3] =
+                std::array::from_fn(|index| vertices[triangle[index] as usize]);
+            if triangle.iter().any(|vertex| vertex.clip_position.w <= 0.0) {
+                continue;
+            }
+            let ndc = triangle.map(|vertex| vertex.clip_position.xyz() / vertex.clip_position.w);
+            let screen = ndc.map(|ndc| ndc.xy());
+            // 反時計回りが表 (wgpu の既定)
+            let is_front_facing = edge(screen[0], screen[1], screen[2]) > 0.0;
+
+            // 画素の範囲に絞る
+            let to_pixel = |ndc: glm::Vec2| {
+                glm::vec2(
+                    (ndc.x + 1.0) * 0.5 * width as f32,
+                    (1.0 - ndc.y) * 0.5 * height as f32,
+                )
+            };
+            let pixels = screen.map(to_pixel);
+            let min_x = pixels.iter().map(|p| p.x).fold(f32::MAX, f32::min).max(0.0) as u32;
+            let max_x =
+                (pixels.iter().map(|p| p.x).fold(f32::MIN, f32::max).ceil() as u32).min(width);
+            let min_y = pixels.iter().map(|p| p.y).fold(f32::MAX, f32::min).max(0.0) as u32;
+            let max_y =
+                (pixels.iter().map(|p| p.y).fold(f32::MIN, f32::max).ceil() as u32).min(height);
+
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let Some(weights) = barycentric(screen, pixel_center(x, y, width, height))
+                    else {
+                        continue;
+                    };
+                    let depth =
+                        weights[0] * ndc[0].z + weights[1] * ndc[1].z + weights[2] * ndc[2].z;
+                    let depth_index = (y * width + x) as usize;
+                    if !(0.0..=1.0).contains(&depth) || depth >= depth_buffer[depth_index] {
+                        continue;
+                    }
+                    depth_buffer[depth_index] = depth;
+
+                    // 透視補正
+                    let perspective: [f32; 3] = std::array::from_fn(|index| {
+                        weights[index] / triangle[index].clip_position.w
+                    });
+                    let sum: f32 = perspective.iter().sum();
+                    let interpolate = |value: fn(&Model3dVertex) -> glm::Vec3| {
+                        (value(&triangle[0]) * perspective[0]
+                            + value(&triangle[1]) * perspective[1]
+                            + value(&triangle[2]) * perspective[2])
+                            / sum
+                    };
+                    let position = interpolate(|vertex| vertex.world_position);
+                    let mut normal = interpolate(|vertex| vertex.normal).normalize();
+                    if !is_front_facing {
+                        normal = -normal;
+                    }
+
+                    let color = shade(
+                        camera_position,
+                        ambient,
+                        &lights,
+                        &material,
+                        position,
+                        normal,
+                    );
+                    let index = depth_index * 4;
+                    image.pixels[index..index + 4].copy_from_slice(&[
+                        to_u8(color.x),
+                        to_u8(color.y),
+                        to_u8(color.z),
+                        to_u8(color.w),
+                    ]);
+                }
+            }
+        }
+    }
+    image
+}
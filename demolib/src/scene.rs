@@ -0,0 +1,222 @@
+mod gltf;
+mod obj;
+mod usda;
+
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    // line は 1 始まり。行がわからないときは 0
+    Parse { line: usize, message: String },
+    InvalidMesh { prim: String, message: String },
+    Gltf(::gltf::Error),
+    Obj(tobj::LoadError),
+    UnsupportedFormat(String),
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{error}"),
+            SceneError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            SceneError::InvalidMesh { prim, message } => write!(f, "{prim}: {message}"),
+            SceneError::Gltf(error) => write!(f, "{error}"),
+            SceneError::Obj(error) => write!(f, "{error}"),
+            SceneError::UnsupportedFormat(name) => write!(f, "unsupported format: {name}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<::gltf::Error> for SceneError {
+    fn from(error: ::gltf::Error) -> Self {
+        SceneError::Gltf(error)
+    }
+}
+
+impl From<tobj::LoadError> for SceneError {
+    fn from(error: tobj::LoadError) -> Self {
+        SceneError::Obj(error)
+    }
+}
+
+// glTF の metallic-roughness に合わせたマテリアル
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+    pub name: String,
+    // リニア
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0; 3],
+        }
+    }
+}
+
+// 三角形分割済みのメッシュ
+// 頂点はローカル座標で、transform でワールド座標に変換する
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    pub transform: glm::Mat4,
+    // Scene::materials の番号
+    pub material: Option<usize>,
+}
+
+impl Mesh {
+    pub fn world_positions(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        self.positions.iter().map(|position| {
+            let position = self.transform * glm::vec4(position[0], position[1], position[2], 1.0);
+            [position.x, position.y, position.z]
+        })
+    }
+
+    // 法線を変換するための transform の左上 3x3 の逆転置
+    pub fn normal_matrix(&self) -> glm::Mat3 {
+        glm::mat4_to_mat3(&self.transform)
+            .try_inverse()
+            .unwrap_or_else(glm::Mat3::identity)
+            .transpose()
+    }
+
+    pub fn world_normals(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        let normal_matrix = self.normal_matrix();
+        self.normals.iter().map(move |normal| {
+            let normal = (normal_matrix * glm::Vec3::from(*normal)).normalize();
+            [normal.x, normal.y, normal.z]
+        })
+    }
+}
+
+// 読み込んだ形式によらない中間表現
+// Z-up のワールド座標に配置されたメッシュの集まり
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Scene {
+    pub fn from_usda(source: &str) -> Result<Self, SceneError> {
+        usda::load(source)
+    }
+
+    // .gltf の外部バッファーは読めないので、埋め込みか .glb にする
+    pub fn from_gltf(bytes: &[u8]) -> Result<Self, SceneError> {
+        gltf::load(bytes, None)
+    }
+
+    // .mtl は読まない
+    pub fn from_obj(source: &str) -> Result<Self, SceneError> {
+        obj::load(source)
+    }
+
+    // ファイル名の拡張子で形式を判断する
+    // Web でドロップされたファイルのようにパスがないとき用
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<Self, SceneError> {
+        let extension = std::path::Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            // .usd はテキストとバイナリのどちらもありうる
+            "usda" | "usd" if !bytes.starts_with(b"PXR-USDC") => {
+                let source = std::str::from_utf8(bytes).map_err(|error| SceneError::Parse {
+                    line: 0,
+                    message: error.to_string(),
+                })?;
+                Self::from_usda(source)
+            }
+            "gltf" | "glb" => Self::from_gltf(bytes),
+            "obj" => Self::from_obj(&String::from_utf8_lossy(bytes)),
+            _ => Err(SceneError::UnsupportedFormat(name.to_string())),
+        }
+    }
+
+    // 外部ファイル (.bin, .mtl) も同じディレクトリから読む
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "gltf" | "glb" => gltf::load(&std::fs::read(path)?, path.parent()),
+            "obj" => obj::load_file(path),
+            _ => Self::from_bytes(&path.to_string_lossy(), &std::fs::read(path)?),
+        }
+    }
+
+    // ワールド座標での (最小, 最大)
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.meshes
+            .iter()
+            .flat_map(|mesh| mesh.world_positions())
+            .fold(None, |bounds, position| {
+                let Some((min, max)) = bounds else {
+                    return Some((position, position));
+                };
+                Some((
+                    std::array::from_fn(|index| f32::min(min[index], position[index])),
+                    std::array::from_fn(|index| f32::max(max[index], position[index])),
+                ))
+            })
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum()
+    }
+}
+
+// Y-up から Z-up への変換
+fn y_up_to_z_up() -> glm::Mat4 {
+    glm::rotation(std::f32::consts::FRAC_PI_2, &glm::Vec3::x())
+}
+
+// 面積で重み付けした頂点法線
+fn compute_vertex_normals(points: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![glm::Vec3::zeros(); points.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|index| glm::Vec3::from(points[triangle[index] as usize]));
+        let normal = (b - a).cross(&(c - a));
+        for index in triangle {
+            normals[*index as usize] += normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| {
+            let normal = if normal.norm() > 0.0 {
+                normal.normalize()
+            } else {
+                glm::Vec3::z()
+            };
+            [normal.x, normal.y, normal.z]
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+// glTF 2.0 (.gltf / .glb) の読み込み
+// 三角形のプリミティブだけを Mesh にする
+
+use std::path::Path;
+
+use nalgebra_glm as glm;
+
+use super::{compute_vertex_normals, y_up_to_z_up, Material, Mesh, Scene, SceneError};
+
+// base は外部バッファーの URI を解決するディレクトリ
+pub fn load(bytes: &[u8], base: Option<&Path>) -> Result<Scene, SceneError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(bytes)?;
+    let buffers = gltf::import_buffers(&document, base, blob)?;
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            Material {
+                name: material.name().unwrap_or_default().to_string(),
+                base_color: pbr.base_color_factor(),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: material.emissive_factor(),
+            }
+        })
+        .collect();
+
+    let mut meshes = Vec::new();
+    let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    else {
+        return Ok(Scene { meshes, materials });
+    };
+    for node in scene.nodes() {
+        collect_meshes(&node, &y_up_to_z_up(), &buffers, &mut meshes)?;
+    }
+    Ok(Scene { meshes, materials })
+}
+
+fn collect_meshes(
+    node: &gltf::Node,
+    parent_transform: &glm::Mat4,
+    buffers: &[gltf::buffer::Data],
+    meshes: &mut Vec<Mesh>,
+) -> Result<(), SceneError> {
+    // 列優先の配列
+    let local_transform = glm::Mat4::from(node.transform().matrix());
+    let transform = parent_transform * local_transform;
+    let node_name = node
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("node{}", node.index()));
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let name = format!("{node_name}/{}", primitive.index());
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                return Err(SceneError::InvalidMesh {
+                    prim: name,
+                    message: "POSITION is missing".to_string(),
+                });
+            };
+            let positions = positions.collect::<Vec<_>>();
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            if indices
+                .iter()
+                .any(|index| *index as usize >= positions.len())
+            {
+                return Err(SceneError::InvalidMesh {
+                    prim: name,
+                    message: "indices are out of range".to_string(),
+                });
+            }
+            let normals = match reader.read_normals() {
+                Some(normals) => normals.collect(),
+                None => compute_vertex_normals(&positions, &indices),
+            };
+
+            meshes.push(Mesh {
+                name,
+                positions,
+                normals,
+                indices,
+                transform,
+                material: primitive.material().index(),
+            });
+        }
+    }
+
+    for child in node.children() {
+        collect_meshes(&child, &transform, buffers, meshes)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// Wavefront OBJ (+ MTL) の読み込み
+
+use super::{compute_vertex_normals, y_up_to_z_up, Material, Mesh, Scene, SceneError};
+
+const LOAD_OPTIONS: tobj::LoadOptions = tobj::LoadOptions {
+    single_index: true,
+    triangulate: true,
+    ignore_points: true,
+    ignore_lines: true,
+};
+
+// マテリアルは読まない
+pub fn load(source: &str) -> Result<Scene, SceneError> {
+    let (models, _) = tobj::load_obj_buf(&mut source.as_bytes(), &LOAD_OPTIONS, |_| {
+        Err(tobj::LoadError::OpenFileFailed)
+    })?;
+    Ok(to_scene(models, Vec::new()))
+}
+
+// mtllib は OBJ と同じディレクトリから探す
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(path: &std::path::Path) -> Result<Scene, SceneError> {
+    let (models, materials) = tobj::load_obj(path, &LOAD_OPTIONS)?;
+    // MTL が見つからなくても形状だけは表示する
+    let materials = materials.unwrap_or_default();
+    Ok(to_scene(models, materials))
+}
+
+fn to_scene(models: Vec<tobj::Model>, materials: Vec<tobj::Material>) -> Scene {
+    let to_vec3 = |values: &[f32]| {
+        values
+            .chunks_exact(3)
+            .map(|value| [value[0], value[1], value[2]])
+            .collect::<Vec<_>>()
+    };
+
+    let meshes = models
+        .into_iter()
+        .map(|model| {
+            let positions = to_vec3(&model.mesh.positions);
+            let indices = model.mesh.indices;
+            let normals = if model.mesh.normals.len() == model.mesh.positions.len() {
+                to_vec3(&model.mesh.normals)
+            } else {
+                compute_vertex_normals(&positions, &indices)
+            };
+            Mesh {
+                name: model.name,
+                positions,
+                normals,
+                indices,
+                // OBJ は慣習的に Y-up
+                transform: y_up_to_z_up(),
+                material: model.mesh.material_id.filter(|id| *id < materials.len()),
+            }
+        })
+        .collect();
+
+    Scene {
+        meshes,
+        materials: materials.into_iter().map(to_material).collect(),
+    }
+}
+
+fn to_material(material: tobj::Material) -> Material {
+    let parameter = |key: &str| {
+        material
+            .unknown_param
+            .get(key)
+            .map(|value| value.split_whitespace())
+    };
+    let scalar = |key: &str| parameter(key)?.next()?.parse::<f32>().ok();
+    let color = |key: &str| {
+        let values = parameter(key)?
+            .map(|value| value.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        <[f32; 3]>::try_from(values).ok()
+    };
+
+    // PBR 拡張 (Pr, Pm) がなければ鏡面反射指数から粗さを推定する
+    let roughness = scalar("Pr").unwrap_or_else(|| {
+        let shininess = material.shininess.unwrap_or(0.0).max(0.0);
+        (2.0 / (shininess + 2.0)).sqrt()
+    });
+    let [r, g, b] = material.diffuse.unwrap_or([1.0; 3]);
+    Material {
+        base_color: [r, g, b, material.dissolve.unwrap_or(1.0)],
+        metallic: scalar("Pm").unwrap_or(0.0),
+        roughness,
+        emissive: color("Ke").unwrap_or([0.0; 3]),
+        name: material.name,
+    }
+}
@@ -0,0 +1,313 @@
+// usd-rs で読み込んだステージからメッシュを取り出す
+// 多角形は faceVertexCounts から扇状に三角形分割し、xformOp を親から合成する
+
+use nalgebra_glm as glm;
+use usd_rs::serializer::{Definition, PropertyType};
+
+use super::{compute_vertex_normals, y_up_to_z_up, Mesh, Scene, SceneError};
+
+pub fn load(source: &str) -> Result<Scene, SceneError> {
+    let stage = usd_rs::serializer::from_str(source).map_err(|error| SceneError::Parse {
+        line: error.line(),
+        message: error.message().to_string(),
+    })?;
+
+    // Y-up のステージは Z-up に回転する
+    let root_transform = if stage.up_axis() == Some("Y") {
+        y_up_to_z_up()
+    } else {
+        glm::Mat4::identity()
+    };
+
+    let mut meshes = Vec::new();
+    for definition in stage.definitions() {
+        collect_meshes(definition, "", &root_transform, &mut meshes)?;
+    }
+    Ok(Scene {
+        meshes,
+        materials: Vec::new(),
+    })
+}
+
+fn collect_meshes(
+    definition: &Definition,
+    parent_path: &str,
+    parent_transform: &glm::Mat4,
+    meshes: &mut Vec<Mesh>,
+) -> Result<(), SceneError> {
+    let path = format!("{parent_path}/{}", definition.name);
+    let (local_transform, is_reset) = local_transform(definition, &path)?;
+    let transform = if is_reset {
+        local_transform
+    } else {
+        parent_transform * local_transform
+    };
+
+    if let Some(mesh) = to_mesh(definition, &path, transform)? {
+        meshes.push(mesh);
+    }
+    for child in &definition.children {
+        collect_meshes(child, &path, &transform, meshes)?;
+    }
+    Ok(())
+}
+
+// (xformOpOrder を合成した行列, !resetXformStack! があるか)
+fn local_transform(definition: &Definition, path: &str) -> Result<(glm::Mat4, bool), SceneError> {
+    let mut transform = glm::Mat4::identity();
+    let mut is_reset = false;
+    let Some(order) = definition
+        .properties
+        .iter()
+        .find_map(|property| match &property.property {
+            PropertyType::XformOpOrder(order) => Some(order),
+            _ => None,
+        })
+    else {
+        return Ok((transform, is_reset));
+    };
+
+    let invalid = |message: String| SceneError::InvalidMesh {
+        prim: path.to_string(),
+        message,
+    };
+    for op in order {
+        if op == "!resetXformStack!" {
+            is_reset = true;
+            transform = glm::Mat4::identity();
+            continue;
+        }
+        let (op, is_inverted) = match op.strip_prefix("!invert!") {
+            Some(op) => (op, true),
+            None => (op.as_str(), false),
+        };
+        let Some(values) =
+            definition
+                .properties
+                .iter()
+                .find_map(|property| match &property.property {
+                    PropertyType::XformOp(name, values) if name == op => Some(values),
+                    _ => None,
+                })
+        else {
+            return Err(invalid(format!("{op} is not defined")));
+        };
+
+        // xformOp:rotateXYZ:pivot のような接尾辞は無視する
+        let kind = op.split(':').nth(1).unwrap_or_default();
+        let matrix = xform_op_matrix(kind, values)
+            .ok_or_else(|| invalid(format!("unsupported xform op {op}")))?;
+        let matrix = if is_inverted {
+            matrix
+                .try_inverse()
+                .ok_or_else(|| invalid(format!("{op} is not invertible")))?
+        } else {
+            matrix
+        };
+
+        // 先頭の op が一番外側
+        transform *= matrix;
+    }
+    Ok((transform, is_reset))
+}
+
+fn xform_op_matrix(kind: &str, values: &[f64]) -> Option<glm::Mat4> {
+    let rotation = |axis: char, degree: f64| {
+        let axis = match axis {
+            'X' => glm::Vec3::x(),
+            'Y' => glm::Vec3::y(),
+            _ => glm::Vec3::z(),
+        };
+        glm::rotation((degree as f32).to_radians(), &axis)
+    };
+    let vec3 = || match values {
+        [x, y, z] => Some(glm::vec3(*x as f32, *y as f32, *z as f32)),
+        _ => None,
+    };
+
+    let matrix = match kind {
+        "translate" => glm::translation(&vec3()?),
+        "scale" => match values {
+            [scale] => glm::scaling(&glm::Vec3::repeat(*scale as f32)),
+            _ => glm::scaling(&vec3()?),
+        },
+        "rotateX" | "rotateY" | "rotateZ" => match values {
+            [degree] => rotation(kind.chars().last()?, *degree),
+            _ => return None,
+        },
+        // rotateXYZ は X, Y, Z の順に回す
+        "rotateXYZ" | "rotateXZY" | "rotateYXZ" | "rotateYZX" | "rotateZXY" | "rotateZYX"
+            if values.len() == 3 =>
+        {
+            kind["rotate".len()..]
+                .chars()
+                .zip(values)
+                .fold(glm::Mat4::identity(), |matrix, (axis, degree)| {
+                    rotation(axis, *degree) * matrix
+                })
+        }
+        // (実部, i, j, k)
+        "orient" => match values {
+            [w, x, y, z] => glm::quat_to_mat4(
+                &glm::quat(*x as f32, *y as f32, *z as f32, *w as f32).normalize(),
+            ),
+            _ => return None,
+        },
+        // USD は行ベクトルなので、行の順に並んだ値がそのまま列優先の行列になる
+        "transform" if values.len() == 16 => {
+            glm::Mat4::from_fn(|row, column| values[column * 4 + row] as f32)
+        }
+        _ => return None,
+    };
+    Some(matrix)
+}
+
+enum Interpolation {
+    Constant,
+    Uniform,
+    Vertex,
+    FaceVarying,
+}
+
+// points も faceVertexIndices もない定義 (Xform など) は None
+fn to_mesh(
+    definition: &Definition,
+    path: &str,
+    transform: glm::Mat4,
+) -> Result<Option<Mesh>, SceneError> {
+    let invalid = |message: &str| SceneError::InvalidMesh {
+        prim: path.to_string(),
+        message: message.to_string(),
+    };
+    let to_indices = |values: &[i32], name: &str| {
+        values
+            .iter()
+            .map(|value| usize::try_from(*value).ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid(&format!("{name} must not be negative")))
+    };
+
+    let mut points = None;
+    let mut normals = None;
+    let mut counts = None;
+    let mut indices = None;
+    let mut holes = Vec::new();
+    for property in &definition.properties {
+        match &property.property {
+            PropertyType::Points(values) => points = Some(values.clone()),
+            PropertyType::Normals(values) => {
+                normals = Some((values.clone(), property.interpolation.as_deref()));
+            }
+            PropertyType::FaceVertexCounts(values) => {
+                counts = Some(to_indices(values, "faceVertexCounts")?);
+            }
+            PropertyType::FaceVertexIndicies(values) => {
+                indices = Some(to_indices(values, "faceVertexIndices")?);
+            }
+            PropertyType::HoleIndices(values) => holes = to_indices(values, "holeIndices")?,
+            _ => {}
+        }
+    }
+
+    let (points, indices) = match (points, indices) {
+        (None, None) => return Ok(None),
+        (None, _) => return Err(invalid("points is missing")),
+        (_, None) => return Err(invalid("faceVertexIndices is missing")),
+        (Some(points), Some(indices)) => (points, indices),
+    };
+    let counts = counts.ok_or_else(|| invalid("faceVertexCounts is missing"))?;
+    if counts.iter().sum::<usize>() != indices.len() {
+        return Err(invalid("faceVertexCounts does not match faceVertexIndices"));
+    }
+    if indices.iter().any(|index| *index >= points.len()) {
+        return Err(invalid("faceVertexIndices is out of range"));
+    }
+
+    // interpolation の指定がなければ要素数から推測する
+    let normals_and_interpolation = match normals {
+        None => None,
+        Some((normals, specified_interpolation)) => {
+            let interpolation = match specified_interpolation {
+                Some("constant") => Interpolation::Constant,
+                Some("uniform") => Interpolation::Uniform,
+                Some("vertex" | "varying") => Interpolation::Vertex,
+                Some("faceVarying") => Interpolation::FaceVarying,
+                Some(_) => return Err(invalid("unknown normal interpolation")),
+                None if normals.len() == points.len() => Interpolation::Vertex,
+                None if normals.len() == indices.len() => Interpolation::FaceVarying,
+                None if normals.len() == counts.len() => Interpolation::Uniform,
+                None if normals.len() == 1 => Interpolation::Constant,
+                None => return Err(invalid("cannot infer normal interpolation")),
+            };
+            let expected_count = match interpolation {
+                Interpolation::Constant => 1,
+                Interpolation::Uniform => counts.len(),
+                Interpolation::Vertex => points.len(),
+                Interpolation::FaceVarying => indices.len(),
+            };
+            if normals.len() != expected_count {
+                return Err(invalid("normal count does not match its interpolation"));
+            }
+            Some((normals, interpolation))
+        }
+    };
+
+    // 多角形を扇状に三角形分割する (凹多角形は考慮しない)
+    // (面の番号, 面頂点の番号) の三角形の列
+    let mut triangles = Vec::new();
+    let mut offset = 0;
+    for (face, count) in counts.iter().enumerate() {
+        if *count >= 3 && !holes.contains(&face) {
+            for corner in 1..count - 1 {
+                triangles.push((face, [offset, offset + corner, offset + corner + 1]));
+            }
+        }
+        offset += count;
+    }
+
+    let mesh = match normals_and_interpolation {
+        // 頂点を共有できる
+        None | Some((_, Interpolation::Vertex)) => {
+            let triangle_indices = triangles
+                .iter()
+                .flat_map(|(_, triangle)| triangle.map(|corner| indices[corner] as u32))
+                .collect::<Vec<_>>();
+            let normals = match normals_and_interpolation {
+                Some((normals, _)) => normals,
+                None => compute_vertex_normals(&points, &triangle_indices),
+            };
+            Mesh {
+                name: path.to_string(),
+                positions: points,
+                normals,
+                indices: triangle_indices,
+                transform,
+                material: None,
+            }
+        }
+        // 面頂点ごとに頂点を分ける
+        Some((normals, interpolation)) => {
+            let mut mesh = Mesh {
+                name: path.to_string(),
+                positions: Vec::new(),
+                normals: Vec::new(),
+                indices: Vec::new(),
+                transform,
+                material: None,
+            };
+            for (face, triangle) in &triangles {
+                for corner in triangle {
+                    mesh.indices.push(mesh.positions.len() as u32);
+                    mesh.positions.push(points[indices[*corner]]);
+                    mesh.normals.push(match interpolation {
+                        Interpolation::Constant => normals[0],
+                        Interpolation::Uniform => normals[*face],
+                        _ => normals[*corner],
+                    });
+                }
+            }
+            mesh
+        }
+    };
+    Ok(Some(mesh))
+}
@@ -0,0 +1,154 @@
+// デモが使うシェーダーの一覧
+// 既定では build.rs が shaderlib で GLSL から変換した WGSL を埋め込んで使う
+// hot-reload フィーチャーを有効にすると、実行中にコンパイルし直した WGSL で差し替えられる
+
+mod reflection;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+mod reload;
+
+use std::borrow::Cow;
+
+pub(crate) use reflection::assert_std140;
+pub use reflection::{
+    bind_group_layout_entries, create_bind_group_layout, with_dynamic_offsets, VertexLayout,
+};
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+pub use reload::{record_shaders, set_shader_override, ShaderReloader, ShaderUpdate};
+
+// GLSL のブロックと構造体の std140 のレイアウト
+// GLSL のファイルごとのモジュールに、大きさ (SIZE) とメンバーのオフセットの定数がある
+pub mod layouts {
+    include!(concat!(env!("OUT_DIR"), "/layouts.rs"));
+}
+
+// シェーダーが使うリソース
+pub struct ShaderResource {
+    pub group: u32,
+    pub binding: u32,
+    pub ty: wgpu::BindingType,
+}
+
+pub struct ShaderEntry {
+    // デモが参照するときの名前
+    pub name: &'static str,
+    // resources/shaders にある GLSL のファイル名 (拡張子 .vs / .fs でステージを判断する)
+    pub source: &'static str,
+    // コンパイル時に定義するマクロ (名前, 値)
+    pub defines: &'static [(&'static str, &'static str)],
+    // build.rs が変換した WGSL
+    pub wgsl: &'static str,
+    pub stage: wgpu::ShaderStages,
+    // group, binding の順
+    pub resources: &'static [ShaderResource],
+    // 頂点シェーダーの入力 (location, フォーマット)
+    pub vertex_inputs: &'static [(u32, wgpu::VertexFormat)],
+}
+
+// build.rs が OUT_DIR に書き出した shaders.rs から呼ばれる
+macro_rules! shader {
+    (
+        $name:literal,
+        $source:literal,
+        [$(($define:literal, $value:literal)),*],
+        $stage:ident,
+        [$(($group:literal, $binding:literal, $($resource:tt)*)),*],
+        [$(($location:literal, $format:ident)),*]
+    ) => {
+        ShaderEntry {
+            name: $name,
+            source: $source,
+            defines: &[$(($define, $value)),*],
+            wgsl: include_str!(concat!(env!("OUT_DIR"), "/", $name, ".wgsl")),
+            stage: wgpu::ShaderStages::$stage,
+            resources: &[$(ShaderResource {
+                group: $group,
+                binding: $binding,
+                ty: binding_type!($($resource)*),
+            }),*],
+            vertex_inputs: &[$(($location, wgpu::VertexFormat::$format)),*],
+        }
+    };
+}
+
+// リソースの種類ごとの既定のバインディング
+// 動的オフセットやフィルタリングしないテクスチャーは使う側で書き換える
+macro_rules! binding_type {
+    (uniform($size:literal)) => {
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new($size),
+        }
+    };
+    (storage($size:literal, $read_only:literal)) => {
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage {
+                read_only: $read_only,
+            },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new($size),
+        }
+    };
+    (texture($dimension:ident, Float, $multisampled:literal)) => {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::$dimension,
+            multisampled: $multisampled,
+        }
+    };
+    (texture($dimension:ident, $sample_type:ident, $multisampled:literal)) => {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::$sample_type,
+            view_dimension: wgpu::TextureViewDimension::$dimension,
+            multisampled: $multisampled,
+        }
+    };
+    (sampler(true)) => {
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)
+    };
+    (sampler(false)) => {
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+    };
+}
+
+// resources/shaders/shaders.ron に書いたもの
+pub const SHADERS: &[ShaderEntry] = include!(concat!(env!("OUT_DIR"), "/shaders.rs"));
+
+// 名前に対応する WGSL
+// 差し替えられていればそちらを返す
+pub fn shader_source(name: &'static str) -> Cow<'static, str> {
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    if let Some(source) = reload::shader_override(name) {
+        return Cow::Owned(source);
+    }
+
+    Cow::Borrowed(shader_entry(name).wgsl)
+}
+
+pub fn shader_entry(name: &str) -> &'static ShaderEntry {
+    SHADERS
+        .iter()
+        .find(|entry| entry.name == name)
+        .unwrap_or_else(|| panic!("unknown shader: {name}"))
+}
+
+// source を defines のマクロでコンパイルしたシェーダーの名前
+// マクロの順番は問わない。shaders.ron でコンパイルしていない組み合わせならパニックする
+pub fn shader_variant(source: &str, defines: &[(&str, &str)]) -> &'static str {
+    SHADERS
+        .iter()
+        .find(|entry| {
+            entry.source == source
+                && entry.defines.len() == defines.len()
+                && defines.iter().all(|define| entry.defines.contains(define))
+        })
+        .map(|entry| entry.name)
+        .unwrap_or_else(|| panic!("{source} is not compiled with {defines:?}"))
+}
+
+pub fn create_shader_module(device: &wgpu::Device, name: &'static str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(name),
+        source: wgpu::ShaderSource::Wgsl(shader_source(name)),
+    })
+}
@@ -0,0 +1,142 @@
+// build.rs が naga のモジュールから取り出したリソースと頂点入力から、パイプラインのレイアウトを作る
+
+use super::shader_entry;
+
+// shaders で使うリソースから、group のバインドグループレイアウトのエントリーを作る
+// 同じバインディングを複数のシェーダーで使っていれば visibility をまとめる
+pub fn bind_group_layout_entries(shaders: &[&str], group: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+    let mut entries: Vec<wgpu::BindGroupLayoutEntry> = Vec::new();
+    for name in shaders {
+        let shader = shader_entry(name);
+        for resource in shader
+            .resources
+            .iter()
+            .filter(|resource| resource.group == group)
+        {
+            match entries
+                .iter_mut()
+                .find(|entry| entry.binding == resource.binding)
+            {
+                Some(entry) => {
+                    assert_eq!(
+                        entry.ty, resource.ty,
+                        "{name} uses binding {} of group {group} with a different type",
+                        resource.binding
+                    );
+                    entry.visibility |= shader.stage;
+                }
+                None => entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: resource.binding,
+                    visibility: shader.stage,
+                    ty: resource.ty,
+                    count: None,
+                }),
+            }
+        }
+    }
+    entries.sort_by_key(|entry| entry.binding);
+    entries
+}
+
+// entries のバッファーを動的オフセットで切り替えて使うようにする
+pub fn with_dynamic_offsets(
+    mut entries: Vec<wgpu::BindGroupLayoutEntry>,
+) -> Vec<wgpu::BindGroupLayoutEntry> {
+    for entry in &mut entries {
+        if let wgpu::BindingType::Buffer {
+            has_dynamic_offset, ..
+        } = &mut entry.ty
+        {
+            *has_dynamic_offset = true;
+        }
+    }
+    entries
+}
+
+pub fn create_bind_group_layout(
+    device: &wgpu::Device,
+    shaders: &[&str],
+    group: u32,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &bind_group_layout_entries(shaders, group),
+    })
+}
+
+// 頂点シェーダーの入力のうち locations のものを、この順に詰めて並べた頂点バッファー
+pub struct VertexLayout {
+    pub array_stride: wgpu::BufferAddress,
+    pub attributes: Vec<wgpu::VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(shader: &str, locations: &[u32]) -> Self {
+        let inputs = shader_entry(shader).vertex_inputs;
+        let mut array_stride = 0;
+        let attributes = locations
+            .iter()
+            .map(|location| {
+                let (_, format) = inputs
+                    .iter()
+                    .find(|(input_location, _)| input_location == location)
+                    .unwrap_or_else(|| panic!("{shader} has no input at location {location}"));
+                let attribute = wgpu::VertexAttribute {
+                    format: *format,
+                    offset: array_stride,
+                    shader_location: *location,
+                };
+                array_stride += format.size();
+                attribute
+            })
+            .collect();
+        Self {
+            array_stride,
+            attributes,
+        }
+    }
+
+    pub fn buffer_layout(&self, step_mode: wgpu::VertexStepMode) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.array_stride,
+            step_mode,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+// Rust の #[repr(C)] の構造体が、GLSL のブロックと同じ std140 のレイアウトになっているかをコンパイル時に確かめる
+//   assert_std140!(TriangleUniform, triangle_fs::material, { color: U_COLOR });
+// layouts の中のモジュールと、フィールドごとに対応するメンバーの定数を書く
+macro_rules! assert_std140 {
+    ($type:ty, $module:ident::$block:ident, { $($field:ident: $member:ident),* $(,)? }) => {
+        const _: () = {
+            use $crate::shaders::layouts::$module::$block as layout;
+            assert!(
+                std::mem::size_of::<$type>() == layout::SIZE,
+                concat!(
+                    "size of ",
+                    stringify!($type),
+                    " does not match ",
+                    stringify!($module::$block)
+                )
+            );
+            $(
+                assert!(
+                    std::mem::offset_of!($type, $field) == layout::$member,
+                    concat!(
+                        stringify!($type),
+                        "::",
+                        stringify!($field),
+                        " is not at the offset of ",
+                        stringify!($module::$block),
+                        "::",
+                        stringify!($member)
+                    )
+                );
+            )*
+        };
+    };
+}
+
+pub(crate) use assert_std140;
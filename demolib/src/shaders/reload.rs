@@ -0,0 +1,201 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{OnceLock, RwLock},
+    time::SystemTime,
+};
+
+use shaderlib::ShaderError;
+
+use super::SHADERS;
+
+// 名前ごとに差し替えた WGSL
+fn overrides() -> &'static RwLock<HashMap<&'static str, String>> {
+    static OVERRIDES: OnceLock<RwLock<HashMap<&'static str, String>>> = OnceLock::new();
+    OVERRIDES.get_or_init(Default::default)
+}
+
+thread_local! {
+    // record_shaders の実行中に参照されたシェーダーの名前
+    static RECORDING: RefCell<Option<HashSet<&'static str>>> = const { RefCell::new(None) };
+}
+
+pub(super) fn shader_override(name: &'static str) -> Option<String> {
+    RECORDING.with(|recording| {
+        if let Some(names) = recording.borrow_mut().as_mut() {
+            names.insert(name);
+        }
+    });
+    overrides().read().unwrap().get(name).cloned()
+}
+
+// 差し替える WGSL を設定して、それまでの値を返す
+// None なら埋め込みの WGSL に戻す
+pub fn set_shader_override(name: &'static str, source: Option<String>) -> Option<String> {
+    let mut overrides = overrides().write().unwrap();
+    match source {
+        Some(source) => overrides.insert(name, source),
+        None => overrides.remove(name),
+    }
+}
+
+// f の中で参照されたシェーダーの名前を集める
+// デモを作り直すときに、どのシェーダーの変更に影響されるかを知るために使う
+pub fn record_shaders<R>(f: impl FnOnce() -> R) -> (R, HashSet<&'static str>) {
+    let previous = RECORDING.with(|recording| recording.replace(Some(HashSet::new())));
+    let result = f();
+    let names = RECORDING
+        .with(|recording| recording.replace(previous))
+        .unwrap_or_default();
+    (result, names)
+}
+
+// コンパイルし直したシェーダー
+pub struct ShaderUpdate {
+    pub name: &'static str,
+    // GLSL のファイル名
+    pub source: &'static str,
+    pub wgsl: String,
+}
+
+// resources/shaders の GLSL の更新日時を見張って、変更されたものをコンパイルし直す
+// #include しているファイルが変わったときもコンパイルし直す
+pub struct ShaderReloader {
+    directory: PathBuf,
+    // GLSL のファイル名ごとの、読み込むファイルとその更新日時
+    modified: HashMap<&'static str, Vec<(PathBuf, Option<SystemTime>)>>,
+    // GLSL のファイル名ごとのエラー
+    errors: BTreeMap<&'static str, String>,
+}
+
+impl Default for ShaderReloader {
+    fn default() -> Self {
+        Self::new(PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/resources/shaders"
+        )))
+    }
+}
+
+impl ShaderReloader {
+    pub fn new(directory: PathBuf) -> Self {
+        let mut reloader = Self {
+            directory,
+            modified: HashMap::new(),
+            errors: BTreeMap::new(),
+        };
+        // 起動時点のものは埋め込みの WGSL と同じなので、更新日時だけ覚えておく
+        for entry in SHADERS {
+            reloader.watch(entry.source);
+        }
+        reloader
+    }
+
+    // (GLSL のファイル名, メッセージ)
+    pub fn errors(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.errors
+            .iter()
+            .map(|(source, message)| (*source, message.as_str()))
+    }
+
+    // パイプラインの作成に失敗したときなど、コンパイル以外のエラーを表示する
+    pub fn set_error(&mut self, source: &'static str, message: String) {
+        self.errors.insert(source, message);
+    }
+
+    pub fn clear_error(&mut self, source: &'static str) {
+        self.errors.remove(source);
+    }
+
+    // 変更された GLSL をコンパイルし直す
+    // ファイルの中のシェーダーがすべてコンパイルできたときだけ結果を返す
+    pub fn poll(&mut self) -> Vec<ShaderUpdate> {
+        let changed_sources: Vec<_> = self
+            .modified
+            .iter()
+            .filter(|(_, files)| {
+                files
+                    .iter()
+                    .any(|(path, modified)| modified_time(path) != *modified)
+            })
+            .map(|(source, _)| *source)
+            .collect();
+
+        let mut updates = Vec::new();
+        for source in changed_sources {
+            // #include が増減していることもあるので、見張るファイルを調べ直す
+            self.watch(source);
+            match self.compile_source(source) {
+                Ok(mut compiled) => {
+                    self.errors.remove(source);
+                    updates.append(&mut compiled);
+                }
+                Err(message) => {
+                    self.errors.insert(source, message);
+                }
+            }
+        }
+        updates
+    }
+
+    fn watch(&mut self, source: &'static str) {
+        let path = self.directory.join(source);
+        let files = shaderlib::preprocess(&path)
+            .map(|preprocessed| preprocessed.files)
+            .unwrap_or_else(|_| vec![path]);
+        let files = files
+            .into_iter()
+            .map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            })
+            .collect();
+        self.modified.insert(source, files);
+    }
+
+    fn compile_source(&self, source: &'static str) -> Result<Vec<ShaderUpdate>, String> {
+        let path = self.directory.join(source);
+        SHADERS
+            .iter()
+            .filter(|entry| entry.source == source)
+            .map(|entry| {
+                let defines = entry
+                    .defines
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+                let compiled = shaderlib::compile_file(&path, &defines)
+                    .map_err(|error| self.message(error))?;
+                Ok(ShaderUpdate {
+                    name: entry.name,
+                    source,
+                    wgsl: compiled.wgsl,
+                })
+            })
+            .collect()
+    }
+
+    // 表示しやすいように、ファイルの場所を resources/shaders からの相対パスにする
+    fn message(&self, error: ShaderError) -> String {
+        let ShaderError::Compile(diagnostics) = error else {
+            return error.to_string();
+        };
+        diagnostics
+            .into_iter()
+            .map(|mut diagnostic| {
+                if let Ok(file) = diagnostic.file.strip_prefix(&self.directory) {
+                    diagnostic.file = file.to_path_buf();
+                }
+                diagnostic.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,66 @@
+// セッションやプロジェクトファイルに保存するデモのパラメーター
+// 保存先の形式 (RON / JSON) によらない値で持ち、構造を変えたらマイグレーションを足してバージョンを上げる
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DemoState {
+    pub version: u32,
+    pub params: serde_json::Value,
+}
+
+// バージョン n の値をバージョン n + 1 の値に変換する
+pub type Migration = fn(serde_json::Value) -> Result<serde_json::Value, StateError>;
+
+impl DemoState {
+    // migrations[n] はバージョン n から n + 1 への変換で、migrations の長さが最新のバージョン
+    pub fn new<T: Serialize>(params: &T, migrations: &[Migration]) -> Result<Self, StateError> {
+        Ok(Self {
+            version: migrations.len() as u32,
+            params: serde_json::to_value(params)?,
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, migrations: &[Migration]) -> Result<T, StateError> {
+        let latest = migrations.len() as u32;
+        if self.version > latest {
+            return Err(StateError::UnsupportedVersion {
+                version: self.version,
+                latest,
+            });
+        }
+        let params = migrations[self.version as usize..]
+            .iter()
+            .try_fold(self.params, |params, migrate| migrate(params))?;
+        Ok(serde_json::from_value(params)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum StateError {
+    // より新しいアプリで保存された
+    UnsupportedVersion { version: u32, latest: u32 },
+    Migration(String),
+    Serde(serde_json::Error),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::UnsupportedVersion { version, latest } => write!(
+                f,
+                "state version {version} is newer than the supported version {latest}"
+            ),
+            StateError::Migration(message) => write!(f, "failed to migrate state: {message}"),
+            StateError::Serde(error) => write!(f, "invalid state: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<serde_json::Error> for StateError {
+    fn from(error: serde_json::Error) -> Self {
+        StateError::Serde(error)
+    }
+}
@@ -0,0 +1,339 @@
+mod game;
+mod tetromino;
+
+use std::{any::Any, mem::size_of};
+
+use wgpu::util::DeviceExt;
+
+use crate::{shaders::create_shader_module, Demo, DemoParams, DemoState, StateError};
+
+pub use game::{Piece, TetrisGame, TetrisInput, BOARD_HEIGHT, BOARD_WIDTH};
+pub use tetromino::{Rotation, TetrominoKind};
+
+// 盤面のマス + 次のテトリミノのプレビュー
+const MAX_INSTANCE_COUNT: usize = BOARD_WIDTH * BOARD_HEIGHT + 4;
+
+// 1 マスの大きさ (正規化デバイス座標)
+const CELL_SIZE: f32 = 0.09;
+const CELL_GAP: f32 = 0.006;
+const BOARD_ORIGIN: [f32; 2] = [-0.6, -0.9];
+const PREVIEW_ORIGIN: [f32; 2] = [0.45, 0.5];
+
+const EMPTY_COLOR: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+
+pub struct TetrisParams {
+    pub seed: u64,
+    pub game: TetrisGame,
+}
+
+impl Default for TetrisParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            game: TetrisGame::new(0),
+        }
+    }
+}
+
+impl DemoParams for TetrisParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    // 途中のゲームは残さず、シードだけ保存して新しいゲームを始める
+    fn save_state(&self) -> Option<DemoState> {
+        DemoState::new(&self.seed, &[]).ok()
+    }
+
+    fn load_state(&mut self, state: DemoState) -> Result<(), StateError> {
+        self.seed = state.decode(&[])?;
+        self.game = TetrisGame::new(self.seed);
+        Ok(())
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("Score: {}", self.game.score()));
+        ui.label(format!("Level: {}", self.game.level()));
+        ui.label(format!("Lines: {}", self.game.lines()));
+        if self.game.is_game_over() {
+            ui.label("Game Over");
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut self.seed));
+        });
+        if ui.button("Restart").clicked() {
+            self.game = TetrisGame::new(self.seed);
+        }
+
+        ui.separator();
+        ui.label("Left / Right: Move");
+        ui.label("Down: Soft drop");
+        ui.label("Space: Hard drop");
+        ui.label("Up / X: Rotate right");
+        ui.label("Z: Rotate left");
+    }
+
+    fn interact(&mut self, response: &egui::Response) {
+        use egui::{Key, Modifiers};
+
+        // キャンバスにフォーカスがあるときだけキー入力を受け付ける
+        if response.has_focus() {
+            let key_map = [
+                (Key::ArrowLeft, TetrisInput::MoveLeft),
+                (Key::ArrowRight, TetrisInput::MoveRight),
+                (Key::ArrowDown, TetrisInput::SoftDrop),
+                (Key::Space, TetrisInput::HardDrop),
+                (Key::ArrowUp, TetrisInput::RotateClockwise),
+                (Key::X, TetrisInput::RotateClockwise),
+                (Key::Z, TetrisInput::RotateCounterClockwise),
+            ];
+            response.ctx.input_mut(|input| {
+                for (key, tetris_input) in key_map {
+                    if input.consume_key(Modifiers::NONE, key) {
+                        self.game.apply(tetris_input);
+                    }
+                }
+            });
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        self.game.tick(dt);
+    }
+}
+
+#[derive(bytemuck::NoUninit, Clone, Copy)]
+#[repr(C)]
+struct CellInstance {
+    // x, y, width, height
+    rect: [f32; 4],
+    color: [f32; 4],
+}
+
+impl CellInstance {
+    fn new(origin: [f32; 2], x: usize, y: usize, color: [f32; 4]) -> Self {
+        Self {
+            rect: [
+                origin[0] + x as f32 * CELL_SIZE + CELL_GAP * 0.5,
+                origin[1] + y as f32 * CELL_SIZE + CELL_GAP * 0.5,
+                CELL_SIZE - CELL_GAP,
+                CELL_SIZE - CELL_GAP,
+            ],
+            color,
+        }
+    }
+}
+
+pub struct Tetris<'a> {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+    // 盤面がつぶれないように中央の正方形に描く (x, y, 幅, 高さ)
+    viewport: [f32; 4],
+    _marker: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Tetris<'a> {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let vertex_shader_module = create_shader_module(device, "tetris.vs");
+        let pixel_shader_module = create_shader_module(device, "tetris.fs");
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader_module,
+                entry_point: "main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: (size_of::<f32>() * 2) as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[wgpu::VertexAttribute {
+                            format: wgpu::VertexFormat::Float32x2,
+                            offset: 0,
+                            shader_location: 0,
+                        }],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: size_of::<CellInstance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x4,
+                                offset: 0,
+                                shader_location: 1,
+                            },
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x4,
+                                offset: (size_of::<f32>() * 4) as wgpu::BufferAddress,
+                                shader_location: 2,
+                            },
+                        ],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &pixel_shader_module,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: Default::default(),
+            multisample: Default::default(),
+            multiview: Default::default(),
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[
+                0.0f32, 1.0, //
+                0.0, 0.0, //
+                1.0, 0.0, //
+                1.0, 1.0, //
+            ]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[0u16, 1, 2, 0, 2, 3]),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            size: (size_of::<CellInstance>() * MAX_INSTANCE_COUNT) as u64,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            instance_count: 0,
+            viewport: [0.0, 0.0, 1.0, 1.0],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, game: &TetrisGame) {
+        let mut colors = [[EMPTY_COLOR; BOARD_WIDTH]; BOARD_HEIGHT];
+        for (y, row) in colors.iter_mut().enumerate() {
+            for (x, color) in row.iter_mut().enumerate() {
+                if let Some(kind) = game.cell(x, y) {
+                    *color = kind.color();
+                }
+            }
+        }
+
+        let mut set_piece = |piece: &Piece, scale: f32| {
+            let color = piece.kind.color();
+            for (x, y) in piece.cells() {
+                if (0..BOARD_HEIGHT as i32).contains(&y) {
+                    colors[y as usize][x as usize] = [
+                        color[0] * scale,
+                        color[1] * scale,
+                        color[2] * scale,
+                        color[3],
+                    ];
+                }
+            }
+        };
+        if let Some(ghost) = game.ghost_piece() {
+            set_piece(&ghost, 0.3);
+        }
+        if let Some(piece) = game.current_piece() {
+            set_piece(piece, 1.0);
+        }
+
+        let mut instances = Vec::with_capacity(MAX_INSTANCE_COUNT);
+        for (y, row) in colors.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                // ゲームオーバー時は盤面を暗くする
+                let color = if game.is_game_over() {
+                    [color[0] * 0.4, color[1] * 0.4, color[2] * 0.4, color[3]]
+                } else {
+                    *color
+                };
+                instances.push(CellInstance::new(BOARD_ORIGIN, x, y, color));
+            }
+        }
+
+        let next_kind = game.next_kind();
+        for (x, y) in next_kind.cells(Rotation::Spawn) {
+            instances.push(CellInstance::new(
+                PREVIEW_ORIGIN,
+                x as usize,
+                y as usize,
+                next_kind.color(),
+            ));
+        }
+
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        self.instance_count = instances.len() as u32;
+    }
+
+    pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let [x, y, width, height] = self.viewport;
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..6, 0, 0..self.instance_count);
+    }
+}
+
+impl<'a> Demo for Tetris<'a> {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self::new(device, target_format)
+    }
+
+    fn name(&self) -> &'static str {
+        "Tetris"
+    }
+
+    fn create_params(&self) -> Box<dyn DemoParams> {
+        Box::<TetrisParams>::default()
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, width: u32, height: u32) {
+        let size = width.min(height) as f32;
+        self.viewport = [
+            (width as f32 - size) * 0.5,
+            (height as f32 - size) * 0.5,
+            size,
+            size,
+        ];
+    }
+
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams) {
+        let Some(params) = params.as_any().downcast_ref::<TetrisParams>() else {
+            return;
+        };
+        Tetris::update(self, queue, &params.game);
+    }
+
+    fn draw<'b>(&'b self, render_pass: &mut wgpu::RenderPass<'b>) {
+        Tetris::draw(self, render_pass);
+    }
+}
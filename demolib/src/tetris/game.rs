@@ -0,0 +1,324 @@
+use std::collections::VecDeque;
+
+use crate::random::Random;
+
+use super::tetromino::{Rotation, TetrominoKind};
+
+pub const BOARD_WIDTH: usize = 10;
+pub const BOARD_HEIGHT: usize = 20;
+
+// 画面外に 2 行分の余裕を持たせてそこからテトリミノを出現させる
+const BOARD_BUFFER_HEIGHT: usize = BOARD_HEIGHT + 2;
+
+// 接地してから固定されるまでの猶予 (秒)
+const LOCK_DELAY: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TetrisInput {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+}
+
+// 7 種類のテトリミノを 1 セットとしてシャッフルして払い出す
+#[derive(Clone, Debug)]
+pub struct Bag {
+    random: Random,
+    queue: VecDeque<TetrominoKind>,
+}
+
+impl Bag {
+    pub fn new(seed: u64) -> Self {
+        let mut bag = Self {
+            random: Random::new(seed),
+            queue: VecDeque::default(),
+        };
+        bag.refill();
+        bag
+    }
+
+    pub fn next(&mut self) -> TetrominoKind {
+        let kind = self.queue.pop_front().unwrap();
+        if self.queue.len() < TetrominoKind::ALL.len() {
+            self.refill();
+        }
+        kind
+    }
+
+    pub fn peek(&self) -> TetrominoKind {
+        self.queue[0]
+    }
+
+    fn refill(&mut self) {
+        // Fisher–Yates
+        let mut kinds = TetrominoKind::ALL;
+        for index in (1..kinds.len()).rev() {
+            let target = self.random.next_range(index + 1);
+            kinds.swap(index, target);
+        }
+        self.queue.extend(kinds);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Piece {
+    pub kind: TetrominoKind,
+    pub rotation: Rotation,
+    // バウンディングボックスの左下の盤面上の位置
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Piece {
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.kind
+            .cells(self.rotation)
+            .into_iter()
+            .map(|(x, y)| (self.x + x, self.y + y))
+    }
+
+    fn moved(&self, dx: i32, dy: i32) -> Self {
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TetrisGame {
+    // board[y][x] で y = 0 が一番下の行
+    board: [[Option<TetrominoKind>; BOARD_WIDTH]; BOARD_BUFFER_HEIGHT],
+    current: Option<Piece>,
+    bag: Bag,
+    score: u32,
+    lines: u32,
+    gravity_timer: f32,
+    lock_timer: f32,
+    is_game_over: bool,
+}
+
+impl TetrisGame {
+    pub fn new(seed: u64) -> Self {
+        let mut game = Self {
+            board: [[None; BOARD_WIDTH]; BOARD_BUFFER_HEIGHT],
+            current: None,
+            bag: Bag::new(seed),
+            score: 0,
+            lines: 0,
+            gravity_timer: 0.0,
+            lock_timer: 0.0,
+            is_game_over: false,
+        };
+        game.spawn();
+        game
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<TetrominoKind> {
+        self.board[y][x]
+    }
+
+    // 任意の盤面から始めたいとき用
+    pub fn set_cell(&mut self, x: usize, y: usize, kind: Option<TetrominoKind>) {
+        self.board[y][x] = kind;
+    }
+
+    pub fn current_piece(&self) -> Option<&Piece> {
+        self.current.as_ref()
+    }
+
+    // ハードドロップしたときに落ちる位置
+    pub fn ghost_piece(&self) -> Option<Piece> {
+        let mut piece = self.current?;
+        while self.fits(&piece.moved(0, -1)) {
+            piece = piece.moved(0, -1);
+        }
+        Some(piece)
+    }
+
+    pub fn next_kind(&self) -> TetrominoKind {
+        self.bag.peek()
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    pub fn level(&self) -> u32 {
+        self.lines / 10 + 1
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.is_game_over
+    }
+
+    // 1 行落下するのにかかる時間 (秒)
+    // https://tetris.wiki/Marathon
+    pub fn gravity_interval(&self) -> f32 {
+        let level = self.level().min(20) as f32;
+        (0.8 - (level - 1.0) * 0.007).powf(level - 1.0)
+    }
+
+    pub fn apply(&mut self, input: TetrisInput) {
+        if self.is_game_over {
+            return;
+        }
+        let Some(piece) = self.current else {
+            return;
+        };
+
+        match input {
+            TetrisInput::MoveLeft => {
+                self.try_move(piece.moved(-1, 0));
+            }
+            TetrisInput::MoveRight => {
+                self.try_move(piece.moved(1, 0));
+            }
+            TetrisInput::SoftDrop => {
+                if self.try_move(piece.moved(0, -1)) {
+                    self.score += 1;
+                    self.gravity_timer = 0.0;
+                }
+            }
+            TetrisInput::HardDrop => {
+                let ghost = self.ghost_piece().unwrap();
+                self.score += 2 * (piece.y - ghost.y) as u32;
+                self.current = Some(ghost);
+                self.lock();
+            }
+            TetrisInput::RotateClockwise => {
+                self.try_rotate(piece.rotation.clockwise());
+            }
+            TetrisInput::RotateCounterClockwise => {
+                self.try_rotate(piece.rotation.counter_clockwise());
+            }
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) {
+        if self.is_game_over {
+            return;
+        }
+        let Some(piece) = self.current else {
+            return;
+        };
+
+        if self.fits(&piece.moved(0, -1)) {
+            self.lock_timer = 0.0;
+            self.gravity_timer += delta_time;
+            let interval = self.gravity_interval();
+            while self.gravity_timer >= interval {
+                self.gravity_timer -= interval;
+                let Some(piece) = self.current else {
+                    break;
+                };
+                if !self.try_move(piece.moved(0, -1)) {
+                    break;
+                }
+            }
+        } else {
+            self.gravity_timer = 0.0;
+            self.lock_timer += delta_time;
+            if self.lock_timer >= LOCK_DELAY {
+                self.lock();
+            }
+        }
+    }
+
+    fn fits(&self, piece: &Piece) -> bool {
+        piece.cells().all(|(x, y)| {
+            (0..BOARD_WIDTH as i32).contains(&x)
+                && (0..BOARD_BUFFER_HEIGHT as i32).contains(&y)
+                && self.board[y as usize][x as usize].is_none()
+        })
+    }
+
+    fn try_move(&mut self, piece: Piece) -> bool {
+        if !self.fits(&piece) {
+            return false;
+        }
+        self.current = Some(piece);
+        self.lock_timer = 0.0;
+        true
+    }
+
+    fn try_rotate(&mut self, rotation: Rotation) -> bool {
+        let Some(piece) = self.current else {
+            return false;
+        };
+        let rotated = Piece { rotation, ..piece };
+        for (dx, dy) in piece.kind.kicks(piece.rotation, rotation) {
+            if self.try_move(rotated.moved(*dx, *dy)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn lock(&mut self) {
+        let Some(piece) = self.current.take() else {
+            return;
+        };
+        for (x, y) in piece.cells() {
+            self.board[y as usize][x as usize] = Some(piece.kind);
+        }
+
+        let cleared = self.clear_lines();
+        let base = match cleared {
+            1 => 100,
+            2 => 300,
+            3 => 500,
+            4 => 800,
+            _ => 0,
+        };
+        self.score += base * self.level();
+        self.lines += cleared;
+
+        // 見えている範囲より上で固定されたらゲームオーバー
+        if piece.cells().all(|(_, y)| y >= BOARD_HEIGHT as i32) {
+            self.is_game_over = true;
+            return;
+        }
+        self.spawn();
+    }
+
+    fn clear_lines(&mut self) -> u32 {
+        let mut cleared = 0;
+        let mut y = 0;
+        while y < BOARD_BUFFER_HEIGHT {
+            if self.board[y].iter().all(|cell| cell.is_some()) {
+                self.board.copy_within(y + 1.., y);
+                self.board[BOARD_BUFFER_HEIGHT - 1] = [None; BOARD_WIDTH];
+                cleared += 1;
+            } else {
+                y += 1;
+            }
+        }
+        cleared
+    }
+
+    fn spawn(&mut self) {
+        let kind = self.bag.next();
+        let piece = Piece {
+            kind,
+            rotation: Rotation::Spawn,
+            x: (BOARD_WIDTH as i32 - 4) / 2 + if kind == TetrominoKind::O { 1 } else { 0 },
+            y: BOARD_HEIGHT as i32 - 1,
+        };
+        self.gravity_timer = 0.0;
+        self.lock_timer = 0.0;
+        if self.fits(&piece) {
+            self.current = Some(piece);
+        } else {
+            self.is_game_over = true;
+        }
+    }
+}
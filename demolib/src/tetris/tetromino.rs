@@ -0,0 +1,137 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TetrominoKind {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+}
+
+impl TetrominoKind {
+    pub const ALL: [TetrominoKind; 7] = [
+        TetrominoKind::I,
+        TetrominoKind::O,
+        TetrominoKind::T,
+        TetrominoKind::S,
+        TetrominoKind::Z,
+        TetrominoKind::J,
+        TetrominoKind::L,
+    ];
+
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            TetrominoKind::I => [0.0, 0.9, 0.9, 1.0],
+            TetrominoKind::O => [0.9, 0.9, 0.0, 1.0],
+            TetrominoKind::T => [0.6, 0.0, 0.9, 1.0],
+            TetrominoKind::S => [0.0, 0.9, 0.0, 1.0],
+            TetrominoKind::Z => [0.9, 0.0, 0.0, 1.0],
+            TetrominoKind::J => [0.0, 0.2, 0.9, 1.0],
+            TetrominoKind::L => [0.9, 0.5, 0.0, 1.0],
+        }
+    }
+
+    // 回転の基準になるバウンディングボックスの一辺の長さ
+    fn box_size(&self) -> i32 {
+        match self {
+            TetrominoKind::I => 4,
+            TetrominoKind::O => 2,
+            _ => 3,
+        }
+    }
+
+    // 出現時 (回転状態 0) のブロック座標。y は上向き
+    fn spawn_cells(&self) -> [(i32, i32); 4] {
+        match self {
+            TetrominoKind::I => [(0, 2), (1, 2), (2, 2), (3, 2)],
+            TetrominoKind::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            TetrominoKind::T => [(0, 1), (1, 1), (2, 1), (1, 2)],
+            TetrominoKind::S => [(0, 1), (1, 1), (1, 2), (2, 2)],
+            TetrominoKind::Z => [(0, 2), (1, 2), (1, 1), (2, 1)],
+            TetrominoKind::J => [(0, 2), (0, 1), (1, 1), (2, 1)],
+            TetrominoKind::L => [(2, 2), (0, 1), (1, 1), (2, 1)],
+        }
+    }
+
+    // 回転状態 rotation (0, R, 2, L) のときのバウンディングボックス内のブロック座標
+    pub fn cells(&self, rotation: Rotation) -> [(i32, i32); 4] {
+        let size = self.box_size();
+        let mut cells = self.spawn_cells();
+        for _ in 0..rotation.index() {
+            for cell in &mut cells {
+                // 時計回りに 90 度回転
+                *cell = (cell.1, size - 1 - cell.0);
+            }
+        }
+        cells
+    }
+
+    // SRS のウォールキック候補
+    // https://tetris.wiki/Super_Rotation_System
+    pub fn kicks(&self, from: Rotation, to: Rotation) -> &'static [(i32, i32); 5] {
+        use Rotation::*;
+        match self {
+            TetrominoKind::O => &[(0, 0); 5],
+            TetrominoKind::I => match (from, to) {
+                (Spawn, Right) => &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+                (Right, Spawn) => &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+                (Right, Reverse) => &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+                (Reverse, Right) => &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+                (Reverse, Left) => &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+                (Left, Reverse) => &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+                (Left, Spawn) => &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+                (Spawn, Left) => &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+                _ => &[(0, 0); 5],
+            },
+            _ => match (from, to) {
+                (Spawn, Right) => &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+                (Right, Spawn) => &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+                (Right, Reverse) => &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+                (Reverse, Right) => &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+                (Reverse, Left) => &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+                (Left, Reverse) => &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+                (Left, Spawn) => &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+                (Spawn, Left) => &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+                _ => &[(0, 0); 5],
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Spawn,
+    Right,
+    Reverse,
+    Left,
+}
+
+impl Rotation {
+    fn index(&self) -> usize {
+        match self {
+            Rotation::Spawn => 0,
+            Rotation::Right => 1,
+            Rotation::Reverse => 2,
+            Rotation::Left => 3,
+        }
+    }
+
+    pub fn clockwise(&self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Right,
+            Rotation::Right => Rotation::Reverse,
+            Rotation::Reverse => Rotation::Left,
+            Rotation::Left => Rotation::Spawn,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Left,
+            Rotation::Right => Rotation::Spawn,
+            Rotation::Reverse => Rotation::Right,
+            Rotation::Left => Rotation::Reverse,
+        }
+    }
+}
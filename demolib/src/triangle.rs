@@ -1,13 +1,59 @@
-use std::{borrow::Cow, mem::size_of};
+use std::{any::Any, mem::size_of};
 
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
-#[derive(bytemuck::NoUninit, Clone, Copy)]
-#[repr(C)]
+use crate::{
+    create_bind_group_layout,
+    shaders::{assert_std140, create_shader_module},
+    Demo, DemoParams, DemoState, StateError, Std140, VertexLayout,
+};
+
+// triangle.fs の Material と同じレイアウト
+#[derive(Std140, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TriangleParams {
     pub color: [f32; 3],
 }
 
+assert_std140!(TriangleParamsStd140, triangle_fs::material, { color: U_COLOR });
+
+impl Default for TriangleParams {
+    fn default() -> Self {
+        Self {
+            color: [0.1, 0.2, 0.3],
+        }
+    }
+}
+
+impl DemoParams for TriangleParams {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn save_state(&self) -> Option<DemoState> {
+        DemoState::new(self, &[]).ok()
+    }
+
+    fn load_state(&mut self, state: DemoState) -> Result<(), StateError> {
+        *self = state.decode(&[])?;
+        Ok(())
+    }
+
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Color");
+            ui.color_edit_button_rgb(&mut self.color);
+        });
+    }
+}
+
+// 頂点バッファーの中身 (NDC の xy)
+pub const TRIANGLE_VERTICES: [f32; 6] = [-0.5, -0.5, 0.5, -0.5, 0.0, 0.5];
+
 pub struct Triangle<'a> {
     render_pipeline: wgpu::RenderPipeline,
     bind_group: wgpu::BindGroup,
@@ -18,41 +64,24 @@ pub struct Triangle<'a> {
 
 impl<'a> Triangle<'a> {
     pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
-        let vertex_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("triangle.vs.wgsl"))),
-        });
-        let pixel_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("triangle.fs.wgsl"))),
-        });
+        let vertex_shader_module = create_shader_module(device, "triangle.vs");
+        let pixel_shader_module = create_shader_module(device, "triangle.fs");
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&[-0.5f32, -0.5, 0.5, -0.5, 0.0, 0.5]),
+            contents: bytemuck::cast_slice(&TRIANGLE_VERTICES),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let constant_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            size: (size_of::<f32>() * 4) as u64,
+            size: size_of::<TriangleParamsStd140>() as u64,
             mapped_at_creation: false,
         });
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
+        let bind_group_layout =
+            create_bind_group_layout(device, &["triangle.vs", "triangle.fs"], 0);
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
             layout: &bind_group_layout,
@@ -62,41 +91,20 @@ impl<'a> Triangle<'a> {
             }],
         });
 
+        let vertex_layout = VertexLayout::new("triangle.vs", &[0]);
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(
                 &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: None,
-                    bind_group_layouts: &[&device.create_bind_group_layout(
-                        &wgpu::BindGroupLayoutDescriptor {
-                            label: None,
-                            entries: &[wgpu::BindGroupLayoutEntry {
-                                binding: 0,
-                                visibility: wgpu::ShaderStages::FRAGMENT,
-                                ty: wgpu::BindingType::Buffer {
-                                    ty: wgpu::BufferBindingType::Uniform,
-                                    has_dynamic_offset: false,
-                                    min_binding_size: None,
-                                },
-                                count: None,
-                            }],
-                        },
-                    )],
+                    bind_group_layouts: &[&bind_group_layout],
                     push_constant_ranges: &[],
                 }),
             ),
             vertex: wgpu::VertexState {
                 module: &vertex_shader_module,
                 entry_point: "main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: (std::mem::size_of::<f32>() * 2) as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[wgpu::VertexAttribute {
-                        format: wgpu::VertexFormat::Float32x2,
-                        offset: 0,
-                        shader_location: 0,
-                    }],
-                }],
+                buffers: &[vertex_layout.buffer_layout(wgpu::VertexStepMode::Vertex)],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &pixel_shader_module,
@@ -123,7 +131,11 @@ impl<'a> Triangle<'a> {
     }
 
     pub fn update(&mut self, queue: &wgpu::Queue, params: &TriangleParams) {
-        queue.write_buffer(&self.constant_buffer, 0, bytemuck::bytes_of(params));
+        queue.write_buffer(
+            &self.constant_buffer,
+            0,
+            bytemuck::bytes_of(&params.std140()),
+        );
     }
 
     pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
@@ -133,3 +145,28 @@ impl<'a> Triangle<'a> {
         render_pass.draw(0..3, 0..1);
     }
 }
+
+impl<'a> Demo for Triangle<'a> {
+    fn create(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self::new(device, target_format)
+    }
+
+    fn name(&self) -> &'static str {
+        "Triangle"
+    }
+
+    fn create_params(&self) -> Box<dyn DemoParams> {
+        Box::<TriangleParams>::default()
+    }
+
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, params: &dyn DemoParams) {
+        let Some(params) = params.as_any().downcast_ref::<TriangleParams>() else {
+            return;
+        };
+        Triangle::update(self, queue, params);
+    }
+
+    fn draw<'b>(&'b self, render_pass: &mut wgpu::RenderPass<'b>) {
+        Triangle::draw(self, render_pass);
+    }
+}
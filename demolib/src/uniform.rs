@@ -0,0 +1,169 @@
+// GPU のバッファーに書き込む値の std140 / std430 の表現
+//   #[derive(Std140)] した構造体は、パディングを埋めた #[repr(C)] の構造体 (名前 + Std140) になる
+//   [f32; 3] のように長さ 2 から 4 のリテラルで書いたスカラーの配列はベクトル、それ以外の配列は配列
+//   行列は GLSL と同じく vec4 u_Matrix[4] のようなベクトルの配列として書く
+
+use bytemuck::Pod;
+
+pub trait Std140 {
+    // std140 でのアラインメント
+    const ALIGN: usize;
+    type Output: Pod;
+
+    fn std140(&self) -> Self::Output;
+}
+
+pub trait Std430 {
+    // std430 でのアラインメント
+    const ALIGN: usize;
+    type Output: Pod;
+
+    fn std430(&self) -> Self::Output;
+}
+
+// スカラーとベクトルはどちらのレイアウトでも同じ
+macro_rules! impl_value {
+    ($($type:ty => $align:expr),* $(,)?) => {
+        $(
+            impl Std140 for $type {
+                const ALIGN: usize = $align;
+                type Output = $type;
+
+                fn std140(&self) -> $type {
+                    *self
+                }
+            }
+
+            impl Std430 for $type {
+                const ALIGN: usize = $align;
+                type Output = $type;
+
+                fn std430(&self) -> $type {
+                    *self
+                }
+            }
+        )*
+    };
+}
+
+impl_value!(
+    f32 => 4, [f32; 2] => 8, [f32; 3] => 16, [f32; 4] => 16,
+    i32 => 4, [i32; 2] => 8, [i32; 3] => 16, [i32; 4] => 16,
+    u32 => 4, [u32; 2] => 8, [u32; 3] => 16, [u32; 4] => 16,
+);
+
+// GLSL の bool は 4 バイト
+impl Std140 for bool {
+    const ALIGN: usize = 4;
+    type Output = u32;
+
+    fn std140(&self) -> u32 {
+        *self as u32
+    }
+}
+
+impl Std430 for bool {
+    const ALIGN: usize = 4;
+    type Output = u32;
+
+    fn std430(&self) -> u32 {
+        *self as u32
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LayoutRule {
+    Std140,
+    Std430,
+}
+
+impl LayoutRule {
+    // 要素のアラインメントと大きさから、長さ len の配列の (アラインメント, 大きさ) を求める
+    pub const fn array(self, align: usize, size: usize, len: usize) -> (usize, usize) {
+        (
+            self.array_align(align),
+            self.array_stride(align, size) * len,
+        )
+    }
+
+    pub const fn array_stride(self, align: usize, size: usize) -> usize {
+        align_to(size, self.array_align(align))
+    }
+
+    // std140 では配列の要素を vec4 の境界にそろえる
+    const fn array_align(self, align: usize) -> usize {
+        match self {
+            Self::Std140 if align < 16 => 16,
+            _ => align,
+        }
+    }
+
+    const fn struct_align(self, align: usize) -> usize {
+        self.array_align(align)
+    }
+}
+
+// 構造体の各フィールドの前に入れるパディング
+pub struct StructLayout<const N: usize> {
+    pub align: usize,
+    pub size: usize,
+    pub padding: [usize; N],
+    // 最後のフィールドのあとのパディング
+    pub trailing: usize,
+}
+
+impl<const N: usize> StructLayout<N> {
+    // fields はフィールドごとの (アラインメント, 大きさ)
+    pub const fn new(rule: LayoutRule, fields: [(usize, usize); N]) -> Self {
+        let mut padding = [0; N];
+        let mut offset = 0;
+        let mut align = 4;
+        let mut index = 0;
+        while index < N {
+            let (field_align, field_size) = fields[index];
+            let field_offset = align_to(offset, field_align);
+            padding[index] = field_offset - offset;
+            offset = field_offset + field_size;
+            if field_align > align {
+                align = field_align;
+            }
+            index += 1;
+        }
+        let align = rule.struct_align(align);
+        let size = align_to(offset, align);
+        Self {
+            align,
+            size,
+            padding,
+            trailing: size - offset,
+        }
+    }
+}
+
+const fn align_to(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+pub fn std140_array<T: Std140, const STRIDE: usize, const N: usize>(
+    values: &[T; N],
+) -> [[u8; STRIDE]; N] {
+    let mut output = [[0; STRIDE]; N];
+    for (element, value) in output.iter_mut().zip(values) {
+        let value = value.std140();
+        let bytes = bytemuck::bytes_of(&value);
+        element[..bytes.len()].copy_from_slice(bytes);
+    }
+    output
+}
+
+pub fn std430_array<T: Std430, const STRIDE: usize, const N: usize>(
+    values: &[T; N],
+) -> [[u8; STRIDE]; N] {
+    let mut output = [[0; STRIDE]; N];
+    for (element, value) in output.iter_mut().zip(values) {
+        let value = value.std430();
+        let bytes = bytemuck::bytes_of(&value);
+        element[..bytes.len()].copy_from_slice(bytes);
+    }
+    output
+}
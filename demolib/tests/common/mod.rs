@@ -0,0 +1,120 @@
+// 画像の比較とアダプターの用意のように、テストで共有する処理
+// テストごとに使う関数が違うので、使われないものがあっても警告しない
+#![allow(dead_code)]
+
+use demolib::{HeadlessRenderer, Image};
+
+// いずれかのチャンネルがこれより離れていたら違う画素とみなす
+pub const CHANNEL_THRESHOLD: u8 = 8;
+// 違う画素の割合の上限
+pub const MAX_DIFFERENT_RATIO: f64 = 0.01;
+// 輝度の SSIM の平均の下限
+pub const MIN_SSIM: f64 = 0.97;
+
+// GPU のない環境ではテストを飛ばせるように、アダプターが使えなければ理由を表示して None を返す
+pub fn fallback_renderer() -> Option<HeadlessRenderer> {
+    match HeadlessRenderer::new_fallback() {
+        Ok(renderer) => Some(renderer),
+        Err(error) => {
+            eprintln!("skipping: no usable adapter ({error})");
+            None
+        }
+    }
+}
+
+// SSIM を計算するウィンドウの一辺
+const SSIM_WINDOW: u32 = 8;
+
+fn luminance(pixel: &[u8]) -> f64 {
+    (0.2126 * pixel[0] as f64 + 0.7152 * pixel[1] as f64 + 0.0722 * pixel[2] as f64) / 255.0
+}
+
+pub struct Comparison {
+    pub different_ratio: f64,
+    pub ssim: f64,
+    pub diff: Image,
+}
+
+pub fn compare(expected: &Image, actual: &Image) -> Comparison {
+    assert_eq!(
+        (expected.width, expected.height),
+        (actual.width, actual.height),
+        "image size mismatch"
+    );
+
+    // 違いが見えやすいように 4 倍して、違う画素は赤で示す
+    let mut different_count = 0;
+    let mut diff_pixels = Vec::with_capacity(actual.pixels.len());
+    for (expected, actual) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        let max_difference = (0..4)
+            .map(|channel| expected[channel].abs_diff(actual[channel]))
+            .max()
+            .unwrap_or_default();
+        if max_difference > CHANNEL_THRESHOLD {
+            different_count += 1;
+            diff_pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let value = max_difference.saturating_mul(4);
+            diff_pixels.extend_from_slice(&[value, value, value, 255]);
+        }
+    }
+
+    Comparison {
+        different_ratio: different_count as f64 / (actual.width * actual.height) as f64,
+        ssim: mean_ssim(expected, actual),
+        diff: Image {
+            width: actual.width,
+            height: actual.height,
+            pixels: diff_pixels,
+        },
+    }
+}
+
+// 重ならないウィンドウごとに輝度の SSIM を求めて平均する
+fn mean_ssim(expected: &Image, actual: &Image) -> f64 {
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+
+    let width = actual.width;
+    let height = actual.height;
+    let mut total = 0.0;
+    let mut window_count = 0;
+    for window_y in (0..height).step_by(SSIM_WINDOW as usize) {
+        for window_x in (0..width).step_by(SSIM_WINDOW as usize) {
+            let mut samples = Vec::new();
+            for y in window_y..(window_y + SSIM_WINDOW).min(height) {
+                for x in window_x..(window_x + SSIM_WINDOW).min(width) {
+                    let index = ((y * width + x) * 4) as usize;
+                    samples.push((
+                        luminance(&expected.pixels[index..index + 4]),
+                        luminance(&actual.pixels[index..index + 4]),
+                    ));
+                }
+            }
+
+            let count = samples.len() as f64;
+            let mean_a = samples.iter().map(|(a, _)| a).sum::<f64>() / count;
+            let mean_b = samples.iter().map(|(_, b)| b).sum::<f64>() / count;
+            let mut variance_a = 0.0;
+            let mut variance_b = 0.0;
+            let mut covariance = 0.0;
+            for (a, b) in &samples {
+                variance_a += (a - mean_a) * (a - mean_a);
+                variance_b += (b - mean_b) * (b - mean_b);
+                covariance += (a - mean_a) * (b - mean_b);
+            }
+            variance_a /= count;
+            variance_b /= count;
+            covariance /= count;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            window_count += 1;
+        }
+    }
+    total / window_count as f64
+}
@@ -0,0 +1,120 @@
+// 各デモをヘッドレスで描画して、tests/golden にある基準画像と比べる
+// 基準画像を作り直すときは GOLDEN_UPDATE=1 をつけて実行する
+// 一致しなかったときは target/tmp/golden に描画結果と差分画像を書き出す
+
+use demolib::{Demo, Image, Mandelbrot, Model3d, Physics, Tetris, Triangle};
+use std::path::PathBuf;
+
+mod common;
+
+use common::{compare, fallback_renderer, CHANNEL_THRESHOLD, MAX_DIFFERENT_RATIO, MIN_SSIM};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+
+fn golden_directory() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output_directory() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn check_golden<T: Demo>(name: &str) {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let actual = renderer.render::<T>(WIDTH, HEIGHT).unwrap();
+
+    let golden_path = golden_directory().join(format!("{name}.png"));
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        std::fs::create_dir_all(golden_directory()).unwrap();
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    let expected = match std::fs::File::open(&golden_path) {
+        Ok(file) => Image::read_png(std::io::BufReader::new(file)).unwrap(),
+        Err(error) => panic!(
+            "failed to open {}: {error} (run with GOLDEN_UPDATE=1 to create it)",
+            golden_path.display()
+        ),
+    };
+    let comparison = compare(&expected, &actual);
+    if comparison.different_ratio <= MAX_DIFFERENT_RATIO && comparison.ssim >= MIN_SSIM {
+        return;
+    }
+
+    let output = output_directory();
+    std::fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{name}-actual.png"));
+    let diff_path = output.join(format!("{name}-diff.png"));
+    actual.save(&actual_path).unwrap();
+    comparison.diff.save(&diff_path).unwrap();
+    panic!(
+        "{name} does not match {}: {:.2}% of pixels differ (max {:.2}%), SSIM {:.4} (min {MIN_SSIM})\nactual: {}\ndiff: {}",
+        golden_path.display(),
+        comparison.different_ratio * 100.0,
+        MAX_DIFFERENT_RATIO * 100.0,
+        comparison.ssim,
+        actual_path.display(),
+        diff_path.display(),
+    );
+}
+
+#[test]
+fn compare_image_metrics() {
+    let image = Image {
+        width: 16,
+        height: 16,
+        pixels: (0..16 * 16)
+            .flat_map(|index| {
+                let value = (index * 7 % 256) as u8;
+                [value, 255 - value, value / 2, 255]
+            })
+            .collect(),
+    };
+    let identical = compare(&image, &image);
+    assert_eq!(identical.different_ratio, 0.0);
+    assert!((identical.ssim - 1.0).abs() < 1.0e-9);
+
+    // しきい値以下のずれは許容する
+    let mut shifted = image.clone();
+    for value in shifted.pixels.iter_mut() {
+        *value = value.saturating_add(CHANNEL_THRESHOLD / 2);
+    }
+    assert_eq!(compare(&image, &shifted).different_ratio, 0.0);
+
+    let mut inverted = image.clone();
+    for value in inverted.pixels.iter_mut() {
+        *value = 255 - *value;
+    }
+    let comparison = compare(&image, &inverted);
+    assert!(comparison.different_ratio > MAX_DIFFERENT_RATIO);
+    assert!(comparison.ssim < MIN_SSIM);
+}
+
+#[test]
+fn triangle() {
+    check_golden::<Triangle>("triangle");
+}
+
+#[test]
+fn mandelbrot() {
+    check_golden::<Mandelbrot>("mandelbrot");
+}
+
+#[test]
+fn model_3d() {
+    check_golden::<Model3d>("model_3d");
+}
+
+#[test]
+fn tetris() {
+    check_golden::<Tetris>("tetris");
+}
+
+#[test]
+fn physics() {
+    check_golden::<Physics>("physics");
+}
@@ -0,0 +1,105 @@
+// cargo test -p demolib --features hot-reload で実行する
+#![cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use demolib::{record_shaders, set_shader_override, shader_source, ShaderReloader, SHADERS};
+
+// resources/shaders をテストごとのディレクトリに複製する
+fn copy_shaders(name: &str) -> PathBuf {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/shaders");
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&directory).unwrap();
+    for entry in std::fs::read_dir(source).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+    }
+    directory
+}
+
+// 更新日時の分解能に左右されないように、seconds 秒先の時刻にして書き込む
+fn write_later(path: &Path, contents: &str, seconds: u64) {
+    std::fs::write(path, contents).unwrap();
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(seconds))
+        .unwrap();
+}
+
+#[test]
+fn recompile_changed_source() {
+    let directory = copy_shaders("recompile_changed_source");
+    let mut reloader = ShaderReloader::new(directory.clone());
+    assert!(reloader.poll().is_empty());
+
+    let path = directory.join("mandelbrot.fs");
+    let source = std::fs::read_to_string(&path).unwrap();
+    write_later(&path, &source.replace("256.0", "128.0"), 10);
+
+    // マクロを変えてコンパイルするフラクタルの種類ぶん更新される
+    let updates = reloader.poll();
+    let mut names: Vec<_> = updates.iter().map(|update| update.name).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "burning_ship.fs",
+            "julia.fs",
+            "mandelbrot.fs",
+            "multibrot.fs",
+            "tricorn.fs"
+        ]
+    );
+    assert!(updates.iter().all(|update| update.wgsl.contains("128.0")));
+    assert_eq!(reloader.errors().count(), 0);
+    assert!(reloader.poll().is_empty());
+}
+
+#[test]
+fn report_compile_error_with_location() {
+    let directory = copy_shaders("report_compile_error_with_location");
+    let mut reloader = ShaderReloader::new(directory.clone());
+
+    let path = directory.join("triangle.fs");
+    let source = std::fs::read_to_string(&path).unwrap();
+    write_later(&path, &source.replace("u_Color.xyz", "u_Colour.xyz"), 10);
+
+    assert!(reloader.poll().is_empty());
+    let errors: Vec<_> = reloader.errors().collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, "triangle.fs");
+    assert!(errors[0].1.starts_with("triangle.fs:"), "{}", errors[0].1);
+
+    // 直したらエラーは消える
+    write_later(&path, &source, 20);
+    assert_eq!(reloader.poll().len(), 1);
+    assert_eq!(reloader.errors().count(), 0);
+}
+
+#[test]
+fn override_and_record_shaders() {
+    let ((), names) = record_shaders(|| {
+        shader_source("tetris.vs");
+        shader_source("tetris.fs");
+    });
+    assert_eq!(names.len(), 2);
+    assert!(names.contains("tetris.vs") && names.contains("tetris.fs"));
+
+    let baked = SHADERS
+        .iter()
+        .find(|entry| entry.name == "physics.fs")
+        .unwrap()
+        .wgsl;
+    assert_eq!(
+        set_shader_override("physics.fs", Some("// test".to_string())),
+        None
+    );
+    assert_eq!(shader_source("physics.fs"), "// test");
+    assert_eq!(
+        set_shader_override("physics.fs", None).as_deref(),
+        Some("// test")
+    );
+    assert_eq!(shader_source("physics.fs"), baked);
+}
@@ -0,0 +1,177 @@
+use demolib::physics::{sweep_and_prune, Aabb, RigidBody, Shape, World, WorldSettings};
+use nalgebra_glm::{self as glm, Vec3};
+
+const STEP_COUNT: usize = 600;
+
+fn total_energy(world: &World) -> f32 {
+    world.kinetic_energy() + world.potential_energy()
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let settings = WorldSettings::default();
+    let mut world_a = World::new(&settings);
+    let mut world_b = World::new(&settings);
+    for _ in 0..STEP_COUNT {
+        world_a.step();
+        world_b.step();
+    }
+    for (a, b) in world_a.bodies().iter().zip(world_b.bodies()) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.velocity, b.velocity);
+        assert_eq!(a.orientation, b.orientation);
+        assert_eq!(a.angular_velocity, b.angular_velocity);
+    }
+}
+
+#[test]
+fn energy_does_not_increase() {
+    let settings = WorldSettings {
+        restitution: 0.5,
+        ..Default::default()
+    };
+    let mut world = World::new(&settings);
+    let initial_energy = total_energy(&world);
+    let tolerance = initial_energy.abs() * 0.01;
+    for _ in 0..STEP_COUNT {
+        world.step();
+        assert!(total_energy(&world) <= initial_energy + tolerance);
+    }
+}
+
+#[test]
+fn bodies_come_to_rest_without_sinking() {
+    let settings = WorldSettings {
+        body_count: 16,
+        ..Default::default()
+    };
+    let mut world = World::new(&settings);
+    for _ in 0..STEP_COUNT * 2 {
+        world.step();
+    }
+    assert!(world.max_penetration() < 0.05);
+    for body in world.bodies().iter().filter(|body| !body.is_static()) {
+        // 床 (y = 0) より下に落ちていない
+        assert!(body.aabb().min.y > -0.05);
+        assert!(body.velocity.norm() < 0.5);
+        assert!(body.angular_velocity.norm() < 2.0);
+    }
+}
+
+// 床と壁だけの空の世界
+fn empty_world(gravity: [f32; 3]) -> World {
+    World::new(&WorldSettings {
+        gravity,
+        body_count: 0,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn sliding_sphere_starts_rolling() {
+    let mut world = empty_world([0.0, -9.8, 0.0]);
+    let radius = 0.25;
+    let mut sphere = RigidBody::new(
+        Shape::Sphere { radius },
+        Vec3::new(-1.5, radius, 0.0),
+        1000.0,
+        [1.0; 3],
+    );
+    sphere.velocity = Vec3::new(2.0, 0.0, 0.0);
+    let index = world.add_body(sphere);
+    for _ in 0..120 {
+        world.step();
+    }
+
+    // 摩擦で滑りがなくなると、速度は初速の 5/7 で転がり続ける
+    let sphere = &world.bodies()[index];
+    assert!(sphere.angular_velocity.z < 0.0);
+    assert!((sphere.velocity.x + sphere.angular_velocity.z * radius).abs() < 0.05);
+    assert!((sphere.velocity.x - 2.0 * 5.0 / 7.0).abs() < 0.1);
+}
+
+#[test]
+fn tilted_box_settles_on_a_face() {
+    let mut world = empty_world([0.0, -9.8, 0.0]);
+    let half_extents = Vec3::new(0.3, 0.2, 0.25);
+    let mut cuboid = RigidBody::new(
+        Shape::Box { half_extents },
+        Vec3::new(0.0, 1.0, 0.0),
+        1000.0,
+        [1.0; 3],
+    );
+    cuboid.orientation = glm::quat_angle_axis(0.5, &Vec3::new(1.0, 0.0, 1.0).normalize());
+    let index = world.add_body(cuboid);
+    for _ in 0..STEP_COUNT {
+        world.step();
+    }
+
+    let cuboid = &world.bodies()[index];
+    let rotation = cuboid.rotation();
+    let upright = (0..3).any(|axis| rotation.column(axis).dot(&Vec3::y()).abs() > 0.999);
+    assert!(upright, "{:?}", cuboid.orientation);
+    assert!(cuboid.angular_velocity.norm() < 0.05);
+    assert!(cuboid.aabb().min.y.abs() < 0.02);
+}
+
+#[test]
+fn off_center_hit_spins_box() {
+    let mut world = empty_world([0.0; 3]);
+    let cuboid = world.add_body(RigidBody::new(
+        Shape::Box {
+            half_extents: Vec3::repeat(0.2),
+        },
+        Vec3::new(0.0, 1.0, 0.0),
+        1000.0,
+        [1.0; 3],
+    ));
+    let mut sphere = RigidBody::new(
+        Shape::Sphere { radius: 0.1 },
+        Vec3::new(-1.0, 1.15, 0.0),
+        1000.0,
+        [1.0; 3],
+    );
+    sphere.velocity = Vec3::new(3.0, 0.0, 0.0);
+    let sphere = world.add_body(sphere);
+    let momentum = |world: &World| {
+        let bodies = world.bodies();
+        bodies[cuboid].velocity * bodies[cuboid].mass()
+            + bodies[sphere].velocity * bodies[sphere].mass()
+    };
+    let initial_momentum = momentum(&world);
+    for _ in 0..60 {
+        world.step();
+    }
+
+    // 重心より上を +x 方向に押されたので -z 軸まわりに回る
+    let bodies = world.bodies();
+    assert!(bodies[cuboid].velocity.x > 0.0);
+    assert!(bodies[cuboid].angular_velocity.z < -0.1);
+    assert!((momentum(&world) - initial_momentum).norm() < 1.0e-3 * initial_momentum.norm());
+}
+
+#[test]
+fn sweep_and_prune_matches_brute_force() {
+    let aabbs = (0..64)
+        .map(|index| {
+            let x = (index * 37 % 17) as f32 * 0.3;
+            let y = (index * 11 % 5) as f32 * 0.4;
+            let z = (index * 7 % 3) as f32 * 0.5;
+            let min = Vec3::new(x, y, z);
+            Aabb {
+                min,
+                max: min + Vec3::repeat(0.35),
+            }
+        })
+        .collect::<Vec<Aabb>>();
+
+    let mut expected = Vec::new();
+    for a in 0..aabbs.len() {
+        for b in a + 1..aabbs.len() {
+            if aabbs[a].overlaps(&aabbs[b]) {
+                expected.push((a, b));
+            }
+        }
+    }
+    assert_eq!(sweep_and_prune(&aabbs), expected);
+}
@@ -0,0 +1,107 @@
+// CPU の参照実装を GPU で描いた基準画像 (tests/golden) と比べる
+// GPU がなくても実行できる
+
+use demolib::{reference, Image, MandelbrotParams, Model3dParams, TriangleParams};
+use nalgebra_glm as glm;
+use std::path::PathBuf;
+
+mod common;
+
+use common::{compare, MAX_DIFFERENT_RATIO, MIN_SSIM};
+
+// golden.rs と同じ大きさ
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+
+fn load_golden(name: &str) -> Image {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/{name}.png"));
+    let file = std::fs::File::open(&path).unwrap();
+    Image::read_png(std::io::BufReader::new(file)).unwrap()
+}
+
+fn assert_matches_golden(name: &str, actual: &Image) {
+    let comparison = compare(&load_golden(name), actual);
+    if comparison.different_ratio <= MAX_DIFFERENT_RATIO && comparison.ssim >= MIN_SSIM {
+        return;
+    }
+
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("reference");
+    std::fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{name}-actual.png"));
+    let diff_path = output.join(format!("{name}-diff.png"));
+    actual.save(&actual_path).unwrap();
+    comparison.diff.save(&diff_path).unwrap();
+    panic!(
+        "reference {name} does not match the golden image: {:.2}% of pixels differ, SSIM {:.4}\nactual: {}\ndiff: {}",
+        comparison.different_ratio * 100.0,
+        comparison.ssim,
+        actual_path.display(),
+        diff_path.display(),
+    );
+}
+
+#[test]
+fn triangle_matches_golden() {
+    let image = reference::triangle(&TriangleParams::default(), WIDTH, HEIGHT);
+    assert_matches_golden("triangle", &image);
+}
+
+#[test]
+fn triangle_uses_params_color() {
+    let params = TriangleParams {
+        color: [1.0, 0.5, 0.0],
+    };
+    let image = reference::triangle(&params, 16, 16);
+    // 中心は三角形の内側、角は外側
+    let center = ((8 * 16 + 8) * 4) as usize;
+    assert_eq!(&image.pixels[center..center + 4], &[255, 128, 0, 255]);
+    assert_eq!(&image.pixels[0..4], &[0, 0, 0, 255]);
+}
+
+#[test]
+fn mandelbrot_matches_golden() {
+    let image = reference::mandelbrot(&MandelbrotParams::default(), WIDTH, HEIGHT);
+    assert_matches_golden("mandelbrot", &image);
+}
+
+#[test]
+fn mandelbrot_interior_is_black() {
+    let params = MandelbrotParams {
+        center: [0.0, 0.0],
+        scale: 0.01,
+        ..Default::default()
+    };
+    let image = reference::mandelbrot(&params, 8, 8);
+    assert!(image
+        .pixels
+        .chunks_exact(4)
+        .all(|pixel| pixel == [0, 0, 0, 255]));
+}
+
+#[test]
+fn model_3d_matches_golden() {
+    let image = reference::model_3d(&Model3dParams::default(), WIDTH, HEIGHT);
+    assert_matches_golden("model_3d", &image);
+}
+
+#[test]
+fn model_3d_vertex_transform() {
+    let params = Model3dParams::default();
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+    let clip_positions = reference::model_3d_clip_positions(&params, aspect);
+    assert_eq!(clip_positions.len(), params.scene.meshes.len());
+
+    let view_projection = params.camera.view_projection_matrix(aspect);
+    for (mesh, clip_positions) in params.scene.meshes.iter().zip(&clip_positions) {
+        assert_eq!(clip_positions.len(), mesh.positions.len());
+        for (position, clip_position) in mesh.world_positions().zip(clip_positions) {
+            let expected = view_projection * glm::vec4(position[0], position[1], position[2], 1.0);
+            for index in 0..4 {
+                assert!(
+                    (expected[index] - clip_position[index]).abs() < 1.0e-4,
+                    "{expected:?} != {clip_position:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// シェーダーのリフレクションから作るバインドグループと頂点バッファーのレイアウト
+
+use demolib::{bind_group_layout_entries, layouts, shader_entry, shader_variant, VertexLayout};
+
+#[test]
+fn generate_uniform_layouts() {
+    assert_eq!(layouts::triangle_fs::material::SIZE, 16);
+    assert_eq!(layouts::triangle_fs::material::U_COLOR, 0);
+    assert_eq!(layouts::model_3d_vs::object::U_MODEL, 0);
+    // 同じブロックはどのシェーダーからも同じレイアウトになる
+    assert_eq!(
+        layouts::model_3d_vs::view::SIZE,
+        layouts::model_3d_fs::view::SIZE
+    );
+}
+
+#[test]
+fn merge_visibility_of_shared_bindings() {
+    let entries = bind_group_layout_entries(&["triangle.vs", "triangle.fs"], 0);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].binding, 0);
+    assert_eq!(entries[0].visibility, wgpu::ShaderStages::FRAGMENT);
+    assert_eq!(
+        entries[0].ty,
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(16),
+        }
+    );
+
+    let pixel_shader = shader_variant("model_3d.fs", &[("SHADOWS", "1")]);
+    let entries = bind_group_layout_entries(&["model_3d.vs", pixel_shader], 0);
+    let view = entries.iter().find(|entry| entry.binding == 0).unwrap();
+    assert_eq!(
+        view.visibility,
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT
+    );
+
+    // シャドウマップは比較サンプラーで読む
+    let entries = bind_group_layout_entries(&[pixel_shader], 2);
+    assert!(entries.iter().any(|entry| matches!(
+        entry.ty,
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            ..
+        }
+    )));
+    assert!(entries
+        .iter()
+        .any(|entry| entry.ty == wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)));
+}
+
+#[test]
+fn pack_vertex_attributes() {
+    assert_eq!(shader_entry("physics.vs").stage, wgpu::ShaderStages::VERTEX);
+
+    let mesh = VertexLayout::new("physics.vs", &[0, 1]);
+    assert_eq!(mesh.array_stride, 24);
+    assert_eq!(
+        mesh.attributes,
+        [
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 12,
+                shader_location: 1,
+            },
+        ]
+    );
+
+    let instance = VertexLayout::new("physics.vs", &[2, 3, 4]);
+    assert_eq!(instance.array_stride, 48);
+    assert_eq!(
+        instance
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.shader_location, attribute.offset))
+            .collect::<Vec<_>>(),
+        [(2, 0), (3, 16), (4, 32)]
+    );
+}
+
+#[test]
+#[should_panic(expected = "physics.vs has no input at location 7")]
+fn missing_vertex_input() {
+    VertexLayout::new("physics.vs", &[0, 7]);
+}
@@ -0,0 +1,304 @@
+use demolib::scene::{Scene, SceneError};
+
+fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+    for index in 0..3 {
+        assert!(
+            (actual[index] - expected[index]).abs() < 1.0e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn load_embedded_torus() {
+    let scene = Scene::from_usda(include_str!("../resources/models/torus.usda")).unwrap();
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.meshes[0].name, "/Torus");
+
+    // 法線が faceVarying なので面頂点ごとに頂点が分かれる
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.indices.len(), 3456);
+    assert_eq!(mesh.positions.len(), mesh.indices.len());
+    assert_eq!(mesh.positions.len(), mesh.normals.len());
+    assert!(mesh
+        .indices
+        .iter()
+        .all(|index| (*index as usize) < mesh.positions.len()));
+}
+
+#[test]
+fn infer_normal_interpolation() {
+    let source = r#"#usda 1.0
+def Xform "Root"
+{
+}
+
+def Mesh "Vertex"
+{
+    int[] faceVertexCounts = [3, 3]
+    int[] faceVertexIndices = [0, 1, 2, 0, 2, 3]
+    normal3f[] normals = [(0, 0, 1), (0, 0, 1), (0, 0, 1), (0, 0, 1)]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0)]
+}
+
+def Mesh "Uniform"
+{
+    int[] faceVertexCounts = [3, 3]
+    int[] faceVertexIndices = [0, 1, 2, 0, 1, 3]
+    normal3f[] normals = [(0, 0, 1), (0, -1, 0)]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0), (0, 0, 1)]
+}
+
+def Mesh "Computed"
+{
+    int[] faceVertexCounts = [3]
+    int[] faceVertexIndices = [0, 1, 2]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+}
+"#;
+    let scene = Scene::from_usda(source).unwrap();
+    // 点のない Xform はメッシュにならない
+    assert_eq!(scene.meshes.len(), 3);
+
+    // 頂点ごとの法線なら頂点を共有する
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.positions.len(), 4);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+
+    // 面ごとの法線は面頂点に分ける
+    let mesh = &scene.meshes[1];
+    assert_eq!(mesh.positions.len(), 6);
+    assert_near(mesh.normals[2], [0.0, 0.0, 1.0]);
+    assert_near(mesh.normals[3], [0.0, -1.0, 0.0]);
+
+    // 法線がないときは頂点法線を計算する
+    for normal in &scene.meshes[2].normals {
+        assert_near(*normal, [0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn triangulate_polygons_and_skip_holes() {
+    let source = r#"#usda 1.0
+def Mesh "Shape"
+{
+    int[] faceVertexCounts = [4, 5, 3]
+    int[] faceVertexIndices = [0, 1, 2, 3, 0, 1, 2, 3, 4, 0, 1, 2]
+    int[] holeIndices = [2]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0), (-0.5, 0.5, 0)]
+}
+"#;
+    let scene = Scene::from_usda(source).unwrap();
+    let mesh = &scene.meshes[0];
+
+    // 四角形 2 枚 + 五角形 3 枚
+    assert_eq!(
+        mesh.indices,
+        vec![0, 1, 2, 0, 2, 3, 0, 1, 2, 0, 2, 3, 0, 3, 4]
+    );
+
+    // 法線がないときは頂点法線を計算する
+    assert_eq!(mesh.positions.len(), 5);
+    for normal in &mesh.normals {
+        assert_near(*normal, [0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn apply_nested_transforms() {
+    let source = r#"#usda 1.0
+(
+    upAxis = "Z"
+)
+
+def Xform "Root"
+{
+    double3 xformOp:translate = (10, 0, 0)
+    uniform token[] xformOpOrder = ["xformOp:translate"]
+
+    def Xform "Child"
+    {
+        float xformOp:rotateZ = 90
+        float3 xformOp:scale = (2, 2, 2)
+        uniform token[] xformOpOrder = ["xformOp:rotateZ", "xformOp:scale"]
+
+        def Mesh "Triangle"
+        {
+            int[] faceVertexCounts = [3]
+            int[] faceVertexIndices = [0, 1, 2]
+            point3f[] points = [(1, 0, 0), (0, 1, 0), (0, 0, 0)]
+            normal3f[] normals = [(0, 1, 0)] (
+                interpolation = "constant"
+            )
+        }
+    }
+}
+"#;
+    let scene = Scene::from_usda(source).unwrap();
+    assert_eq!(scene.meshes.len(), 1);
+
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.name, "/Root/Child/Triangle");
+    let positions = mesh.world_positions().collect::<Vec<_>>();
+    assert_near(positions[0], [10.0, 2.0, 0.0]);
+    assert_near(positions[1], [8.0, 0.0, 0.0]);
+    assert_near(positions[2], [10.0, 0.0, 0.0]);
+    for normal in mesh.world_normals() {
+        assert_near(normal, [-1.0, 0.0, 0.0]);
+    }
+}
+
+#[test]
+fn convert_y_up_stage() {
+    let source = r#"#usda 1.0
+(
+    upAxis = "Y"
+)
+
+def Mesh "Moved"
+{
+    float3 xformOp:translate = (0, 1, 0)
+    uniform token[] xformOpOrder = ["xformOp:translate"]
+    int[] faceVertexCounts = [3]
+    int[] faceVertexIndices = [0, 1, 2]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 0, 1)]
+    normal3f[] normals = [(0, 1, 0), (0, 1, 0), (0, 1, 0)] (
+        interpolation = "faceVarying"
+    )
+}
+"#;
+    let scene = Scene::from_usda(source).unwrap();
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.positions.len(), 3);
+
+    // Y=1 の移動が Z-up の Z=1 になる
+    let positions = mesh.world_positions().collect::<Vec<_>>();
+    assert_near(positions[0], [0.0, 0.0, 1.0]);
+    assert_near(positions[2], [0.0, -1.0, 1.0]);
+    for normal in mesh.world_normals() {
+        assert_near(normal, [0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn report_errors() {
+    let source = "#usda 1.0\ndef Mesh \"Broken\"\n{\n    int[] faceVertexIndices = [0,\n}\n";
+    assert!(matches!(
+        Scene::from_usda(source),
+        Err(SceneError::Parse { line, .. }) if line > 0
+    ));
+
+    let source = r#"def Mesh "NoPoints"
+{
+    int[] faceVertexCounts = [3]
+    int[] faceVertexIndices = [0, 1, 2]
+}
+"#;
+    let Err(SceneError::InvalidMesh { prim, .. }) = Scene::from_usda(source) else {
+        panic!("points is missing");
+    };
+    assert_eq!(prim, "/NoPoints");
+
+    let source = r#"def Mesh "OutOfRange"
+{
+    int[] faceVertexCounts = [3]
+    int[] faceVertexIndices = [0, 1, 3]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+}
+"#;
+    assert!(matches!(
+        Scene::from_usda(source),
+        Err(SceneError::InvalidMesh { .. })
+    ));
+
+    assert!(matches!(
+        Scene::from_bytes("model.usdc", b"PXR-USDC"),
+        Err(SceneError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn load_embedded_gltf() {
+    let source = r#"{
+  "asset": { "version": "2.0" },
+  "scene": 0,
+  "scenes": [{ "nodes": [0] }],
+  "nodes": [
+    { "name": "Parent", "translation": [0, 0, 5], "children": [1] },
+    { "name": "Child", "mesh": 0, "scale": [2, 2, 2] }
+  ],
+  "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+  "materials": [{
+    "name": "Red",
+    "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0.25, "roughnessFactor": 0.75 },
+    "emissiveFactor": [0, 0, 1]
+  }],
+  "buffers": [{
+    "byteLength": 44,
+    "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+  }],
+  "bufferViews": [
+    { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+    { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+  ],
+  "accessors": [
+    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+    { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+  ]
+}"#;
+    let scene = Scene::from_bytes("triangle.gltf", source.as_bytes()).unwrap();
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.materials.len(), 1);
+
+    let material = &scene.materials[0];
+    assert_eq!(material.name, "Red");
+    assert_eq!(material.base_color, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(material.metallic, 0.25);
+    assert_eq!(material.roughness, 0.75);
+    assert_eq!(material.emissive, [0.0, 0.0, 1.0]);
+
+    // 親子の変換を合成してから Y-up を Z-up にする
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.material, Some(0));
+    assert_eq!(mesh.indices, vec![0, 1, 2]);
+    let positions = mesh.world_positions().collect::<Vec<_>>();
+    assert_near(positions[0], [0.0, -5.0, 0.0]);
+    assert_near(positions[1], [2.0, -5.0, 0.0]);
+    assert_near(positions[2], [0.0, -5.0, 2.0]);
+    for normal in mesh.world_normals() {
+        assert_near(normal, [0.0, -1.0, 0.0]);
+    }
+}
+
+#[test]
+fn load_obj() {
+    let source = r#"# quad
+mtllib missing.mtl
+o Quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+usemtl Missing
+f 1//1 2//1 3//1 4//1
+o Triangle
+v 0 0 1
+v 1 0 1
+v 0 1 1
+f 5 6 7
+"#;
+    let scene = Scene::from_bytes("model.obj", source.as_bytes()).unwrap();
+    assert!(scene.materials.is_empty());
+    assert_eq!(scene.meshes.len(), 2);
+    assert_eq!(scene.meshes[0].name, "Quad");
+    assert_eq!(scene.meshes[0].indices.len(), 6);
+    assert_eq!(scene.meshes[0].material, None);
+    assert_eq!(scene.meshes[1].indices.len(), 3);
+    assert_eq!(scene.triangle_count(), 3);
+
+    // 法線がない Triangle は計算される
+    for normal in scene.meshes[1].world_normals() {
+        assert_near(normal, [0.0, -1.0, 0.0]);
+    }
+}
@@ -0,0 +1,98 @@
+// 保存したパラメーターのバージョンとマイグレーション
+
+use demolib::{DemoParams, DemoState, MandelbrotParams, Migration, StateError, TriangleParams};
+use serde::{Deserialize, Serialize};
+
+// バージョン 0 は明るさを 0 から 255 の整数で持っていた
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Exposure {
+    brightness: f32,
+    gamma: f32,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    |mut params| {
+        let brightness = params["brightness"].as_u64().unwrap_or_default();
+        params["brightness"] = (brightness as f64 / 255.0).into();
+        Ok(params)
+    },
+    |mut params| {
+        params["gamma"] = 2.2.into();
+        Ok(params)
+    },
+];
+
+#[test]
+fn migrate_old_versions() {
+    let state = DemoState {
+        version: 0,
+        params: serde_json::json!({ "brightness": 51 }),
+    };
+    let exposure: Exposure = state.decode(MIGRATIONS).unwrap();
+    assert_eq!(
+        exposure,
+        Exposure {
+            brightness: 0.2,
+            gamma: 2.2
+        }
+    );
+
+    let state = DemoState::new(&exposure, MIGRATIONS).unwrap();
+    assert_eq!(state.version, 2);
+    assert_eq!(state.decode::<Exposure>(MIGRATIONS).unwrap(), exposure);
+}
+
+#[test]
+fn reject_newer_version() {
+    let state = DemoState {
+        version: 3,
+        params: serde_json::json!({ "brightness": 0.5, "gamma": 1.0 }),
+    };
+    assert!(matches!(
+        state.decode::<Exposure>(MIGRATIONS),
+        Err(StateError::UnsupportedVersion {
+            version: 3,
+            latest: 2
+        })
+    ));
+}
+
+#[test]
+fn keep_params_when_state_is_broken() {
+    let mut params = TriangleParams {
+        color: [1.0, 0.5, 0.25],
+    };
+    let state = DemoState {
+        version: 0,
+        params: serde_json::json!({ "colour": [0.0, 0.0, 0.0] }),
+    };
+    assert!(matches!(
+        params.load_state(state),
+        Err(StateError::Serde(_))
+    ));
+    assert_eq!(params.color, [1.0, 0.5, 0.25]);
+}
+
+#[test]
+fn migrate_mandelbrot_from_ron_string() {
+    let saved = MandelbrotParams {
+        max_iterations: 1000,
+        center: [0.25, -0.125],
+        ..Default::default()
+    };
+    // 以前は ron::to_string した文字列をそのまま保存していた
+    let state = DemoState {
+        version: 0,
+        params: serde_json::Value::String(ron::to_string(&saved).unwrap()),
+    };
+
+    let mut params = MandelbrotParams::default();
+    params.load_state(state).unwrap();
+    assert_eq!(params, saved);
+
+    let state = params.save_state().unwrap();
+    assert_eq!(state.version, 1);
+    let mut restored = MandelbrotParams::default();
+    restored.load_state(state).unwrap();
+    assert_eq!(restored, saved);
+}
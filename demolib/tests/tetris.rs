@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use demolib::{TetrisGame, TetrisInput, TetrominoKind, BOARD_WIDTH};
+
+// 最初のテトリミノが kind になるシードを探す
+fn find_game_starting_with(kind: TetrominoKind) -> TetrisGame {
+    (0..)
+        .map(TetrisGame::new)
+        .find(|game| game.current_piece().unwrap().kind == kind)
+        .unwrap()
+}
+
+fn drop_sequence(seed: u64, count: usize) -> Vec<TetrominoKind> {
+    let mut game = TetrisGame::new(seed);
+    let mut kinds = Vec::new();
+    for _ in 0..count {
+        kinds.push(game.current_piece().unwrap().kind);
+        game.apply(TetrisInput::HardDrop);
+    }
+    kinds
+}
+
+#[test]
+fn same_seed_same_sequence() {
+    assert_eq!(drop_sequence(42, 7), drop_sequence(42, 7));
+}
+
+#[test]
+fn bag_contains_every_kind() {
+    let kinds = drop_sequence(7, 7).into_iter().collect::<HashSet<_>>();
+    assert_eq!(kinds.len(), 7);
+}
+
+#[test]
+fn rotate_four_times_returns_to_spawn() {
+    let mut game = find_game_starting_with(TetrominoKind::T);
+    let before = game.current_piece().unwrap().cells().collect::<Vec<_>>();
+    for _ in 0..4 {
+        game.apply(TetrisInput::RotateClockwise);
+    }
+    let after = game.current_piece().unwrap().cells().collect::<Vec<_>>();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn wall_kick_keeps_piece_inside_board() {
+    let mut game = find_game_starting_with(TetrominoKind::I);
+    game.apply(TetrisInput::RotateClockwise);
+    for _ in 0..BOARD_WIDTH {
+        game.apply(TetrisInput::MoveRight);
+    }
+    // 右の壁に接した縦向きの I は回転するときに左へ蹴り出される
+    game.apply(TetrisInput::RotateClockwise);
+    let piece = game.current_piece().unwrap();
+    assert!(piece
+        .cells()
+        .all(|(x, _)| (0..BOARD_WIDTH as i32).contains(&x)));
+    assert!(piece
+        .cells()
+        .all(|(_, y)| y == piece.cells().next().unwrap().1));
+}
+
+#[test]
+fn line_clear_scores_and_shifts_rows() {
+    let mut game = find_game_starting_with(TetrominoKind::I);
+    let columns = game
+        .current_piece()
+        .unwrap()
+        .cells()
+        .map(|(x, _)| x as usize)
+        .collect::<Vec<_>>();
+    for x in (0..BOARD_WIDTH).filter(|x| !columns.contains(x)) {
+        game.set_cell(x, 0, Some(TetrominoKind::O));
+        game.set_cell(x, 1, Some(TetrominoKind::O));
+    }
+    let drop_distance = game.current_piece().unwrap().y - game.ghost_piece().unwrap().y;
+
+    game.apply(TetrisInput::HardDrop);
+
+    assert_eq!(game.lines(), 1);
+    assert_eq!(game.score(), 100 + 2 * drop_distance as u32);
+    // 2 行目にあったブロックが 1 行目に落ちてくる
+    assert!((0..BOARD_WIDTH).all(|x| game.cell(x, 0).is_some() != columns.contains(&x)));
+    assert!((0..BOARD_WIDTH).all(|x| game.cell(x, 1).is_none()));
+}
+
+#[test]
+fn gravity_moves_piece_down() {
+    let mut game = TetrisGame::new(0);
+    let y = game.current_piece().unwrap().y;
+    game.tick(game.gravity_interval());
+    assert_eq!(game.current_piece().unwrap().y, y - 1);
+}
+
+#[test]
+fn stacking_to_the_top_ends_game() {
+    let mut game = TetrisGame::new(3);
+    for _ in 0..100 {
+        if game.is_game_over() {
+            break;
+        }
+        game.apply(TetrisInput::HardDrop);
+    }
+    assert!(game.is_game_over());
+}
@@ -0,0 +1,130 @@
+// derive(Std140) と derive(Std430) が作るバイト列のレイアウト
+
+use std::mem::{offset_of, size_of};
+
+use demolib::{Std140, Std430};
+
+#[derive(Std140, Std430)]
+struct Material {
+    color: [f32; 3],
+    roughness: f32,
+    emissive: [f32; 3],
+    unlit: bool,
+}
+
+#[derive(Std140, Std430)]
+struct Light {
+    direction: [f32; 2],
+    intensity: f32,
+}
+
+#[derive(Std140, Std430)]
+struct Scene {
+    light_count: u32,
+    lights: [Light; 3],
+    weights: [f32; 5],
+    transform: [[f32; 3]; 3],
+    material: Material,
+    exposure: f32,
+}
+
+fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+fn pack_scalars_after_vec3() {
+    let material = Material {
+        color: [0.25, 0.5, 0.75],
+        roughness: 0.5,
+        emissive: [1.0, 2.0, 3.0],
+        unlit: true,
+    };
+    assert_eq!(offset_of!(MaterialStd140, roughness), 12);
+    assert_eq!(offset_of!(MaterialStd140, emissive), 16);
+    assert_eq!(offset_of!(MaterialStd140, unlit), 28);
+    assert_eq!(size_of::<MaterialStd140>(), 32);
+    assert_eq!(<Material as Std140>::ALIGN, 16);
+    assert_eq!(
+        words(bytemuck::bytes_of(&material.std140())),
+        [
+            0.25f32.to_bits(),
+            0.5f32.to_bits(),
+            0.75f32.to_bits(),
+            0.5f32.to_bits(),
+            1.0f32.to_bits(),
+            2.0f32.to_bits(),
+            3.0f32.to_bits(),
+            1,
+        ]
+    );
+    // ベクトルとスカラーだけならどちらのレイアウトでも同じ
+    assert_eq!(
+        bytemuck::bytes_of(&material.std140()),
+        bytemuck::bytes_of(&material.std430())
+    );
+}
+
+#[test]
+fn std140_rounds_arrays_and_structs_to_vec4() {
+    assert_eq!(size_of::<LightStd140>(), 16);
+    assert_eq!(offset_of!(SceneStd140, lights), 16);
+    assert_eq!(offset_of!(SceneStd140, weights), 64);
+    // float の配列も 16 バイトおき
+    assert_eq!(offset_of!(SceneStd140, transform), 144);
+    assert_eq!(offset_of!(SceneStd140, material), 192);
+    assert_eq!(offset_of!(SceneStd140, exposure), 224);
+    assert_eq!(size_of::<SceneStd140>(), 240);
+}
+
+#[test]
+fn std430_packs_arrays_and_structs() {
+    assert_eq!(size_of::<LightStd430>(), 16);
+    assert_eq!(<Light as Std430>::ALIGN, 8);
+    assert_eq!(offset_of!(SceneStd430, lights), 8);
+    assert_eq!(offset_of!(SceneStd430, weights), 56);
+    // vec3 の配列は std430 でも 16 バイトおき
+    assert_eq!(offset_of!(SceneStd430, transform), 80);
+    assert_eq!(offset_of!(SceneStd430, material), 128);
+    assert_eq!(offset_of!(SceneStd430, exposure), 160);
+    assert_eq!(size_of::<SceneStd430>(), 176);
+}
+
+#[test]
+fn write_array_elements_at_stride() {
+    let scene = Scene {
+        light_count: 2,
+        lights: std::array::from_fn(|index| Light {
+            direction: [index as f32, 1.0],
+            intensity: 10.0 + index as f32,
+        }),
+        weights: [1.0, 2.0, 3.0, 4.0, 5.0],
+        transform: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        material: Material {
+            color: [1.0; 3],
+            roughness: 0.0,
+            emissive: [0.0; 3],
+            unlit: false,
+        },
+        exposure: 0.5,
+    };
+
+    let std140 = words(bytemuck::bytes_of(&scene.std140()));
+    assert_eq!(std140[0], 2);
+    // パディングは 0 で埋める
+    assert_eq!(&std140[1..4], [0, 0, 0]);
+    assert_eq!(
+        &std140[8..12],
+        [1.0f32.to_bits(), 1.0f32.to_bits(), 11.0f32.to_bits(), 0]
+    );
+    assert_eq!(std140[16 + 4], 2.0f32.to_bits());
+    assert_eq!(std140[36 + 4 + 1], 1.0f32.to_bits());
+    assert_eq!(std140[56], 0.5f32.to_bits());
+
+    let std430 = words(bytemuck::bytes_of(&scene.std430()));
+    assert_eq!(&std430[14..19], [1.0, 2.0, 3.0, 4.0, 5.0].map(f32::to_bits));
+    assert_eq!(std430[40], 0.5f32.to_bits());
+}
@@ -0,0 +1,152 @@
+// shaders.ron でコンパイルし分けたシェーダーと、それを使うパイプラインの使い回し
+
+use std::cell::Cell;
+
+use demolib::{
+    shader_source, shader_variant, Demo, FractalType, Image, Mandelbrot, MandelbrotParams, Model3d,
+    Model3dParams, PipelineCache, HEADLESS_COLOR_FORMAT,
+};
+
+mod common;
+
+use common::fallback_renderer;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+#[test]
+fn find_shader_variants() {
+    assert_eq!(
+        shader_variant("mandelbrot.fs", &[("FRACTAL_JULIA", "1")]),
+        "julia.fs"
+    );
+    assert_eq!(
+        shader_variant("model_3d.fs", &[("SHADOWS", "0")]),
+        "model_3d.fs[SHADOWS=0]"
+    );
+    assert_eq!(shader_variant("model_3d.vs", &[]), "model_3d.vs");
+
+    // 影を読まない方はシャドウマップを参照しない
+    let with_shadows = shader_source(shader_variant("model_3d.fs", &[("SHADOWS", "1")]));
+    let without_shadows = shader_source(shader_variant("model_3d.fs", &[("SHADOWS", "0")]));
+    assert!(with_shadows.contains("textureSampleCompare"));
+    assert!(!without_shadows.contains("textureSampleCompare"));
+}
+
+#[test]
+#[should_panic(expected = "is not compiled")]
+fn unknown_shader_variant() {
+    shader_variant("model_3d.fs", &[("SHADOWS", "2")]);
+}
+
+#[test]
+fn create_pipeline_once_per_key() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let device = renderer.device();
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(
+            "@vertex fn vs() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }
+             @fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }"
+                .into(),
+        ),
+    });
+    let created = Cell::new(0);
+    let create = |format: wgpu::TextureFormat| {
+        created.set(created.get() + 1);
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs",
+                targets: &[Some(format.into())],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    };
+
+    let mut cache = PipelineCache::new();
+    assert!(cache.is_empty());
+    cache.prepare(wgpu::TextureFormat::Rgba8Unorm, create);
+    cache.prepare(wgpu::TextureFormat::Rgba8Unorm, create);
+    cache.prepare(wgpu::TextureFormat::Bgra8Unorm, create);
+    assert_eq!(created.get(), 2);
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(wgpu::TextureFormat::Rgba8Unorm).is_some());
+    assert!(cache.get(wgpu::TextureFormat::R8Unorm).is_none());
+}
+
+#[test]
+fn switch_fractal_type() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let mut demo = Mandelbrot::create(renderer.device(), HEADLESS_COLOR_FORMAT);
+    let mut images: Vec<Image> = Vec::new();
+    for fractal_type in FractalType::ALL {
+        let mut params = MandelbrotParams::default();
+        params.set_fractal_type(fractal_type);
+        let image = renderer
+            .render_demo(&mut demo, &params, WIDTH, HEIGHT)
+            .unwrap();
+        assert!(
+            images.iter().all(|other| other.pixels != image.pixels),
+            "{} looks like another fractal",
+            fractal_type.name()
+        );
+        images.push(image);
+    }
+}
+
+// Multibrot は f32 でしか計算しないので、ノイズになるほどは拡大させない
+#[test]
+fn clamp_zoom_per_fractal_type() {
+    for fractal_type in FractalType::ALL {
+        let mut params = MandelbrotParams::default();
+        params.set_fractal_type(fractal_type);
+        for _ in 0..200 {
+            params.zoom(0.5, [0.0, 0.0]);
+        }
+        assert_eq!(params.scale, fractal_type.min_scale());
+        assert_eq!(
+            params.is_double_float_required(),
+            fractal_type.supports_double_float()
+        );
+    }
+    assert!(FractalType::Multibrot.min_scale() >= 1.0e-5);
+    assert!(FractalType::Mandelbrot.min_scale() < 1.0e-10);
+}
+
+#[test]
+fn toggle_shadows() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let mut demo = Model3d::create(renderer.device(), HEADLESS_COLOR_FORMAT);
+    let mut params = Model3dParams::default();
+    let with_shadows = renderer
+        .render_demo(&mut demo, &params, WIDTH, HEIGHT)
+        .unwrap();
+    params.shadow.enabled = false;
+    let without_shadows = renderer
+        .render_demo(&mut demo, &params, WIDTH, HEIGHT)
+        .unwrap();
+    params.shadow.enabled = true;
+    let with_shadows_again = renderer
+        .render_demo(&mut demo, &params, WIDTH, HEIGHT)
+        .unwrap();
+
+    assert!(without_shadows.pixels.iter().any(|value| *value != 0));
+    assert_eq!(with_shadows.pixels, with_shadows_again.pixels);
+}
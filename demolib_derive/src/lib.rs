@@ -0,0 +1,199 @@
+// GPU に送る構造体の std140 / std430 の表現を作る derive マクロ
+//
+//   #[derive(Std140)]
+//   struct ViewUniform {
+//       view_projection: [[f32; 4]; 4],
+//       camera_position: [f32; 3],
+//       light_count: u32,
+//   }
+//
+// パディングを明示した #[repr(C)] の ViewUniformStd140 と、demolib::Std140 の実装を生成する
+// 各フィールドのオフセットは demolib::__private::StructLayout がコンパイル時に計算する
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Lit, Type};
+
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, "Std140")
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, "Std430")
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum FieldType<'a> {
+    // スカラー、ベクトル、Std140 / Std430 を実装した構造体
+    Value(&'a Type),
+    // 要素ごとに配列のストライドまでパディングする (行列は列ベクトルの配列)
+    Array { element: &'a Type, len: &'a Expr },
+}
+
+fn field_type(ty: &Type) -> syn::Result<FieldType<'_>> {
+    let Type::Array(array) = ungroup(ty) else {
+        return Ok(FieldType::Value(ty));
+    };
+    if is_vector(ty) {
+        return Ok(FieldType::Value(ty));
+    }
+    if matches!(ungroup(&array.elem), Type::Array(_)) && !is_vector(&array.elem) {
+        return Err(syn::Error::new_spanned(
+            ty,
+            "arrays of arrays are only supported for vectors; wrap the inner array in a struct",
+        ));
+    }
+    Ok(FieldType::Array {
+        element: &array.elem,
+        len: &array.len,
+    })
+}
+
+// マクロを通った型は Type::Group に包まれていることがある
+fn ungroup(ty: &Type) -> &Type {
+    match ty {
+        Type::Group(group) => ungroup(&group.elem),
+        Type::Paren(paren) => ungroup(&paren.elem),
+        _ => ty,
+    }
+}
+
+// [f32; 3] のように、要素がスカラーで長さが 2 から 4 のリテラルの配列
+fn is_vector(ty: &Type) -> bool {
+    let Type::Array(array) = ungroup(ty) else {
+        return false;
+    };
+    let is_scalar = matches!(
+        ungroup(&array.elem),
+        Type::Path(path)
+            if path.qself.is_none()
+                && ["f32", "i32", "u32"].iter().any(|scalar| path.path.is_ident(scalar))
+    );
+    let is_vector_length = matches!(
+        &array.len,
+        Expr::Lit(ExprLit { lit: Lit::Int(len), .. })
+            if matches!(len.base10_parse::<usize>(), Ok(2..=4))
+    );
+    is_scalar && is_vector_length
+}
+
+fn expand(input: &DeriveInput, rule: &str) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            format!("{rule} can only be derived for structs"),
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            input,
+            format!("{rule} can only be derived for structs with named fields"),
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            format!("{rule} cannot be derived for generic structs"),
+        ));
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let rule_name = format_ident!("{rule}");
+    let method = format_ident!("{}", rule.to_lowercase());
+    let array_method = format_ident!("{}_array", rule.to_lowercase());
+    let output = format_ident!("{name}{rule}");
+    let layout = format_ident!("__{name}_{}_LAYOUT", rule.to_uppercase());
+    let field_count = fields.named.len();
+
+    let mut field_layouts = Vec::new();
+    let mut output_fields = Vec::new();
+    let mut output_values = Vec::new();
+    for (index, field) in fields.named.iter().enumerate() {
+        let ident = field.ident.as_ref().unwrap();
+        let field_vis = &field.vis;
+        let padding = format_ident!("__padding{index}");
+        let (field_layout, output_type, value) = match field_type(&field.ty)? {
+            FieldType::Value(ty) => (
+                quote! {
+                    (
+                        <#ty as ::demolib::#rule_name>::ALIGN,
+                        ::core::mem::size_of::<<#ty as ::demolib::#rule_name>::Output>(),
+                    )
+                },
+                quote! { <#ty as ::demolib::#rule_name>::Output },
+                quote! { ::demolib::#rule_name::#method(&self.#ident) },
+            ),
+            FieldType::Array { element, len } => (
+                quote! {
+                    ::demolib::__private::LayoutRule::#rule_name.array(
+                        <#element as ::demolib::#rule_name>::ALIGN,
+                        ::core::mem::size_of::<<#element as ::demolib::#rule_name>::Output>(),
+                        #len,
+                    )
+                },
+                quote! {
+                    [[u8; ::demolib::__private::LayoutRule::#rule_name.array_stride(
+                        <#element as ::demolib::#rule_name>::ALIGN,
+                        ::core::mem::size_of::<<#element as ::demolib::#rule_name>::Output>(),
+                    )]; #len]
+                },
+                quote! { ::demolib::__private::#array_method(&self.#ident) },
+            ),
+        };
+        field_layouts.push(field_layout);
+        output_fields.push(quote! {
+            #padding: [u8; #layout.padding[#index]],
+            #field_vis #ident: #output_type,
+        });
+        output_values.push(quote! {
+            #padding: ::demolib::__private::bytemuck::Zeroable::zeroed(),
+            #ident: #value,
+        });
+    }
+
+    Ok(quote! {
+        #[allow(non_upper_case_globals)]
+        const #layout: ::demolib::__private::StructLayout<#field_count> =
+            ::demolib::__private::StructLayout::new(
+                ::demolib::__private::LayoutRule::#rule_name,
+                [#(#field_layouts),*],
+            );
+
+        #[allow(dead_code)]
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        #vis struct #output {
+            #(#output_fields)*
+            __padding_end: [u8; #layout.trailing],
+        }
+
+        // 暗黙のパディングがなければ、全フィールドの大きさの和と一致する
+        const _: () = assert!(
+            ::core::mem::size_of::<#output>() == #layout.size,
+            concat!("size of ", stringify!(#output), " does not match the ", #rule, " layout"),
+        );
+
+        unsafe impl ::demolib::__private::bytemuck::Zeroable for #output {}
+        unsafe impl ::demolib::__private::bytemuck::Pod for #output {}
+
+        impl ::demolib::#rule_name for #name {
+            const ALIGN: usize = #layout.align;
+            type Output = #output;
+
+            fn #method(&self) -> #output {
+                #output {
+                    #(#output_values)*
+                    __padding_end: ::demolib::__private::bytemuck::Zeroable::zeroed(),
+                }
+            }
+        }
+    })
+}
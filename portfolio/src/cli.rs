@@ -0,0 +1,217 @@
+// portfolio のコマンドライン
+//   portfolio --demo Mandelbrot --project project.ron --size 1600x900 --backend vulkan --no-vsync
+//   portfolio list-demos
+//   portfolio render project.ron --fps 60 --pipe ffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - out.mp4
+//   portfolio validate-shaders
+// サブコマンドはウィンドウを開かない
+
+use std::{path::PathBuf, str::FromStr};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::RenderSettings;
+
+#[derive(Debug, Parser)]
+#[command(name = "portfolio", version, about = "wgpu demos with live parameters")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Demo to show first (see list-demos)"
+    )]
+    pub demo: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Project file (.ron / .json) to load on startup"
+    )]
+    pub project: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "WIDTHxHEIGHT",
+        help = "Initial window size in points"
+    )]
+    pub size: Option<Size>,
+
+    #[arg(long, global = true, help = "wgpu backend [default: all available]")]
+    pub backend: Option<Backend>,
+
+    #[arg(long, global = true, help = "Which adapter to prefer")]
+    pub power_preference: Option<PowerPreference>,
+
+    #[arg(long, help = "Present frames without waiting for vertical sync")]
+    pub no_vsync: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Print the names of the demos")]
+    ListDemos,
+    #[command(about = "Render a project to PNG frames without opening a window")]
+    Render(RenderArgs),
+    #[command(about = "Validate the embedded WGSL shaders without a GPU")]
+    ValidateShaders,
+}
+
+#[derive(Debug, Args)]
+pub struct RenderArgs {
+    #[arg(help = "Project file (.ron / .json)")]
+    pub project: PathBuf,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Demo to render [default: the project's]"
+    )]
+    pub demo: Option<String>,
+
+    #[arg(long, default_value = "frames", help = "Directory for the PNG frames")]
+    pub output: PathBuf,
+
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1280x720")]
+    pub size: Size,
+
+    #[arg(long, default_value_t = 30.0, value_parser = parse_fps)]
+    pub fps: f64,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+    pub start: f64,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Length to render [default: to the end of the timeline]"
+    )]
+    pub duration: Option<f64>,
+
+    // --pipe より後ろはすべてエンコーダーのコマンドライン
+    #[arg(
+        long,
+        value_name = "PROGRAM ARGS",
+        num_args = 1..,
+        allow_hyphen_values = true,
+        help = "Also pipe raw RGBA frames to PROGRAM; {width} {height} {fps} in ARGS are replaced"
+    )]
+    pub pipe: Vec<String>,
+}
+
+impl RenderArgs {
+    pub fn settings(&self) -> RenderSettings {
+        RenderSettings {
+            demo: self.demo.clone(),
+            width: self.size.width,
+            height: self.size.height,
+            fps: self.fps,
+            start: self.start,
+            duration: self.duration,
+        }
+    }
+}
+
+fn parse_fps(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(fps) if fps > 0.0 && fps.is_finite() => Ok(fps),
+        Ok(_) => Err("must be positive".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+// 1280x720 のような大きさ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (width, height) = text
+            .split_once('x')
+            .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {text}"))?;
+        let parse = |value: &str| match value.trim().parse::<u32>() {
+            Ok(0) => Err("the size must not be zero".to_string()),
+            Ok(value) => Ok(value),
+            Err(error) => Err(format!("{error}: {value}")),
+        };
+        Ok(Self {
+            width: parse(width)?,
+            height: parse(height)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    // Vulkan, Metal, DX12, WebGPU
+    Primary,
+    Vulkan,
+    Metal,
+    Dx12,
+    Dx11,
+    Gl,
+}
+
+impl Backend {
+    pub fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::Primary => wgpu::Backends::PRIMARY,
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Dx11 => wgpu::Backends::DX11,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PowerPreference {
+    Low,
+    High,
+}
+
+impl From<PowerPreference> for wgpu::PowerPreference {
+    fn from(preference: PowerPreference) -> Self {
+        match preference {
+            PowerPreference::Low => wgpu::PowerPreference::LowPower,
+            PowerPreference::High => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl Cli {
+    // 指定がなければすべてのバックエンドから選ぶ
+    pub fn backends(&self) -> wgpu::Backends {
+        self.backend
+            .map_or(wgpu::Backends::all(), Backend::backends)
+    }
+
+    pub fn native_options(&self) -> eframe::NativeOptions {
+        let mut options = eframe::NativeOptions {
+            vsync: !self.no_vsync,
+            ..Default::default()
+        };
+        if let Some(size) = self.size {
+            options.initial_window_size =
+                Some(eframe::egui::vec2(size.width as f32, size.height as f32));
+        }
+        if let Some(backend) = self.backend {
+            options.wgpu_options.supported_backends = backend.backends();
+        }
+        if let Some(power_preference) = self.power_preference {
+            options.wgpu_options.power_preference = power_preference.into();
+        }
+        // wgpu では vsync ではなくスワップチェーンの表示モードで切り替わる
+        if self.no_vsync {
+            options.wgpu_options.present_mode = wgpu::PresentMode::AutoNoVsync;
+        }
+        options
+    }
+}
@@ -0,0 +1,259 @@
+// ワークスペースの編集履歴 (元に戻す / やり直す)
+// パラメーターの編集は前後のデモの状態 (DemoState) を持っておき、戻すときは前の状態を読み込む
+
+use std::collections::VecDeque;
+
+use demolib::DemoState;
+
+// 履歴が使うメモリの既定の上限 (バイト)
+pub const DEFAULT_HISTORY_BUDGET: usize = 16 * 1024 * 1024;
+
+// ボタンを離してからこの時間 (秒) のうちは、同じ項目の編集を同じステップにまとめる
+// マウスホイールやキーリピートでの編集がステップだらけにならないように
+const COALESCE_SECONDS: f64 = 0.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    // demo 番目のデモのパラメーターの編集
+    Params {
+        demo: usize,
+        before: DemoState,
+        after: DemoState,
+    },
+    // 表示するデモの切り替え
+    SelectDemo {
+        from: usize,
+        to: usize,
+    },
+}
+
+impl Edit {
+    // 状態を JSON にしたときの大きさで見積もる
+    fn size(&self) -> usize {
+        let state_size =
+            |state: &DemoState| serde_json::to_vec(&state.params).map_or(0, |bytes| bytes.len());
+        let state_size = match self {
+            Edit::Params { before, after, .. } => state_size(before) + state_size(after),
+            Edit::SelectDemo { .. } => 0,
+        };
+        std::mem::size_of::<Self>() + state_size
+    }
+}
+
+struct Step {
+    label: String,
+    edit: Edit,
+    size: usize,
+}
+
+impl Step {
+    fn new(label: String, edit: Edit) -> Self {
+        let size = label.len() + edit.size();
+        Self { label, edit, size }
+    }
+}
+
+// 最後のステップに続けて編集をまとめられる状態
+struct Continuation {
+    // 最後の編集のときにポインターのボタンが押されていたか
+    held: bool,
+    time: f64,
+}
+
+enum Merge {
+    Merged,
+    // 元の値に戻ったのでステップごと消した
+    Removed,
+    Rejected,
+}
+
+pub struct History {
+    // 古い順
+    undo: VecDeque<Step>,
+    // 戻した順 (最後がつぎにやり直すステップ)
+    redo: Vec<Step>,
+    budget: usize,
+    size: usize,
+    continuation: Option<Continuation>,
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            budget,
+            size: 0,
+            continuation: None,
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    // 上限を超えたぶんは古いステップから捨てる
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    // 保持しているステップが使っているメモリの見積もり (バイト)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // 適用済みのステップの数
+    // labels() のうち、これより前が適用済みで、これ以降が戻したステップ
+    pub fn position(&self) -> usize {
+        self.undo.len()
+    }
+
+    // 古い順のステップの説明
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|step| step.label.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.size = 0;
+        self.continuation = None;
+    }
+
+    // ひとつのステップとして積む
+    pub fn push(&mut self, label: String, edit: Edit) {
+        self.continuation = None;
+        self.push_step(Step::new(label, edit));
+    }
+
+    // ドラッグのような続けての編集を積む
+    // 直前のステップと同じデモの同じ項目の編集で、ボタンが押されたままか離してから間もなければ、直前のステップにまとめる
+    // time は入力の時刻 (秒)、held はポインターのボタンが押されているか
+    pub fn push_continuous(&mut self, label: String, edit: Edit, time: f64, held: bool) {
+        self.expire(time, held);
+        let merge = if self.continuation.is_some() {
+            self.merge(&label, &edit)
+        } else {
+            Merge::Rejected
+        };
+        self.continuation = match merge {
+            Merge::Merged => Some(Continuation { held, time }),
+            // 消したステップの前のステップにはまとめない
+            Merge::Removed => None,
+            Merge::Rejected => {
+                self.push_step(Step::new(label, edit));
+                Some(Continuation { held, time })
+            }
+        };
+    }
+
+    // 編集がないフレームでも呼んで、まとめられる時間を過ぎたら直前のステップを閉じる
+    pub fn expire(&mut self, time: f64, held: bool) {
+        let Some(continuation) = &mut self.continuation else {
+            return;
+        };
+        if !held {
+            continuation.held = false;
+        }
+        if !continuation.held && time - continuation.time >= COALESCE_SECONDS {
+            self.continuation = None;
+        }
+    }
+
+    // 戻すステップを取り出す
+    pub fn undo(&mut self) -> Option<&Edit> {
+        self.continuation = None;
+        let step = self.undo.pop_back()?;
+        self.redo.push(step);
+        self.redo.last().map(|step| &step.edit)
+    }
+
+    // やり直すステップを取り出す
+    pub fn redo(&mut self) -> Option<&Edit> {
+        self.continuation = None;
+        let step = self.redo.pop()?;
+        self.undo.push_back(step);
+        self.undo.back().map(|step| &step.edit)
+    }
+
+    fn merge(&mut self, label: &str, edit: &Edit) -> Merge {
+        let Some(step) = self.undo.back_mut() else {
+            return Merge::Rejected;
+        };
+        let (
+            Edit::Params {
+                demo,
+                before,
+                after,
+                ..
+            },
+            Edit::Params {
+                demo: next_demo,
+                after: next_after,
+                ..
+            },
+        ) = (&mut step.edit, edit)
+        else {
+            return Merge::Rejected;
+        };
+        if step.label != label || demo != next_demo {
+            return Merge::Rejected;
+        }
+
+        *after = next_after.clone();
+        // ドラッグで元の値に戻ったらステップごと消す
+        let is_unchanged = before == after;
+        self.size -= step.size;
+        if is_unchanged {
+            self.undo.pop_back();
+            return Merge::Removed;
+        }
+        step.size = step.label.len() + step.edit.size();
+        self.size += step.size;
+        self.evict();
+        Merge::Merged
+    }
+
+    fn push_step(&mut self, step: Step) {
+        self.size -= self.redo.drain(..).map(|step| step.size).sum::<usize>();
+        self.size += step.size;
+        self.undo.push_back(step);
+        self.evict();
+    }
+
+    // 直前のステップは上限を超えていても残す
+    fn evict(&mut self) {
+        while self.size > self.budget {
+            let step = if self.undo.len() > 1 {
+                self.undo.pop_front()
+            } else if !self.redo.is_empty() {
+                // 戻したステップはいちばん先のものから
+                Some(self.redo.remove(0))
+            } else {
+                None
+            };
+            let Some(step) = step else {
+                break;
+            };
+            self.size -= step.size;
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_BUDGET)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use demolib::{record_shaders, set_shader_override, Demo, ShaderReloader};
+
+use crate::CreateDemo;
+
+// ファイルの更新日時を調べる間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+struct DemoFactory {
+    create: CreateDemo,
+    // 作成時に参照したシェーダーの名前
+    shaders: HashSet<&'static str>,
+}
+
+// シェーダーの変更を見張って、そのシェーダーを使っているデモを作り直す
+// 作り直しに失敗したら差し替えを取り消して、それまでのパイプラインを使い続ける
+pub struct HotReload {
+    reloader: ShaderReloader,
+    // DemoManager::demos と同じ順
+    factories: Vec<DemoFactory>,
+    last_poll: Instant,
+}
+
+impl HotReload {
+    pub fn new() -> Self {
+        Self {
+            reloader: ShaderReloader::default(),
+            factories: Vec::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    // デモを作って、使っているシェーダーを覚えておく
+    pub fn create(
+        &mut self,
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        create: CreateDemo,
+    ) -> Box<dyn Demo> {
+        let (demo, shaders) = record_shaders(|| create(device, target_format));
+        self.factories.push(DemoFactory { create, shaders });
+        demo
+    }
+
+    // (GLSL のファイル名, メッセージ)
+    pub fn errors(&self) -> Vec<(&'static str, String)> {
+        self.reloader
+            .errors()
+            .map(|(source, message)| (source, message.to_string()))
+            .collect()
+    }
+
+    pub fn reload<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        demos: &mut [Box<dyn Demo + 'a>],
+        size: [u32; 2],
+    ) {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        let updates = self.reloader.poll();
+        if updates.is_empty() {
+            return;
+        }
+
+        let names: HashSet<_> = updates.iter().map(|update| update.name).collect();
+        let previous: Vec<_> = updates
+            .iter()
+            .map(|update| {
+                let previous = set_shader_override(update.name, Some(update.wgsl.clone()));
+                (update.name, previous)
+            })
+            .collect();
+
+        // 検証エラーはパニックさせずに受け取る
+        let mut rebuilt = Vec::new();
+        let mut error = None;
+        for (index, factory) in self.factories.iter().enumerate() {
+            if factory.shaders.is_disjoint(&names) {
+                continue;
+            }
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let (demo, shaders) = record_shaders(|| (factory.create)(device, target_format));
+            if let Some(scope_error) = futures::executor::block_on(device.pop_error_scope()) {
+                error = Some(scope_error.to_string());
+                break;
+            }
+            rebuilt.push((index, demo, shaders));
+        }
+
+        if let Some(error) = error {
+            for (name, previous) in previous {
+                set_shader_override(name, previous);
+            }
+            for update in &updates {
+                self.reloader.set_error(update.source, error.clone());
+            }
+            return;
+        }
+
+        for (index, mut demo, shaders) in rebuilt {
+            demo.resize(device, size[0], size[1]);
+            demos[index] = demo;
+            self.factories[index].shaders = shaders;
+        }
+    }
+}
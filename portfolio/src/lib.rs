@@ -3,42 +3,85 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-//mod mandelbrot;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod history;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+mod hot_reload;
+mod project;
 mod property_panel;
+#[cfg(not(target_arch = "wasm32"))]
+mod render;
+mod timeline;
+mod timeline_panel;
+#[cfg(not(target_arch = "wasm32"))]
+mod validate;
 mod workspace;
 
-use demolib::{Mandelbrot, Model3d, Triangle};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cli::{Backend, Cli, Command, PowerPreference, RenderArgs, Size};
+use demolib::{Demo, HeadlessError, Image, Mandelbrot, Model3d, Physics, Tetris, Triangle};
 use eframe::egui_wgpu::{CallbackResources, CallbackTrait};
-use futures_intrusive::channel::shared::GenericOneshotReceiver;
+pub use history::{Edit, History, DEFAULT_HISTORY_BUDGET};
+pub use project::{ProjectError, ProjectFormat, WorkspaceState, WORKSPACE_VERSION};
 pub use property_panel::PropertyPanel;
+#[cfg(not(target_arch = "wasm32"))]
+pub use render::{
+    create_demos, render, CommandSink, FrameSink, PngSequence, RenderError, RenderSettings,
+};
+pub use timeline::{Interpolation, Keyframe, Timeline, Track};
+pub use timeline_panel::TimelinePanel;
+#[cfg(not(target_arch = "wasm32"))]
+pub use validate::{validate_shaders, validate_wgsl};
 
-//pub use mandelbrot::Mandelbrot;
-use parking_lot::RawMutex;
-use wgpu::{util::DeviceExt, BufferAsyncError};
+use wgpu::util::DeviceExt;
 pub use workspace::Workspace;
 
-#[derive(PartialEq, Clone, Copy)]
-pub enum DemoType {
-    Triangle,
-    Mandelbrot,
-    Model3d,
-    Physics,
-    Tetris,
+// デモの描画結果を書き込むカラーバッファーのフォーマット
+// スクリーンショットをヘッドレス描画と同じ経路で読み戻せるようにそろえておく
+const COLOR_BUFFER_FORMAT: wgpu::TextureFormat = demolib::HEADLESS_COLOR_FORMAT;
+
+// デモを作る関数
+pub type CreateDemo = fn(&wgpu::Device, wgpu::TextureFormat) -> Box<dyn Demo>;
+
+fn create_demo<T: Demo + 'static>(
+    device: &wgpu::Device,
+    target_format: wgpu::TextureFormat,
+) -> Box<dyn Demo> {
+    Box::new(T::create(device, target_format))
 }
 
+// 登録するデモ (Demo::name と同じ名前, 作る関数)
+// ウィンドウ、オフライン描画、list-demos はすべてこの一覧を使う
+pub const DEMOS: [(&str, CreateDemo); 5] = [
+    ("Triangle", create_demo::<Triangle>),
+    ("Mandelbrot", create_demo::<Mandelbrot>),
+    ("Model3d", create_demo::<Model3d>),
+    ("Tetris", create_demo::<Tetris>),
+    ("Physics", create_demo::<Physics>),
+];
+
+// 最初のフレームで描画先の大きさがわかるまでの仮の大きさ
+const DEFAULT_BUFFER_SIZE: [u32; 2] = [700, 700];
+
 pub struct DemoManager<'a> {
     workspace: Arc<Mutex<Workspace>>,
-    triangle: Triangle<'a>,
-    mandelbrot: Mandelbrot<'a>,
-    model_3d: Model3d<'a>,
+    demos: Vec<Box<dyn Demo + 'a>>,
 
     // 四角形描画
     render_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     color_buffer: wgpu::Texture,
     depth_buffer: wgpu::Texture,
+    // カラーバッファーと深度バッファーの大きさ (物理ピクセル)
+    buffer_size: [u32; 2],
+
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    hot_reload: hot_reload::HotReload,
 }
 
 impl<'a> DemoManager<'a> {
@@ -49,41 +92,19 @@ impl<'a> DemoManager<'a> {
     ) -> Self {
         let vertex_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("draw_texture.vs.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("OUT_DIR"),
+                "/draw_texture.vs.wgsl"
+            )))),
         });
         let pixel_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("draw_texture.fs.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("OUT_DIR"),
+                "/draw_texture.fs.wgsl"
+            )))),
         });
 
-        let color_buffer = device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: 700,
-                height: 700,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-        });
-        let depth_buffer = device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: 700,
-                height: 700,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -162,51 +183,91 @@ impl<'a> DemoManager<'a> {
             multiview: Default::default(),
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&color_buffer.create_view(
-                        &wgpu::TextureViewDescriptor {
-                            label: None,
-                            format: Some(wgpu::TextureFormat::Rgba8Unorm),
-                            dimension: Some(wgpu::TextureViewDimension::D2),
-                            aspect: wgpu::TextureAspect::All,
-                            base_mip_level: 0,
-                            mip_level_count: None,
-                            base_array_layer: 0,
-                            array_layer_count: None,
-                        },
-                    )),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+        let (color_buffer, depth_buffer, bind_group) =
+            create_render_targets(&device, &bind_group_layout, &sampler, DEFAULT_BUFFER_SIZE);
 
-        Self {
+        let mut demo_manager = Self {
             workspace,
-            triangle: Triangle::new(&device, wgpu::TextureFormat::Rgba8Unorm),
-            mandelbrot: Mandelbrot::new(&device, wgpu::TextureFormat::Rgba8Unorm),
-            model_3d: Model3d::new(&device, wgpu::TextureFormat::Rgba8Unorm),
+            demos: Vec::new(),
             // 四角形描画
             render_pipeline,
+            bind_group_layout,
             bind_group,
+            sampler,
             vertex_buffer,
             index_buffer,
             color_buffer,
             depth_buffer,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+            hot_reload: hot_reload::HotReload::new(),
+        };
+        for (_, create) in DEMOS {
+            demo_manager.register(&device, create);
+        }
+        demo_manager
+    }
+
+    // デモを追加して、そのパラメーターをワークスペースに登録する
+    pub fn register(&mut self, device: &wgpu::Device, create: CreateDemo) {
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        let mut demo = self.hot_reload.create(device, COLOR_BUFFER_FORMAT, create);
+        #[cfg(not(all(feature = "hot-reload", not(target_arch = "wasm32"))))]
+        let mut demo = create(device, COLOR_BUFFER_FORMAT);
+        demo.resize(device, self.buffer_size[0], self.buffer_size[1]);
+        self.workspace
+            .lock()
+            .unwrap()
+            .add_demo(demo.name(), demo.create_params());
+        self.demos.push(demo);
+    }
+
+    // 描画先の大きさが変わったらカラーバッファーと深度バッファーを作り直す
+    pub fn resize(&mut self, device: &wgpu::Device, size: [u32; 2]) {
+        let max_size = device.limits().max_texture_dimension_2d;
+        let size = size.map(|value| value.clamp(1, max_size));
+        if size == self.buffer_size {
+            return;
+        }
+
+        self.buffer_size = size;
+        (self.color_buffer, self.depth_buffer, self.bind_group) =
+            create_render_targets(device, &self.bind_group_layout, &self.sampler, size);
+        for demo in &mut self.demos {
+            demo.resize(device, size[0], size[1]);
         }
     }
 
-    pub fn update(&mut self, queue: &wgpu::Queue) {
-        let workspace = self.workspace.lock().unwrap();
-        let triangle_params = workspace.get_triangle_params();
-        self.triangle.update(queue, triangle_params);
+    // 変更されたシェーダーを読み込み直して、それを使うデモを作り直す
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    pub fn reload_shaders(&mut self, device: &wgpu::Device) {
+        self.hot_reload.reload(
+            device,
+            COLOR_BUFFER_FORMAT,
+            &mut self.demos,
+            self.buffer_size,
+        );
+    }
+
+    // (GLSL のファイル名, メッセージ)
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    pub fn shader_errors(&self) -> Vec<(&'static str, String)> {
+        self.hot_reload.errors()
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut workspace = self.workspace.lock().unwrap();
+        // 手で書き換えたプロジェクトのトラックは読み込むときに確かめていないので失敗しうる
+        // エラーはタイムラインのパネルに表示する
+        let result = workspace.apply_timeline();
+        workspace.set_timeline_error(result.err());
+        let index = workspace.get_current_demo_index();
+        let (Some(demo), Some(params)) =
+            (self.demos.get_mut(index), workspace.get_demo_params(index))
+        else {
+            return;
+        };
+        demo.update(device, queue, params);
     }
 
     pub async fn do_something(&mut self) {}
@@ -215,11 +276,16 @@ impl<'a> DemoManager<'a> {
         &'a self,
         mut command_encoder: wgpu::CommandEncoder,
     ) -> Option<wgpu::CommandEncoder> {
-        let workspace = self.workspace.lock().unwrap();
+        let Some(demo) = self
+            .demos
+            .get(self.workspace.lock().unwrap().get_current_demo_index())
+        else {
+            return Some(command_encoder);
+        };
 
         let texture_view = self.color_buffer.create_view(&wgpu::TextureViewDescriptor {
             label: None,
-            format: Some(wgpu::TextureFormat::Rgba8Unorm),
+            format: Some(COLOR_BUFFER_FORMAT),
             dimension: Some(wgpu::TextureViewDimension::D2),
             aspect: wgpu::TextureAspect::All,
             base_mip_level: 0,
@@ -238,46 +304,26 @@ impl<'a> DemoManager<'a> {
             array_layer_count: None,
         });
 
-        {
-            let is_depth_required = match workspace.get_current_demo_type() {
-                DemoType::Triangle => false,
-                DemoType::Mandelbrot => false,
-                DemoType::Model3d => true,
-                _ => false,
-            };
-
-            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations::default(),
-                })],
-                depth_stencil_attachment: if is_depth_required {
-                    Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: &depth_buffer_view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
-                            store: true,
-                        }),
-                        stencil_ops: None,
-                    })
-                } else {
-                    None
-                },
-            });
-
-            match workspace.get_current_demo_type() {
-                DemoType::Triangle => self.triangle.draw(&mut render_pass),
-                DemoType::Mandelbrot => self.mandelbrot.draw(&mut render_pass),
-                DemoType::Model3d => self.model_3d.draw(&mut render_pass),
-                _ => {}
-            }
-        }
+        demolib::encode_demo(
+            demo.as_ref(),
+            &mut command_encoder,
+            &texture_view,
+            &depth_buffer_view,
+        );
 
         Some(command_encoder)
     }
 
+    // 直近のフレームで描画したカラーバッファーを読み戻す
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn screenshot(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Image, HeadlessError> {
+        Image::from_texture(device, queue, &self.color_buffer)
+    }
+
     pub fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
         // カラーバッファーのコンテンツをスキャンバッファーにコピー
         // ただし eframe にカラーバッファーをコピーする API がないので、カラーバッファーを画面いっぱいに描画することで代用している
@@ -289,30 +335,14 @@ impl<'a> DemoManager<'a> {
     }
 }
 
-trait IDemoImpl<'a> {
-    type TParams;
-
-    fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self;
-
-    fn update(
-        &mut self,
-        param: &Self::TParams,
-    ) -> GenericOneshotReceiver<RawMutex, Result<(), BufferAsyncError>>;
-
-    fn draw(&'a self, render_pass: &mut wgpu::RenderPass<'a>);
+pub struct RenderBridge {
+    // 描画先の大きさ (物理ピクセル)
+    size: [u32; 2],
 }
 
-pub struct RenderBridge;
-
 impl RenderBridge {
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl Default for RenderBridge {
-    fn default() -> Self {
-        Self::new()
+    pub fn new(size: [u32; 2]) -> Self {
+        Self { size }
     }
 }
 
@@ -328,7 +358,10 @@ impl CallbackTrait for RenderBridge {
             return Vec::new();
         };
 
-        demo_manager.update(queue);
+        demo_manager.resize(device, self.size);
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        demo_manager.reload_shaders(device);
+        demo_manager.update(device, queue);
 
         let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
         if let Some(encoder) = demo_manager.draw_pre(encoder) {
@@ -351,3 +384,66 @@ impl CallbackTrait for RenderBridge {
         demo_manager.draw(render_pass);
     }
 }
+
+// (カラーバッファー, 深度バッファー, カラーバッファーを描画するためのバインドグループ)
+fn create_render_targets(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    size: [u32; 2],
+) -> (wgpu::Texture, wgpu::Texture, wgpu::BindGroup) {
+    let size = wgpu::Extent3d {
+        width: size[0],
+        height: size[1],
+        depth_or_array_layers: 1,
+    };
+    let color_buffer = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: COLOR_BUFFER_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[COLOR_BUFFER_FORMAT],
+    });
+    let depth_buffer = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth32Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&color_buffer.create_view(
+                    &wgpu::TextureViewDescriptor {
+                        label: None,
+                        format: Some(COLOR_BUFFER_FORMAT),
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        aspect: wgpu::TextureAspect::All,
+                        base_mip_level: 0,
+                        mip_level_count: None,
+                        base_array_layer: 0,
+                        array_layer_count: None,
+                    },
+                )),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+    (color_buffer, depth_buffer, bind_group)
+}
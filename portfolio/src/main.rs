@@ -1,9 +1,34 @@
-use eframe::{egui_wgpu::Callback, CreationContext};
+use eframe::{
+    egui::{Button, Key, Modifiers, RichText, ScrollArea},
+    egui_wgpu::Callback,
+    CreationContext,
+};
 use std::sync::{Arc, Mutex};
 
-use portfolio::{DemoManager, PropertyPanel, RenderBridge, Workspace};
+use portfolio::{
+    DemoManager, ProjectFormat, PropertyPanel, RenderBridge, TimelinePanel, Workspace,
+    WorkspaceState,
+};
 
 fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let cli = {
+        use clap::Parser;
+        portfolio::Cli::parse()
+    };
+
+    // サブコマンドはウィンドウを開かずに終わる
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Err(error) = run_command(&cli) {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        }
+        if cli.command.is_some() {
+            return;
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .worker_threads(4)
@@ -16,37 +41,131 @@ fn main() {
         .unwrap();
 
     let runtime = Arc::new(runtime);
+    #[cfg(not(target_arch = "wasm32"))]
+    run(runtime, cli);
+    #[cfg(target_arch = "wasm32")]
     run(runtime);
 }
 
-fn run(runtime: Arc<tokio::runtime::Runtime>) {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let options = eframe::NativeOptions::default();
+// ウィンドウを開く前に --demo の名前を確かめる
+#[cfg(not(target_arch = "wasm32"))]
+fn check_demo(name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    match name {
+        Some(name) if !portfolio::DEMOS.iter().any(|(demo, _)| *demo == name) => {
+            let names: Vec<&str> = portfolio::DEMOS.iter().map(|(name, _)| *name).collect();
+            Err(format!(
+                "unknown demo: {name} (expected one of {})",
+                names.join(", ")
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
 
-        eframe::run_native(
-            "My Window",
-            options,
-            Box::new(|cc| Box::new(App::new(runtime, cc))),
-        )
-        .unwrap();
+#[cfg(not(target_arch = "wasm32"))]
+fn run_command(cli: &portfolio::Cli) -> Result<(), Box<dyn std::error::Error>> {
+    use portfolio::Command;
+
+    match &cli.command {
+        None => check_demo(cli.demo.as_deref())?,
+        Some(Command::ListDemos) => {
+            for (name, _) in portfolio::DEMOS {
+                println!("{name}");
+            }
+        }
+        Some(Command::Render(args)) => {
+            check_demo(args.demo.as_deref())?;
+            render(cli, args)?;
+        }
+        Some(Command::ValidateShaders) => {
+            let results = portfolio::validate_shaders();
+            let mut failures = 0;
+            for (name, result) in &results {
+                match result {
+                    Ok(()) => println!("ok    {name}"),
+                    Err(error) => {
+                        failures += 1;
+                        println!("error {name}\n{error}");
+                    }
+                }
+            }
+            if failures > 0 {
+                return Err(format!("{failures} of {} shaders are invalid", results.len()).into());
+            }
+        }
     }
+    Ok(())
+}
 
-    #[cfg(target_arch = "wasm32")]
-    {
-        let web_options = eframe::WebOptions::default();
-
-        wasm_bindgen_futures::spawn_local(async {
-            eframe::WebRunner::new()
-                .start(
-                    "canvas", // hardcode it
-                    web_options,
-                    Box::new(|cc| Box::new(App::new(runtime, cc))),
-                )
-                .await
-                .expect("failed to start eframe");
-        });
+// portfolio render project.ron --demo Mandelbrot --size 1920x1080 --fps 60 --pipe ffmpeg -f rawvideo ...
+#[cfg(not(target_arch = "wasm32"))]
+fn render(
+    cli: &portfolio::Cli,
+    args: &portfolio::RenderArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use portfolio::{CommandSink, FrameSink, PngSequence};
+
+    let settings = args.settings();
+    let path = args.project.as_path();
+    let state = ProjectFormat::from_path(path)?.read(&std::fs::read_to_string(path)?)?;
+    // アダプターが見つからなければエンコーダーを起動する前に止める
+    let power_preference = cli.power_preference.map(Into::into).unwrap_or_default();
+    let renderer = demolib::HeadlessRenderer::with_options(cli.backends(), power_preference)?;
+
+    let mut png_sequence = PngSequence::new(&args.output)?;
+    let mut command_sink = match args.pipe.split_first() {
+        Some((program, pipe_args)) => Some(CommandSink::spawn(program, pipe_args, &settings)?),
+        None => None,
+    };
+    let mut sinks: Vec<&mut dyn FrameSink> = vec![&mut png_sequence];
+    if let Some(command_sink) = &mut command_sink {
+        sinks.push(command_sink);
     }
+
+    let frame_count = portfolio::render(&renderer, state, &settings, &mut sinks)?;
+    println!("Rendered {frame_count} frames to {}", args.output.display());
+    Ok(())
+}
+
+// コマンドラインで指定した起動時の状態
+#[derive(Default)]
+struct Startup {
+    demo: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    project: Option<std::path::PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run(runtime: Arc<tokio::runtime::Runtime>, cli: portfolio::Cli) {
+    let options = cli.native_options();
+    let startup = Startup {
+        demo: cli.demo,
+        project: cli.project,
+    };
+
+    eframe::run_native(
+        "My Window",
+        options,
+        Box::new(|cc| Box::new(App::new(runtime, cc, startup))),
+    )
+    .unwrap();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn run(runtime: Arc<tokio::runtime::Runtime>) {
+    let web_options = eframe::WebOptions::default();
+
+    wasm_bindgen_futures::spawn_local(async {
+        eframe::WebRunner::new()
+            .start(
+                "canvas", // hardcode it
+                web_options,
+                Box::new(|cc| Box::new(App::new(runtime, cc, Startup::default()))),
+            )
+            .await
+            .expect("failed to start eframe");
+    });
 }
 
 struct App {
@@ -54,10 +173,20 @@ struct App {
     runtime: Arc<tokio::runtime::Runtime>,
     workspace: Arc<Mutex<Workspace>>,
     property_panel: PropertyPanel,
+    timeline_panel: TimelinePanel,
+    // スクリーンショットやプロジェクトファイルの保存結果
+    status: Option<String>,
+    // 保存と読み込みに使うプロジェクトファイルのパス
+    #[cfg(not(target_arch = "wasm32"))]
+    project_path: String,
 }
 
 impl App {
-    pub fn new(runtime: Arc<tokio::runtime::Runtime>, context: &CreationContext) -> Self {
+    fn new(
+        runtime: Arc<tokio::runtime::Runtime>,
+        context: &CreationContext,
+        startup: Startup,
+    ) -> Self {
         let workspace = Arc::new(Mutex::new(Workspace::new()));
         if let Some(render_state) = &context.wgpu_render_state {
             let target_format = render_state.target_format;
@@ -71,35 +200,276 @@ impl App {
                 .write()
                 .callback_resources
                 .insert(demo_manager);
+
+            // デモは DemoManager で登録されるので、そのあとで前回の状態を復元する
+            let status = context.storage.and_then(|storage| {
+                let mut binding = workspace.lock();
+                let workspace = binding.as_mut().unwrap();
+                let state = match storage.get_string(WORKSPACE_KEY) {
+                    Some(state) => ProjectFormat::Ron.read(&state),
+                    None => Ok(WorkspaceState::from_legacy_storage(
+                        &workspace.get_demo_names(),
+                        |key| storage.get_string(key),
+                    )),
+                };
+                state
+                    .and_then(|state| workspace.load(state))
+                    .err()
+                    .map(|error| format!("Failed to restore the workspace: {error}"))
+            });
+
+            // コマンドラインの指定は前回の状態より優先する
+            #[cfg(not(target_arch = "wasm32"))]
+            let (status, project_path) = match &startup.project {
+                Some(path) => {
+                    let result = workspace.lock().unwrap().load_project(path);
+                    let status = match result {
+                        Ok(()) => format!("Loaded {}", path.display()),
+                        Err(error) => format!("Failed to load project: {error}"),
+                    };
+                    (Some(status), path.display().to_string())
+                }
+                None => (status, DEFAULT_PROJECT_PATH.to_string()),
+            };
+            if let Some(name) = &startup.demo {
+                let mut workspace = workspace.lock().unwrap();
+                if let Some(index) = workspace.find_demo(name) {
+                    workspace.set_current_demo_index(index);
+                }
+            }
+
             Self {
                 workspace: workspace.clone(),
                 runtime,
                 property_panel: PropertyPanel::new(workspace.clone()),
+                timeline_panel: TimelinePanel::new(workspace.clone()),
+                status,
+                #[cfg(not(target_arch = "wasm32"))]
+                project_path,
             }
         } else {
             Self {
                 runtime,
                 workspace: workspace.clone(),
                 property_panel: PropertyPanel::new(workspace.clone()),
+                timeline_panel: TimelinePanel::new(workspace.clone()),
+                status: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                project_path: DEFAULT_PROJECT_PATH.to_string(),
+            }
+        }
+    }
+
+    // Ctrl+Z で元に戻す、Ctrl+Shift+Z でやり直す (macOS は Cmd)
+    // テキストの入力中はテキストの編集に任せる
+    fn handle_history_shortcuts(&mut self, ctx: &eframe::egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = ctx.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::COMMAND, Key::Z),
+                input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+            )
+        });
+        let mut binding = self.workspace.lock();
+        let workspace = binding.as_mut().unwrap();
+        let (action, result) = match (undo, redo) {
+            (true, _) => ("undo", workspace.undo()),
+            (_, true) => ("redo", workspace.redo()),
+            _ => return,
+        };
+        if let Err(error) = result {
+            self.status = Some(format!("Failed to {action}: {error}"));
+        }
+    }
+}
+
+// 編集履歴の一覧
+// 項目をクリックすると、その編集のあとまで戻すかやり直す
+fn draw_history(
+    ui: &mut eframe::egui::Ui,
+    workspace: &mut Workspace,
+) -> Result<(), demolib::StateError> {
+    let history = workspace.history();
+    let (can_undo, can_redo) = (history.can_undo(), history.can_redo());
+    let mut target = None;
+    ui.horizontal(|ui| {
+        if ui.add_enabled(can_undo, Button::new("Undo")).clicked() {
+            target = Some(history.position() - 1);
+        }
+        if ui.add_enabled(can_redo, Button::new("Redo")).clicked() {
+            target = Some(history.position() + 1);
+        }
+    });
+    ui.label(format!(
+        "{} / {} KiB",
+        history.size().div_ceil(1024),
+        history.budget() / 1024
+    ));
+    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+        let position = history.position();
+        if ui
+            .selectable_label(position == 0, "Initial state")
+            .clicked()
+        {
+            target = Some(0);
+        }
+        for (index, label) in history.labels().enumerate() {
+            // 戻した編集は薄く表示する
+            let text = if index < position {
+                RichText::new(label)
+            } else {
+                RichText::new(label).weak()
+            };
+            if ui.selectable_label(index + 1 == position, text).clicked() {
+                target = Some(index + 1);
             }
         }
+    });
+    match target {
+        Some(position) => workspace.go_to_history(position),
+        None => Ok(()),
     }
 }
 
+// eframe のストレージでワークスペースの状態を保存するキー
+const WORKSPACE_KEY: &str = "workspace";
+
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_PROJECT_PATH: &str = "project.ron";
+
+// 直近のフレームで描画したカラーバッファーを PNG でカレントディレクトリに保存する
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot(frame: &eframe::Frame, demo_name: &str) -> Result<String, String> {
+    let render_state = frame
+        .wgpu_render_state()
+        .ok_or("wgpu is not available".to_string())?;
+    let renderer = render_state.renderer.read();
+    let demo_manager: &DemoManager = renderer
+        .callback_resources
+        .get()
+        .ok_or("demo manager is not available".to_string())?;
+    let image = demo_manager
+        .screenshot(&render_state.device, &render_state.queue)
+        .map_err(|error| error.to_string())?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let file_name = format!("screenshot-{}-{timestamp}.png", demo_name.replace(' ', "_"));
+    image.save(&file_name).map_err(|error| error.to_string())?;
+    Ok(file_name)
+}
+
+// シェーダーのコンパイルに失敗したら、キャンバスの上にエラーを重ねて表示する
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+fn show_shader_errors(ctx: &eframe::egui::Context, frame: &eframe::Frame) {
+    let Some(render_state) = frame.wgpu_render_state() else {
+        return;
+    };
+    let errors = render_state
+        .renderer
+        .read()
+        .callback_resources
+        .get::<DemoManager>()
+        .map(|demo_manager| demo_manager.shader_errors())
+        .unwrap_or_default();
+    if errors.is_empty() {
+        return;
+    }
+
+    eframe::egui::Area::new("Shader errors")
+        .anchor(eframe::egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .show(ctx, |ui| {
+            eframe::egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "Shader error (the last good shader is still in use)",
+                );
+                for (source, message) in errors {
+                    ui.separator();
+                    ui.strong(source);
+                    ui.label(eframe::egui::RichText::new(message).monospace());
+                }
+            });
+        });
+}
+
 impl eframe::App for App {
-    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let binding = self.workspace.lock();
+        let workspace = binding.as_ref().unwrap();
+        if let Ok(state) = ProjectFormat::Ron.write(&workspace.save()) {
+            storage.set_string(WORKSPACE_KEY, state);
+        }
+    }
+
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
         ctx.request_repaint();
+        #[cfg(target_arch = "wasm32")]
+        let _ = frame;
+        self.handle_history_shortcuts(ctx);
+        eframe::egui::TopBottomPanel::bottom("Timeline")
+            .resizable(true)
+            .show(ctx, |ui| {
+                self.timeline_panel.draw(ui);
+            });
         eframe::egui::SidePanel::left("Demo List")
             .resizable(false)
             .default_width(150.0)
             .show(ctx, |ui| {
                 let mut binding = self.workspace.lock();
                 let workspace = binding.as_mut().unwrap();
-                let mut current_demo_type = workspace.get_current_demo_type();
-                for (demo_type, lanel) in Workspace::get_demo_types() {
-                    ui.radio_value(&mut current_demo_type, *demo_type, *lanel);
+                let mut current_demo_index = workspace.get_current_demo_index();
+                for (index, name) in workspace.get_demo_names().into_iter().enumerate() {
+                    ui.radio_value(&mut current_demo_index, index, name);
                 }
-                workspace.set_demo_type(current_demo_type);
+                workspace.set_current_demo_index(current_demo_index);
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.separator();
+                    let names = workspace.get_demo_names();
+                    if let Some(name) = names.get(current_demo_index) {
+                        if ui.button("Save screenshot").clicked() {
+                            self.status = Some(match save_screenshot(frame, name) {
+                                Ok(file_name) => format!("Saved {file_name}"),
+                                Err(error) => format!("Failed to save screenshot: {error}"),
+                            });
+                        }
+                    }
+
+                    ui.separator();
+                    ui.label("Project (.ron / .json)");
+                    ui.text_edit_singleline(&mut self.project_path);
+                    ui.horizontal(|ui| {
+                        let path = std::path::Path::new(&self.project_path);
+                        if ui.button("Save").clicked() {
+                            self.status = Some(match workspace.save_project(path) {
+                                Ok(()) => format!("Saved {}", path.display()),
+                                Err(error) => format!("Failed to save project: {error}"),
+                            });
+                        }
+                        if ui.button("Load").clicked() {
+                            self.status = Some(match workspace.load_project(path) {
+                                Ok(()) => format!("Loaded {}", path.display()),
+                                Err(error) => format!("Failed to load project: {error}"),
+                            });
+                        }
+                    });
+                }
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+
+                ui.separator();
+                ui.collapsing("History", |ui| {
+                    if let Err(error) = draw_history(ui, workspace) {
+                        self.status = Some(format!("History: {error}"));
+                    }
+                });
             });
         eframe::egui::SidePanel::right("Property")
             .resizable(true)
@@ -111,14 +481,34 @@ impl eframe::App for App {
 
         eframe::egui::CentralPanel::default().show(ctx, |ui| {
             eframe::egui::Frame::canvas(ui.style()).show(ui, |ui| {
-                let (rect, _response) = ui.allocate_exact_size(
-                    eframe::egui::vec2(700.0, 700.0),
-                    eframe::egui::Sense::drag(),
+                // パネルいっぱいに広げて、描画先は物理ピクセルで確保する
+                let (rect, response) = ui.allocate_exact_size(
+                    ui.available_size(),
+                    eframe::egui::Sense::click_and_drag(),
                 );
+                let pixels_per_point = ctx.pixels_per_point();
+                let size = [
+                    (rect.width() * pixels_per_point).round() as u32,
+                    (rect.height() * pixels_per_point).round() as u32,
+                ];
+                if response.clicked() {
+                    response.request_focus();
+                }
 
-                let callback = Callback::new_paint_callback(rect, RenderBridge::new());
+                let mut binding = self.workspace.lock();
+                let workspace = binding.as_mut().unwrap();
+                let (time, held) = ctx.input(|input| (input.time, input.pointer.any_down()));
+                workspace.edit_current_demo(time, held, |params| params.interact(&response));
+                if let Some(params) = workspace.get_current_demo_params_mut() {
+                    params.advance(ctx.input(|input| input.stable_dt));
+                }
+
+                let callback = Callback::new_paint_callback(rect, RenderBridge::new(size));
                 ui.painter().add(callback);
             });
         });
+
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        show_shader_errors(ctx, frame);
     }
 }
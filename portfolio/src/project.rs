@@ -0,0 +1,162 @@
+// ワークスペースの状態の保存形式
+// eframe のストレージにも、名前を付けて保存するプロジェクトファイル (.ron / .json) にも同じものを書く
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use demolib::{DemoState, StateError};
+use serde::{Deserialize, Serialize};
+
+use crate::Timeline;
+
+// WorkspaceState の構造を変えたら上げて、WorkspaceState::migrate に変換を足す
+// 0 はストレージにデモごとのキーで RON の文字列を保存していた (WorkspaceState::from_legacy_storage)
+pub const WORKSPACE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceState {
+    pub version: u32,
+    // 選ばれていたデモの名前
+    pub current_demo: Option<String>,
+    // デモの名前ごとの状態
+    pub demos: BTreeMap<String, DemoState>,
+    // キーフレームアニメーション (これを足す前のファイルにはない)
+    #[serde(default)]
+    pub timeline: Timeline,
+}
+
+impl WorkspaceState {
+    // バージョン 0 のストレージを読む
+    // デモの状態もバージョン 0 (RON の文字列) として、変換はデモに任せる
+    pub fn from_legacy_storage(
+        demo_names: &[&str],
+        get_string: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let demos = demo_names
+            .iter()
+            .filter_map(|name| {
+                let state = get_string(&format!("demo/{name}"))?;
+                Some((
+                    name.to_string(),
+                    DemoState {
+                        version: 0,
+                        params: serde_json::Value::String(state),
+                    },
+                ))
+            })
+            .collect();
+        Self {
+            version: WORKSPACE_VERSION,
+            current_demo: None,
+            demos,
+            timeline: Timeline::default(),
+        }
+    }
+
+    pub fn migrate(self) -> Result<Self, ProjectError> {
+        if self.version > WORKSPACE_VERSION {
+            return Err(ProjectError::UnsupportedVersion {
+                version: self.version,
+                latest: WORKSPACE_VERSION,
+            });
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectFormat {
+    Ron,
+    Json,
+}
+
+impl ProjectFormat {
+    // 拡張子で決める
+    pub fn from_path(path: &Path) -> Result<Self, ProjectError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ron") => Ok(ProjectFormat::Ron),
+            Some("json") => Ok(ProjectFormat::Json),
+            _ => Err(ProjectError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn write(self, state: &WorkspaceState) -> Result<String, ProjectError> {
+        match self {
+            ProjectFormat::Ron => {
+                ron::ser::to_string_pretty(state, ron::ser::PrettyConfig::default())
+                    .map_err(ProjectError::RonWrite)
+            }
+            ProjectFormat::Json => Ok(serde_json::to_string_pretty(state)?),
+        }
+    }
+
+    pub fn read(self, text: &str) -> Result<WorkspaceState, ProjectError> {
+        let state: WorkspaceState = match self {
+            ProjectFormat::Ron => ron::from_str(text)?,
+            ProjectFormat::Json => serde_json::from_str(text)?,
+        };
+        state.migrate()
+    }
+}
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(std::io::Error),
+    UnsupportedFormat(PathBuf),
+    Ron(ron::error::SpannedError),
+    RonWrite(ron::Error),
+    Json(serde_json::Error),
+    // より新しいアプリで保存された
+    UnsupportedVersion { version: u32, latest: u32 },
+    // 復元できなかったデモ (ほかのデモは復元してある)
+    Demos(Vec<(String, StateError)>),
+}
+
+impl std::fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectError::Io(error) => write!(f, "{error}"),
+            ProjectError::UnsupportedFormat(path) => {
+                write!(f, "{}: project files must be .ron or .json", path.display())
+            }
+            ProjectError::Ron(error) => write!(f, "{error}"),
+            ProjectError::RonWrite(error) => write!(f, "{error}"),
+            ProjectError::Json(error) => write!(f, "{error}"),
+            ProjectError::UnsupportedVersion { version, latest } => write!(
+                f,
+                "project version {version} is newer than the supported version {latest}"
+            ),
+            ProjectError::Demos(errors) => {
+                for (index, (name, error)) in errors.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{name}: {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(error: std::io::Error) -> Self {
+        ProjectError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ProjectError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ProjectError::Ron(error)
+    }
+}
+
+impl From<serde_json::Error> for ProjectError {
+    fn from(error: serde_json::Error) -> Self {
+        ProjectError::Json(error)
+    }
+}
@@ -0,0 +1,234 @@
+// デモのオフライン描画 (連番画像や動画)
+// ヘッドレスで固定のフレームレートで時間を進め、タイムラインの値を反映したフレームをシンクに渡す
+// ウィンドウの入力や経過時間に依存しないので、同じ入力からは同じフレームが得られる
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+use demolib::{Demo, HeadlessError, HeadlessRenderer, Image, StateError, HEADLESS_COLOR_FORMAT};
+
+use crate::{ProjectError, Workspace, WorkspaceState, DEMOS};
+
+pub struct RenderSettings {
+    // 描画するデモの名前。None ならプロジェクトで選ばれていたデモ
+    pub demo: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    // タイムラインの開始時刻 (秒)
+    pub start: f64,
+    // 描画する長さ (秒)。None ならタイムラインの最後まで
+    pub duration: Option<f64>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            demo: None,
+            width: 1280,
+            height: 720,
+            fps: 30.0,
+            start: 0.0,
+            duration: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn frame_count(&self, timeline_duration: f64) -> usize {
+        let duration = self
+            .duration
+            .unwrap_or(timeline_duration - self.start)
+            .max(0.0);
+        // 最後の時刻も含める
+        (duration * self.fps).floor() as usize + 1
+    }
+}
+
+// 描画したフレームの書き出し先
+pub trait FrameSink {
+    // index は 0 から始まるフレームの番号
+    fn write_frame(&mut self, index: usize, image: &Image) -> Result<(), RenderError>;
+
+    // 最後のフレームのあとに呼ばれる
+    fn finish(&mut self) -> Result<(), RenderError> {
+        Ok(())
+    }
+}
+
+// directory/frame_00000.png のような連番の PNG
+pub struct PngSequence {
+    directory: PathBuf,
+}
+
+impl PngSequence {
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self, RenderError> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    pub fn path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("frame_{index:05}.png"))
+    }
+}
+
+impl FrameSink for PngSequence {
+    fn write_frame(&mut self, index: usize, image: &Image) -> Result<(), RenderError> {
+        image.save(self.path(index))?;
+        Ok(())
+    }
+}
+
+// フレームの RGBA8 の画素をそのまま外部のエンコーダーの標準入力に流す
+//   ffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - out.mp4
+// のように、引数の {width} {height} {fps} は描画の設定で置き換える
+pub struct CommandSink {
+    child: Child,
+}
+
+impl CommandSink {
+    pub fn spawn(
+        program: &str,
+        args: &[String],
+        settings: &RenderSettings,
+    ) -> Result<Self, RenderError> {
+        let args = args.iter().map(|arg| {
+            arg.replace("{width}", &settings.width.to_string())
+                .replace("{height}", &settings.height.to_string())
+                .replace("{fps}", &settings.fps.to_string())
+        });
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        Ok(Self { child })
+    }
+}
+
+impl FrameSink for CommandSink {
+    fn write_frame(&mut self, _index: usize, image: &Image) -> Result<(), RenderError> {
+        let stdin = self.child.stdin.as_mut().ok_or_else(|| {
+            RenderError::Encoder("the encoder closed its standard input".to_string())
+        })?;
+        stdin.write_all(&image.pixels)?;
+        Ok(())
+    }
+
+    // 標準入力を閉じてエンコーダーの終了を待つ
+    fn finish(&mut self) -> Result<(), RenderError> {
+        drop(self.child.stdin.take());
+        let status = self.child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(RenderError::Encoder(format!(
+                "the encoder exited with {status}"
+            )))
+        }
+    }
+}
+
+// オフライン描画で使えるデモ (DemoManager に登録しているものと同じ)
+pub fn create_demos(device: &wgpu::Device) -> Vec<Box<dyn Demo>> {
+    DEMOS
+        .iter()
+        .map(|(_, create)| create(device, HEADLESS_COLOR_FORMAT))
+        .collect()
+}
+
+// state (プロジェクトファイルの中身) のデモを描画して、フレームを順に sinks に渡す
+// フレーム i はタイムラインの start + i / fps 秒の値で描画し、時間で動くデモはフレームごとに 1 / fps 秒進める
+// 描画したフレームの数を返す
+pub fn render(
+    renderer: &HeadlessRenderer,
+    state: WorkspaceState,
+    settings: &RenderSettings,
+    sinks: &mut [&mut dyn FrameSink],
+) -> Result<usize, RenderError> {
+    let mut demos = create_demos(renderer.device());
+    let mut workspace = Workspace::new();
+    for demo in &demos {
+        workspace.add_demo(demo.name(), demo.create_params());
+    }
+    workspace.load(state)?;
+    if let Some(name) = &settings.demo {
+        let index = workspace
+            .find_demo(name)
+            .ok_or_else(|| RenderError::UnknownDemo(name.clone()))?;
+        workspace.set_current_demo_index(index);
+    }
+    let index = workspace.get_current_demo_index();
+    let demo = demos[index].as_mut();
+
+    let frame_count = settings.frame_count(workspace.timeline().duration);
+    let dt = 1.0 / settings.fps;
+    for frame in 0..frame_count {
+        workspace.timeline_mut().time = settings.start + frame as f64 * dt;
+        workspace.apply_timeline()?;
+        let params = workspace.get_demo_params(index).unwrap();
+        let image = renderer.render_demo(demo, params, settings.width, settings.height)?;
+        for sink in sinks.iter_mut() {
+            sink.write_frame(frame, &image)?;
+        }
+        if let Some(params) = workspace.get_current_demo_params_mut() {
+            params.advance(dt as f32);
+        }
+    }
+    for sink in sinks.iter_mut() {
+        sink.finish()?;
+    }
+    Ok(frame_count)
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    Headless(HeadlessError),
+    Project(ProjectError),
+    State(StateError),
+    UnknownDemo(String),
+    Io(std::io::Error),
+    Encoder(String),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Headless(error) => write!(f, "{error}"),
+            RenderError::Project(error) => write!(f, "{error}"),
+            RenderError::State(error) => write!(f, "{error}"),
+            RenderError::UnknownDemo(name) => write!(f, "unknown demo: {name}"),
+            RenderError::Io(error) => write!(f, "{error}"),
+            RenderError::Encoder(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<HeadlessError> for RenderError {
+    fn from(error: HeadlessError) -> Self {
+        RenderError::Headless(error)
+    }
+}
+
+impl From<ProjectError> for RenderError {
+    fn from(error: ProjectError) -> Self {
+        RenderError::Project(error)
+    }
+}
+
+impl From<StateError> for RenderError {
+    fn from(error: StateError) -> Self {
+        RenderError::State(error)
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(error: std::io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
@@ -0,0 +1,303 @@
+// デモのパラメーターのキーフレームアニメーション
+// トラックはデモの保存される状態 (DemoState::params) の中の数値か数値の配列を JSON Pointer で指す
+//   "/color" (三角形の色), "/center" と "/scale" (マンデルブロ集合の範囲), "/camera/yaw" (カメラの向き) など
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    // つぎのキーまで値を変えない
+    Step,
+    #[default]
+    Linear,
+    // 3 次ベジェ曲線。制御点は前後のキーを結ぶ向きから自動で決め、最初と最後のキーでは止まる
+    Bezier,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 3] = [
+        Interpolation::Step,
+        Interpolation::Linear,
+        Interpolation::Bezier,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Step => "Step",
+            Interpolation::Linear => "Linear",
+            Interpolation::Bezier => "Bezier",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    // 秒
+    pub time: f64,
+    // スカラーは長さ 1
+    pub value: Vec<f64>,
+    // このキーからつぎのキーまでの補間
+    pub interpolation: Interpolation,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    // デモの名前
+    pub demo: String,
+    // DemoState::params の中の JSON Pointer
+    pub path: String,
+    // 時刻の順
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(demo: &str, path: &str) -> Self {
+        Self {
+            demo: demo.to_string(),
+            path: path.to_string(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    // 同じ時刻のキーがあれば置き換える
+    pub fn set_key(&mut self, time: f64, value: Vec<f64>, interpolation: Interpolation) {
+        let keyframe = Keyframe {
+            time,
+            value,
+            interpolation,
+        };
+        match self
+            .keyframes
+            .binary_search_by(|keyframe| keyframe.time.total_cmp(&time))
+        {
+            Ok(index) => self.keyframes[index] = keyframe,
+            Err(index) => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    // time のキーを消す。なければ false
+    pub fn remove_key(&mut self, time: f64) -> bool {
+        let Some(index) = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time == time)
+        else {
+            return false;
+        };
+        self.keyframes.remove(index);
+        true
+    }
+
+    // キーがなければ None
+    // 最初のキーより前と最後のキーよりあとは、それぞれのキーの値のまま
+    pub fn evaluate(&self, time: f64) -> Option<Vec<f64>> {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return keyframes.first().map(|keyframe| keyframe.value.clone());
+        }
+        let current = &keyframes[next - 1];
+        let Some(end) = keyframes.get(next) else {
+            return Some(current.value.clone());
+        };
+
+        let duration = end.time - current.time;
+        let t = (time - current.time) / duration;
+        let value = match current.interpolation {
+            Interpolation::Step => current.value.clone(),
+            Interpolation::Linear => zip_with(&current.value, &end.value, |start, end| {
+                start + (end - start) * t
+            }),
+            Interpolation::Bezier => {
+                // キーでの傾き (値 / 秒) から制御点を決める
+                let start_slope = self.slope(next - 1);
+                let end_slope = self.slope(next);
+                (0..current.value.len().min(end.value.len()))
+                    .map(|index| {
+                        let p0 = current.value[index];
+                        let p3 = end.value[index];
+                        let p1 = p0 + start_slope[index] * duration / 3.0;
+                        let p2 = p3 - end_slope[index] * duration / 3.0;
+                        let s = 1.0 - t;
+                        s * s * s * p0
+                            + 3.0 * s * s * t * p1
+                            + 3.0 * s * t * t * p2
+                            + t * t * t * p3
+                    })
+                    .collect()
+            }
+        };
+        Some(value)
+    }
+
+    // index 番目のキーでの傾き (Catmull-Rom)。最初と最後のキーでは 0
+    fn slope(&self, index: usize) -> Vec<f64> {
+        let keyframe = &self.keyframes[index];
+        let (Some(previous), Some(next)) = (
+            index
+                .checked_sub(1)
+                .and_then(|index| self.keyframes.get(index)),
+            self.keyframes.get(index + 1),
+        ) else {
+            return vec![0.0; keyframe.value.len()];
+        };
+        let duration = next.time - previous.time;
+        zip_with(&previous.value, &next.value, |previous, next| {
+            (next - previous) / duration
+        })
+    }
+}
+
+fn zip_with(a: &[f64], b: &[f64], f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| f(*a, *b)).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub tracks: Vec<Track>,
+    // 再生位置 (秒)
+    pub time: f64,
+    // 長さ (秒)
+    pub duration: f64,
+    pub playing: bool,
+    // 最後まで再生したら最初に戻る
+    pub looping: bool,
+}
+
+impl Timeline {
+    // 再生中なら dt 秒だけ進める
+    pub fn advance(&mut self, dt: f64) {
+        if !self.playing {
+            return;
+        }
+        self.time += dt;
+        if self.time < self.duration {
+            return;
+        }
+        if self.looping && self.duration > 0.0 {
+            self.time %= self.duration;
+        } else {
+            self.time = self.duration;
+            self.playing = false;
+        }
+    }
+
+    pub fn track(&self, demo: &str, path: &str) -> Option<&Track> {
+        self.tracks
+            .iter()
+            .find(|track| track.demo == demo && track.path == path)
+    }
+
+    // なければ作る
+    pub fn track_mut(&mut self, demo: &str, path: &str) -> &mut Track {
+        let index = match self
+            .tracks
+            .iter()
+            .position(|track| track.demo == demo && track.path == path)
+        {
+            Some(index) => index,
+            None => {
+                self.tracks.push(Track::new(demo, path));
+                self.tracks.len() - 1
+            }
+        };
+        &mut self.tracks[index]
+    }
+
+    pub fn has_tracks(&self, demo: &str) -> bool {
+        self.tracks.iter().any(|track| track.demo == demo)
+    }
+
+    // demo のトラックを現在の時刻で評価して params に書き込む
+    pub fn apply(&self, demo: &str, params: &mut Value) {
+        for track in self.tracks.iter().filter(|track| track.demo == demo) {
+            if let (Some(value), Some(target)) =
+                (track.evaluate(self.time), params.pointer_mut(&track.path))
+            {
+                write_value(target, &value);
+            }
+        }
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            tracks: Vec::new(),
+            time: 0.0,
+            duration: 10.0,
+            playing: false,
+            looping: true,
+        }
+    }
+}
+
+// アニメーションできる値 (数値か数値の配列) なら、その値
+pub fn read_value(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Number(number) => Some(vec![number.as_f64()?]),
+        Value::Array(elements) if !elements.is_empty() => {
+            elements.iter().map(Value::as_f64).collect()
+        }
+        _ => None,
+    }
+}
+
+// 整数のところには丸めて書き込む (max_iterations など)
+fn write_value(target: &mut Value, value: &[f64]) {
+    match target {
+        Value::Number(number) => {
+            if let Some(value) = value.first() {
+                *number = to_number(number, *value);
+            }
+        }
+        Value::Array(elements) => {
+            for (element, value) in elements.iter_mut().zip(value) {
+                if let Value::Number(number) = element {
+                    *number = to_number(number, *value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn to_number(previous: &serde_json::Number, value: f64) -> serde_json::Number {
+    if previous.is_u64() {
+        (value.round().max(0.0) as u64).into()
+    } else if previous.is_i64() {
+        (value.round() as i64).into()
+    } else {
+        serde_json::Number::from_f64(value).unwrap_or_else(|| previous.clone())
+    }
+}
+
+// params の中でアニメーションできる値の JSON Pointer
+pub fn animatable_paths(params: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_paths(params, String::new(), &mut paths);
+    paths
+}
+
+fn collect_paths(value: &Value, path: String, paths: &mut Vec<String>) {
+    if read_value(value).is_some() {
+        paths.push(path);
+        return;
+    }
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                // JSON Pointer では ~ と / をエスケープする
+                let key = key.replace('~', "~0").replace('/', "~1");
+                collect_paths(value, format!("{path}/{key}"), paths);
+            }
+        }
+        Value::Array(elements) => {
+            for (index, value) in elements.iter().enumerate() {
+                collect_paths(value, format!("{path}/{index}"), paths);
+            }
+        }
+        _ => {}
+    }
+}
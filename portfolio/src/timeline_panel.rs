@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::egui::{self, Color32, ComboBox, DragValue, Sense, Slider, Ui};
+
+use crate::{Interpolation, Timeline, Workspace};
+
+// トラックの行の高さ
+const TRACK_HEIGHT: f32 = 16.0;
+
+// ウィンドウの下に出すタイムライン
+// 再生と再生位置の操作、現在のデモの値へのキーの打ち込み、トラックごとのキーの一覧
+pub struct TimelinePanel {
+    workspace: Arc<Mutex<Workspace>>,
+    // キーを打つ値の JSON Pointer
+    path: Option<String>,
+    // 新しく打つキーの補間
+    interpolation: Interpolation,
+}
+
+impl TimelinePanel {
+    pub fn new(workspace: Arc<Mutex<Workspace>>) -> Self {
+        Self {
+            workspace,
+            path: None,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut Ui) {
+        let mut workspace = self.workspace.lock().unwrap();
+        let dt = ui.input(|input| input.stable_dt);
+        workspace.timeline_mut().advance(dt as f64);
+
+        draw_transport(ui, workspace.timeline_mut());
+        if let Some(error) = workspace.timeline_error() {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Timeline: {error}"));
+        }
+
+        let paths = workspace.animatable_paths();
+        if self.path.as_ref().is_some_and(|path| !paths.contains(path)) {
+            self.path = None;
+        }
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source("Timeline value")
+                .selected_text(self.path.as_deref().unwrap_or("Select a value"))
+                .show_ui(ui, |ui| {
+                    for path in paths {
+                        let text = path.clone();
+                        ui.selectable_value(&mut self.path, Some(path), text);
+                    }
+                });
+            ComboBox::from_id_source("Timeline interpolation")
+                .selected_text(self.interpolation.name())
+                .show_ui(ui, |ui| {
+                    for interpolation in Interpolation::ALL {
+                        ui.selectable_value(
+                            &mut self.interpolation,
+                            interpolation,
+                            interpolation.name(),
+                        );
+                    }
+                });
+            if let Some(path) = &self.path {
+                if ui.button("Add key").clicked() {
+                    workspace.add_keyframe(path, self.interpolation);
+                }
+            }
+        });
+
+        draw_tracks(ui, workspace.timeline_mut());
+    }
+}
+
+// 再生、停止、再生位置、長さ
+fn draw_transport(ui: &mut Ui, timeline: &mut Timeline) {
+    ui.horizontal(|ui| {
+        let label = if timeline.playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            // 最後まで再生していたら最初から
+            if !timeline.playing && timeline.time >= timeline.duration {
+                timeline.time = 0.0;
+            }
+            timeline.playing = !timeline.playing;
+        }
+        if ui.button("Stop").clicked() {
+            timeline.playing = false;
+            timeline.time = 0.0;
+        }
+        ui.checkbox(&mut timeline.looping, "Loop");
+        ui.label("Duration");
+        ui.add(
+            DragValue::new(&mut timeline.duration)
+                .speed(0.1)
+                .clamp_range(0.1..=3600.0)
+                .suffix(" s"),
+        );
+        timeline.time = timeline.time.min(timeline.duration);
+        ui.spacing_mut().slider_width = (ui.available_width() - 80.0).max(50.0);
+        ui.add(
+            Slider::new(&mut timeline.time, 0.0..=timeline.duration)
+                .suffix(" s")
+                .max_decimals(2),
+        );
+    });
+}
+
+// トラックごとにキーの位置を並べる
+// 行をクリックかドラッグすると再生位置を動かし、キーを右クリックすると消す
+fn draw_tracks(ui: &mut Ui, timeline: &mut Timeline) {
+    let mut removed_track = None;
+    let mut removed_key = None;
+    let mut time = None;
+    for (track_index, track) in timeline.tracks.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("✕").on_hover_text("Remove track").clicked() {
+                removed_track = Some(track_index);
+            }
+            ui.add_sized(
+                [200.0, TRACK_HEIGHT],
+                egui::Label::new(format!("{} {}", track.demo, track.path)).truncate(true),
+            );
+
+            let (rect, response) = ui.allocate_exact_size(
+                egui::vec2(ui.available_width(), TRACK_HEIGHT),
+                Sense::click_and_drag(),
+            );
+            let to_x = |time: f64| rect.left() + (time / timeline.duration) as f32 * rect.width();
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+            let key_color = ui.visuals().text_color();
+            for keyframe in &track.keyframes {
+                let center = egui::pos2(to_x(keyframe.time), rect.center().y);
+                painter.circle_filled(center, 4.0, key_color);
+            }
+            painter.vline(
+                to_x(timeline.time),
+                rect.y_range(),
+                egui::Stroke::new(1.0, Color32::RED),
+            );
+
+            let Some(position) = response.interact_pointer_pos() else {
+                return;
+            };
+            if response.secondary_clicked() {
+                removed_key = track
+                    .keyframes
+                    .iter()
+                    .find(|keyframe| (to_x(keyframe.time) - position.x).abs() <= 4.0)
+                    .map(|keyframe| (track_index, keyframe.time));
+            } else if response.clicked() || response.dragged() {
+                let x = ((position.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+                time = Some(x as f64 * timeline.duration);
+            }
+        });
+    }
+
+    if let Some(time) = time {
+        timeline.time = time;
+    }
+    if let Some((track_index, time)) = removed_key {
+        timeline.tracks[track_index].remove_key(time);
+    }
+    if let Some(track_index) = removed_track {
+        timeline.tracks.remove(track_index);
+    }
+}
@@ -0,0 +1,55 @@
+// 埋め込んだ WGSL を naga で読み込んで検証する (GPU もウィンドウも使わない)
+// build.rs で変換したときにも検証しているが、wgpu が受け付ける形になっているかを配布したバイナリで確かめる
+
+// (シェーダーの名前, エラー)
+pub fn validate_shaders() -> Vec<(&'static str, Result<(), String>)> {
+    let portfolio_shaders = [
+        (
+            "draw_texture.vs",
+            include_str!(concat!(env!("OUT_DIR"), "/draw_texture.vs.wgsl")),
+            wgpu::ShaderStages::VERTEX,
+        ),
+        (
+            "draw_texture.fs",
+            include_str!(concat!(env!("OUT_DIR"), "/draw_texture.fs.wgsl")),
+            wgpu::ShaderStages::FRAGMENT,
+        ),
+    ];
+    demolib::SHADERS
+        .iter()
+        .map(|entry| (entry.name, entry.wgsl, entry.stage))
+        .chain(portfolio_shaders)
+        .map(|(name, wgsl, stage)| (name, validate_wgsl(name, wgsl, stage)))
+        .collect()
+}
+
+// エントリーポイント main が stage のシェーダーかも確かめる
+pub fn validate_wgsl(name: &str, wgsl: &str, stage: wgpu::ShaderStages) -> Result<(), String> {
+    let module = naga::front::wgsl::parse_str(wgsl)
+        .map_err(|error| error.emit_to_string_with_path(wgsl, name))?;
+    // ヘッドレス描画と同じく追加の機能を要求しないデバイスで動くこと
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|error| error.emit_to_string_with_path(wgsl, name))?;
+
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|entry_point| entry_point.name == "main")
+        .ok_or_else(|| format!("{name}: no entry point named main"))?;
+    let entry_stage = match entry_point.stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    };
+    if entry_stage != stage {
+        return Err(format!(
+            "{name}: main is a {:?} shader, expected {stage:?}",
+            entry_point.stage
+        ));
+    }
+    Ok(())
+}
@@ -1,19 +1,31 @@
-use demolib::TriangleParams;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 
-use crate::DemoType;
+use demolib::{DemoParams, DemoState, StateError};
+
+use crate::{
+    timeline, Edit, History, Interpolation, ProjectError, ProjectFormat, Timeline, WorkspaceState,
+    WORKSPACE_VERSION,
+};
 
 pub struct Workspace {
-    demo_type: DemoType,
-    triangle_params: TriangleParams,
+    current_demo_index: usize,
+    // 登録されたデモの名前とパラメーター
+    demos: Vec<(&'static str, Box<dyn DemoParams>)>,
+    history: History,
+    timeline: Timeline,
+    // 最後に apply_timeline を呼んだときのエラー
+    timeline_error: Option<StateError>,
 }
 
 impl Workspace {
     pub fn new() -> Self {
         Self {
-            demo_type: DemoType::Triangle,
-            triangle_params: TriangleParams {
-                color: [0.1, 0.2, 0.3],
-            },
+            current_demo_index: 0,
+            demos: Vec::new(),
+            history: History::default(),
+            timeline: Timeline::default(),
+            timeline_error: None,
         }
     }
 
@@ -21,29 +33,276 @@ impl Workspace {
 
     pub fn draw<'a>(&self, _render_pass: &mut wgpu::RenderPass<'a>) {}
 
-    pub fn get_demo_types() -> &'static [(DemoType, &'static str)] {
-        &[
-            (DemoType::Triangle, "Triangle"),
-            (DemoType::Mandelbrot, "Mandelbrot"),
-            (DemoType::Model3d, "Model3d"),
-            (DemoType::Tetris, "Tetris"),
-            (DemoType::Physics, "Physics"),
-        ]
+    pub fn add_demo(&mut self, name: &'static str, params: Box<dyn DemoParams>) {
+        self.demos.push((name, params));
+    }
+
+    pub fn get_demo_names(&self) -> Vec<&'static str> {
+        self.demos.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn find_demo(&self, name: &str) -> Option<usize> {
+        self.demos.iter().position(|(x, _)| *x == name)
+    }
+
+    pub fn get_current_demo_index(&self) -> usize {
+        self.current_demo_index
+    }
+
+    // 切り替えは履歴に残る
+    pub fn set_current_demo_index(&mut self, index: usize) {
+        if index == self.current_demo_index {
+            return;
+        }
+        let name = self.demos.get(index).map_or("", |(name, _)| *name);
+        self.history.push(
+            format!("Show {name}"),
+            Edit::SelectDemo {
+                from: self.current_demo_index,
+                to: index,
+            },
+        );
+        self.current_demo_index = index;
+    }
+
+    pub fn get_demo_params(&self, index: usize) -> Option<&dyn DemoParams> {
+        self.demos.get(index).map(|(_, params)| params.as_ref())
+    }
+
+    pub fn save(&self) -> WorkspaceState {
+        WorkspaceState {
+            version: WORKSPACE_VERSION,
+            current_demo: self
+                .demos
+                .get(self.current_demo_index)
+                .map(|(name, _)| name.to_string()),
+            demos: self
+                .demos
+                .iter()
+                .filter_map(|(name, params)| Some((name.to_string(), params.save_state()?)))
+                .collect(),
+            timeline: self.timeline.clone(),
+        }
+    }
+
+    // 登録されていないデモの状態は無視する
+    // 復元できなかったデモがあっても、ほかのデモは復元する
+    // 読み込む前の編集には戻れなくなる
+    pub fn load(&mut self, state: WorkspaceState) -> Result<(), ProjectError> {
+        let state = state.migrate()?;
+        self.history.clear();
+        self.timeline = state.timeline;
+        if let Some(index) = state
+            .current_demo
+            .and_then(|current| self.demos.iter().position(|(name, _)| *name == current))
+        {
+            self.current_demo_index = index;
+        }
+
+        let mut errors = Vec::new();
+        for (name, demo_state) in state.demos {
+            for (_, params) in self.demos.iter_mut().filter(|(x, _)| *x == name) {
+                if let Err(error) = params.load_state(demo_state.clone()) {
+                    errors.push((name.clone(), error));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ProjectError::Demos(errors))
+        }
+    }
+
+    // 拡張子 (.ron / .json) の形式でプロジェクトファイルに保存する
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_project(&self, path: &Path) -> Result<(), ProjectError> {
+        let text = ProjectFormat::from_path(path)?.write(&self.save())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_project(&mut self, path: &Path) -> Result<(), ProjectError> {
+        let format = ProjectFormat::from_path(path)?;
+        let state = format.read(&std::fs::read_to_string(path)?)?;
+        self.load(state)
+    }
+
+    // ここで変えたパラメーターは履歴に残らない
+    pub fn get_current_demo_params_mut(&mut self) -> Option<&mut dyn DemoParams> {
+        self.demos
+            .get_mut(self.current_demo_index)
+            .map(|(_, params)| params.as_mut())
     }
 
-    pub fn get_current_demo_type(&self) -> DemoType {
-        self.demo_type
+    // 現在のデモのパラメーターを編集して、保存される状態が変わったら履歴に積む
+    // time は入力の時刻 (秒)、held はポインターのボタンが押されているか
+    // ドラッグ中の同じ項目の編集はひとつのステップにまとめる
+    pub fn edit_current_demo(
+        &mut self,
+        time: f64,
+        held: bool,
+        edit: impl FnOnce(&mut dyn DemoParams),
+    ) {
+        self.history.expire(time, held);
+        let index = self.current_demo_index;
+        let Some((name, params)) = self.demos.get_mut(index) else {
+            return;
+        };
+        let before = params.save_state();
+        edit(params.as_mut());
+        let (Some(before), Some(after)) = (before, params.save_state()) else {
+            return;
+        };
+        if before != after {
+            let label = edit_label(name, &before, &after);
+            self.history.push_continuous(
+                label,
+                Edit::Params {
+                    demo: index,
+                    before,
+                    after,
+                },
+                time,
+                held,
+            );
+        }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    // 上限を超えたぶんは古いステップから捨てる
+    pub fn set_history_budget(&mut self, budget: usize) {
+        self.history.set_budget(budget);
     }
 
-    pub fn set_demo_type(&mut self, demo_type: DemoType) {
-        self.demo_type = demo_type;
+    // 戻すステップがなければ false
+    pub fn undo(&mut self) -> Result<bool, StateError> {
+        let Some(edit) = self.history.undo() else {
+            return Ok(false);
+        };
+        match edit {
+            Edit::Params { demo, before, .. } => {
+                if let Some((_, params)) = self.demos.get_mut(*demo) {
+                    params.load_state(before.clone())?;
+                }
+            }
+            Edit::SelectDemo { from, .. } => self.current_demo_index = *from,
+        }
+        Ok(true)
     }
 
-    pub fn get_triangle_params(&self) -> &TriangleParams {
-        &self.triangle_params
+    // やり直すステップがなければ false
+    pub fn redo(&mut self) -> Result<bool, StateError> {
+        let Some(edit) = self.history.redo() else {
+            return Ok(false);
+        };
+        match edit {
+            Edit::Params { demo, after, .. } => {
+                if let Some((_, params)) = self.demos.get_mut(*demo) {
+                    params.load_state(after.clone())?;
+                }
+            }
+            Edit::SelectDemo { to, .. } => self.current_demo_index = *to,
+        }
+        Ok(true)
+    }
+
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    pub fn timeline_mut(&mut self) -> &mut Timeline {
+        &mut self.timeline
+    }
+
+    // 現在のデモのトラックを評価してパラメーターに書き込む
+    // DemoManager がパラメーターを GPU に送る前に毎フレーム呼ぶ
+    pub fn apply_timeline(&mut self) -> Result<(), StateError> {
+        let Some((name, params)) = self.demos.get_mut(self.current_demo_index) else {
+            return Ok(());
+        };
+        if !self.timeline.has_tracks(name) {
+            return Ok(());
+        }
+        let Some(state) = params.save_state() else {
+            return Ok(());
+        };
+        let mut animated = state.clone();
+        self.timeline.apply(name, &mut animated.params);
+        // 値が変わらなければ読み込み直さない (物理シミュレーションは読み込むと作り直しになる)
+        if animated != state {
+            params.load_state(animated)?;
+        }
+        Ok(())
     }
 
-    pub fn get_triangle_params_mut(&mut self) -> &mut TriangleParams {
-        &mut self.triangle_params
+    pub fn timeline_error(&self) -> Option<&StateError> {
+        self.timeline_error.as_ref()
+    }
+
+    pub fn set_timeline_error(&mut self, error: Option<StateError>) {
+        self.timeline_error = error;
+    }
+
+    // 現在のデモでキーフレームを打てる値の JSON Pointer
+    pub fn animatable_paths(&self) -> Vec<String> {
+        self.get_demo_params(self.current_demo_index)
+            .and_then(|params| params.save_state())
+            .map(|state| timeline::animatable_paths(&state.params))
+            .unwrap_or_default()
+    }
+
+    // 現在のデモの path の今の値を、再生位置のキーにする
+    // アニメーションできない値なら false
+    pub fn add_keyframe(&mut self, path: &str, interpolation: Interpolation) -> bool {
+        let Some((name, params)) = self.demos.get(self.current_demo_index) else {
+            return false;
+        };
+        let Some(value) = params
+            .save_state()
+            .and_then(|state| timeline::read_value(state.params.pointer(path)?))
+        else {
+            return false;
+        };
+        let time = self.timeline.time;
+        self.timeline
+            .track_mut(name, path)
+            .set_key(time, value, interpolation);
+        true
+    }
+
+    // 最初から position 個のステップを適用した状態まで戻すかやり直す
+    pub fn go_to_history(&mut self, position: usize) -> Result<(), StateError> {
+        while self.history.position() > position && self.undo()? {}
+        while self.history.position() < position && self.redo()? {}
+        Ok(())
+    }
+}
+
+// "Triangle: color" のように、変わった項目の名前を並べる
+fn edit_label(name: &str, before: &DemoState, after: &DemoState) -> String {
+    let (Some(before), Some(after)) = (before.params.as_object(), after.params.as_object()) else {
+        return name.to_string();
+    };
+    let keys: Vec<&str> = before
+        .keys()
+        .chain(after.keys().filter(|key| !before.contains_key(*key)))
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(String::as_str)
+        .collect();
+    if keys.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name}: {}", keys.join(", "))
+    }
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new()
     }
 }
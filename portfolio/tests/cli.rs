@@ -0,0 +1,193 @@
+// コマンドライン引数の解釈とウィンドウを開かないサブコマンド
+
+use clap::Parser;
+use portfolio::{
+    create_demos, validate_shaders, validate_wgsl, Backend, Cli, Command, PowerPreference, Size,
+    DEMOS,
+};
+
+mod common;
+
+use common::fallback_renderer;
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(std::iter::once("portfolio").chain(args.iter().copied()))
+}
+
+#[test]
+fn parse_window_options() {
+    let cli = parse(&[
+        "--demo",
+        "Mandelbrot",
+        "--project",
+        "scene.json",
+        "--size",
+        "1600x900",
+        "--backend",
+        "vulkan",
+        "--power-preference",
+        "low",
+        "--no-vsync",
+    ])
+    .unwrap();
+    assert!(cli.command.is_none());
+    assert_eq!(cli.demo.as_deref(), Some("Mandelbrot"));
+    assert_eq!(cli.project.as_deref(), Some("scene.json".as_ref()));
+    assert_eq!(cli.backend, Some(Backend::Vulkan));
+    assert_eq!(cli.power_preference, Some(PowerPreference::Low));
+
+    let options = cli.native_options();
+    assert_eq!(
+        options.initial_window_size,
+        Some(eframe::egui::vec2(1600.0, 900.0))
+    );
+    assert!(!options.vsync);
+    assert_eq!(
+        options.wgpu_options.supported_backends,
+        wgpu::Backends::VULKAN
+    );
+    assert_eq!(
+        options.wgpu_options.power_preference,
+        wgpu::PowerPreference::LowPower
+    );
+    assert_eq!(
+        options.wgpu_options.present_mode,
+        wgpu::PresentMode::AutoNoVsync
+    );
+
+    // 何も指定しなければ eframe の既定のまま
+    let cli = parse(&[]).unwrap();
+    let options = cli.native_options();
+    let default = eframe::NativeOptions::default();
+    assert_eq!(options.initial_window_size, default.initial_window_size);
+    assert!(options.vsync);
+    assert_eq!(
+        options.wgpu_options.supported_backends,
+        default.wgpu_options.supported_backends
+    );
+    assert_eq!(cli.backends(), wgpu::Backends::all());
+}
+
+#[test]
+fn reject_invalid_options() {
+    assert!(parse(&["--size", "1600"]).is_err());
+    assert!(parse(&["--size", "0x900"]).is_err());
+    assert!(parse(&["--backend", "glide"]).is_err());
+    assert!(parse(&["render"]).is_err());
+    assert!(parse(&["render", "project.ron", "--fps", "0"]).is_err());
+    assert_eq!(
+        "1280x720".parse(),
+        Ok(Size {
+            width: 1280,
+            height: 720
+        })
+    );
+}
+
+#[test]
+fn parse_render_command() {
+    let cli = parse(&[
+        "--backend",
+        "gl",
+        "render",
+        "project.ron",
+        "--demo",
+        "Physics",
+        "--size",
+        "640x360",
+        "--fps",
+        "60",
+        "--duration",
+        "2",
+        "--pipe",
+        "ffmpeg",
+        "-f",
+        "rawvideo",
+        "-s",
+        "{width}x{height}",
+        "-i",
+        "-",
+        "--demo",
+        "out.mp4",
+    ])
+    .unwrap();
+    assert_eq!(cli.backend, Some(Backend::Gl));
+    let Some(Command::Render(args)) = cli.command else {
+        panic!("{:?}", cli.command);
+    };
+    assert_eq!(args.project, std::path::Path::new("project.ron"));
+    assert_eq!(args.output, std::path::Path::new("frames"));
+    // --pipe より後ろはエンコーダーの引数
+    assert_eq!(
+        args.pipe,
+        [
+            "ffmpeg",
+            "-f",
+            "rawvideo",
+            "-s",
+            "{width}x{height}",
+            "-i",
+            "-",
+            "--demo",
+            "out.mp4"
+        ]
+    );
+    let settings = args.settings();
+    assert_eq!(settings.demo.as_deref(), Some("Physics"));
+    assert_eq!((settings.width, settings.height), (640, 360));
+    assert_eq!(settings.fps, 60.0);
+    assert_eq!(settings.start, 0.0);
+    assert_eq!(settings.duration, Some(2.0));
+
+    // バックエンドはサブコマンドのあとでも指定できる
+    let cli = parse(&["render", "project.ron", "--power-preference", "high"]).unwrap();
+    assert_eq!(cli.power_preference, Some(PowerPreference::High));
+    assert!(matches!(cli.command, Some(Command::Render(args)) if args.pipe.is_empty()));
+
+    assert!(matches!(
+        parse(&["list-demos"]).unwrap().command,
+        Some(Command::ListDemos)
+    ));
+    assert!(matches!(
+        parse(&["validate-shaders"]).unwrap().command,
+        Some(Command::ValidateShaders)
+    ));
+}
+
+#[test]
+fn validate_embedded_shaders() {
+    let results = validate_shaders();
+    assert!(results.len() > demolib::SHADERS.len());
+    for (name, result) in results {
+        assert_eq!(result, Ok(()), "{name}");
+    }
+
+    let vertex = demolib::SHADERS
+        .iter()
+        .find(|entry| entry.stage == wgpu::ShaderStages::VERTEX)
+        .unwrap();
+    let error = validate_wgsl(vertex.name, vertex.wgsl, wgpu::ShaderStages::FRAGMENT).unwrap_err();
+    assert!(error.contains("expected"), "{error}");
+
+    let error = validate_wgsl(
+        "broken.fs",
+        "@fragment fn main() -> @location(0) vec4<f32> { return 1.0; }",
+        wgpu::ShaderStages::FRAGMENT,
+    )
+    .unwrap_err();
+    assert!(error.contains("broken.fs"), "{error}");
+}
+
+// list-demos は GPU なしで名前を出すので、デモの名前とずれていないか確かめる
+#[test]
+fn list_registered_demos() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let names: Vec<&str> = create_demos(renderer.device())
+        .iter()
+        .map(|demo| demo.name())
+        .collect();
+    let registered: Vec<&str> = DEMOS.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, registered);
+}
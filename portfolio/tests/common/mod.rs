@@ -0,0 +1,14 @@
+// render と cli のテストで共有するアダプターの用意
+
+use demolib::HeadlessRenderer;
+
+// GPU のない環境ではテストを飛ばせるように、アダプターが使えなければ理由を表示して None を返す
+pub fn fallback_renderer() -> Option<HeadlessRenderer> {
+    match HeadlessRenderer::new_fallback() {
+        Ok(renderer) => Some(renderer),
+        Err(error) => {
+            eprintln!("skipping: no usable adapter ({error})");
+            None
+        }
+    }
+}
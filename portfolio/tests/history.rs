@@ -0,0 +1,243 @@
+// ワークスペースの編集履歴 (元に戻す / やり直す)
+
+use demolib::{MandelbrotParams, TriangleParams};
+use portfolio::Workspace;
+
+fn workspace() -> Workspace {
+    let mut workspace = Workspace::new();
+    workspace.add_demo("Triangle", Box::<TriangleParams>::default());
+    workspace.add_demo("Mandelbrot", Box::<MandelbrotParams>::default());
+    workspace
+}
+
+fn params<T: 'static>(workspace: &Workspace, index: usize) -> &T {
+    workspace
+        .get_demo_params(index)
+        .unwrap()
+        .as_any()
+        .downcast_ref()
+        .unwrap()
+}
+
+fn edit<T: 'static>(workspace: &mut Workspace, time: f64, held: bool, f: impl FnOnce(&mut T)) {
+    workspace.edit_current_demo(time, held, |params| {
+        f(params.as_any_mut().downcast_mut().unwrap())
+    });
+}
+
+fn labels(workspace: &Workspace) -> Vec<&str> {
+    workspace.history().labels().collect()
+}
+
+#[test]
+fn undo_and_redo_edits() {
+    let mut workspace = workspace();
+    let default_color = params::<TriangleParams>(&workspace, 0).color;
+    edit(&mut workspace, 0.0, false, |params: &mut TriangleParams| {
+        params.color = [0.9, 0.1, 0.4];
+    });
+    workspace.set_current_demo_index(1);
+    edit(
+        &mut workspace,
+        1.0,
+        false,
+        |params: &mut MandelbrotParams| {
+            params.max_iterations = 100;
+        },
+    );
+    assert_eq!(
+        labels(&workspace),
+        [
+            "Triangle: color",
+            "Show Mandelbrot",
+            "Mandelbrot: max_iterations"
+        ]
+    );
+
+    assert!(workspace.undo().unwrap());
+    assert_eq!(
+        params::<MandelbrotParams>(&workspace, 1).max_iterations,
+        MandelbrotParams::default().max_iterations
+    );
+    assert!(workspace.undo().unwrap());
+    assert_eq!(workspace.get_current_demo_index(), 0);
+    assert!(workspace.undo().unwrap());
+    assert_eq!(params::<TriangleParams>(&workspace, 0).color, default_color);
+    assert!(!workspace.undo().unwrap());
+
+    assert!(workspace.redo().unwrap());
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        [0.9, 0.1, 0.4]
+    );
+    assert_eq!(workspace.history().position(), 1);
+
+    // 戻したあとに編集すると、やり直せなくなる
+    edit(&mut workspace, 2.0, false, |params: &mut TriangleParams| {
+        params.color = [0.0, 1.0, 0.0];
+    });
+    assert!(!workspace.history().can_redo());
+    assert_eq!(labels(&workspace), ["Triangle: color", "Triangle: color"]);
+}
+
+#[test]
+fn coalesce_continuous_edits() {
+    let mut workspace = workspace();
+    // ドラッグ中はボタンを押したままフレームが進む
+    for frame in 0..30 {
+        edit(
+            &mut workspace,
+            frame as f64 / 60.0,
+            true,
+            |params: &mut TriangleParams| params.color[0] = frame as f32 / 30.0,
+        );
+    }
+    // ボタンを離したあとのマウスホイールは、間隔を空けて届く
+    for frame in 0..3 {
+        edit(
+            &mut workspace,
+            0.8 + frame as f64 * 0.2,
+            false,
+            |params: &mut TriangleParams| params.color[0] = 0.5 + frame as f32 * 0.1,
+        );
+    }
+    assert_eq!(workspace.history().position(), 1);
+
+    // 間が空いたら別のステップ
+    edit(&mut workspace, 3.0, false, |params: &mut TriangleParams| {
+        params.color[0] = 0.0;
+    });
+    assert_eq!(workspace.history().position(), 2);
+    workspace.undo().unwrap();
+    assert!((params::<TriangleParams>(&workspace, 0).color[0] - 0.7).abs() < 1e-6);
+    workspace.undo().unwrap();
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        TriangleParams::default().color
+    );
+}
+
+#[test]
+fn separate_edits_of_other_fields() {
+    let mut workspace = workspace();
+    workspace.set_current_demo_index(1);
+    edit(
+        &mut workspace,
+        0.0,
+        true,
+        |params: &mut MandelbrotParams| {
+            params.max_iterations = 100;
+        },
+    );
+    edit(
+        &mut workspace,
+        0.1,
+        true,
+        |params: &mut MandelbrotParams| {
+            params.exponent = 4.0;
+        },
+    );
+    assert_eq!(
+        labels(&workspace),
+        [
+            "Show Mandelbrot",
+            "Mandelbrot: max_iterations",
+            "Mandelbrot: exponent"
+        ]
+    );
+}
+
+#[test]
+fn drop_edit_dragged_back_to_start() {
+    let mut workspace = workspace();
+    edit(&mut workspace, 0.0, false, |params: &mut TriangleParams| {
+        params.color = [1.0, 0.0, 0.0];
+    });
+    // 前の編集とは別のドラッグで、元の値に戻るまで動かす
+    edit(&mut workspace, 5.0, true, |params: &mut TriangleParams| {
+        params.color = [0.5, 0.0, 0.0];
+    });
+    edit(&mut workspace, 5.1, true, |params: &mut TriangleParams| {
+        params.color = [1.0, 0.0, 0.0];
+    });
+    assert_eq!(workspace.history().position(), 1);
+
+    // ドラッグを続けても、消したステップの前のステップにはまとめない
+    edit(&mut workspace, 5.2, true, |params: &mut TriangleParams| {
+        params.color = [0.0, 0.0, 1.0];
+    });
+    assert_eq!(workspace.history().position(), 2);
+    workspace.undo().unwrap();
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        [1.0, 0.0, 0.0]
+    );
+}
+
+#[test]
+fn go_to_history_position() {
+    let mut workspace = workspace();
+    for step in 0..5 {
+        edit(
+            &mut workspace,
+            step as f64,
+            false,
+            |params: &mut TriangleParams| params.color = [step as f32 / 4.0; 3],
+        );
+    }
+    workspace.go_to_history(2).unwrap();
+    assert_eq!(params::<TriangleParams>(&workspace, 0).color, [0.25; 3]);
+    assert_eq!(labels(&workspace).len(), 5);
+    workspace.go_to_history(4).unwrap();
+    assert_eq!(params::<TriangleParams>(&workspace, 0).color, [0.75; 3]);
+    workspace.go_to_history(0).unwrap();
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        TriangleParams::default().color
+    );
+}
+
+#[test]
+fn keep_history_within_budget() {
+    let mut workspace = workspace();
+    workspace.set_current_demo_index(1);
+    for step in 0..10 {
+        edit(
+            &mut workspace,
+            step as f64,
+            false,
+            |params: &mut MandelbrotParams| params.max_iterations = step + 1,
+        );
+    }
+    let size = workspace.history().size();
+    assert_eq!(workspace.history().position(), 11);
+
+    // 古いステップから捨てる
+    workspace.set_history_budget(size / 2);
+    let history = workspace.history();
+    assert!(history.size() <= size / 2);
+    assert!(history.position() < 11);
+    assert_eq!(history.labels().last(), Some("Mandelbrot: max_iterations"));
+    assert!(!history.labels().any(|label| label == "Show Mandelbrot"));
+
+    // 上限がどれだけ小さくても直前のステップは残す
+    workspace.set_history_budget(0);
+    assert_eq!(workspace.history().position(), 1);
+    workspace.undo().unwrap();
+    assert_eq!(params::<MandelbrotParams>(&workspace, 1).max_iterations, 9);
+}
+
+#[test]
+fn clear_history_on_load() {
+    let mut workspace = workspace();
+    edit(&mut workspace, 0.0, false, |params: &mut TriangleParams| {
+        params.color = [1.0, 0.0, 0.0];
+    });
+    let state = workspace.save();
+    workspace.load(state).unwrap();
+    assert!(!workspace.history().can_undo());
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        [1.0, 0.0, 0.0]
+    );
+}
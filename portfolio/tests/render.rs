@@ -0,0 +1,188 @@
+// オフライン描画 (固定フレームレートでタイムラインを進めてフレームを書き出す)
+
+use demolib::{Image, TriangleParams};
+use portfolio::{
+    render, FrameSink, Interpolation, PngSequence, RenderError, RenderSettings, Workspace,
+    WorkspaceState,
+};
+
+mod common;
+
+use common::fallback_renderer;
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+
+// フレームをメモリに集める
+#[derive(Default)]
+struct Frames {
+    images: Vec<Image>,
+    finished: bool,
+}
+
+impl FrameSink for Frames {
+    fn write_frame(&mut self, index: usize, image: &Image) -> Result<(), RenderError> {
+        assert_eq!(index, self.images.len());
+        self.images.push(image.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), RenderError> {
+        self.finished = true;
+        Ok(())
+    }
+}
+
+// 三角形の色を 1 秒かけて黒から白にする
+fn animated_triangle() -> WorkspaceState {
+    let mut workspace = Workspace::new();
+    workspace.add_demo("Triangle", Box::<TriangleParams>::default());
+    let params: &mut TriangleParams = workspace
+        .get_current_demo_params_mut()
+        .unwrap()
+        .as_any_mut()
+        .downcast_mut()
+        .unwrap();
+    params.color = [0.0, 0.0, 0.0];
+    workspace.add_keyframe("/color", Interpolation::Linear);
+    workspace.timeline_mut().time = 1.0;
+    workspace.timeline_mut().duration = 1.0;
+    let params: &mut TriangleParams = workspace
+        .get_current_demo_params_mut()
+        .unwrap()
+        .as_any_mut()
+        .downcast_mut()
+        .unwrap();
+    params.color = [1.0, 1.0, 1.0];
+    workspace.add_keyframe("/color", Interpolation::Linear);
+    workspace.save()
+}
+
+fn render_frames(state: WorkspaceState, settings: &RenderSettings) -> Option<Vec<Image>> {
+    let renderer = fallback_renderer()?;
+    let mut frames = Frames::default();
+    let frame_count = render(&renderer, state, settings, &mut [&mut frames]).unwrap();
+    assert!(frames.finished);
+    assert_eq!(frames.images.len(), frame_count);
+    Some(frames.images)
+}
+
+// 描画された画素のうちいちばん明るい値
+fn brightest(image: &Image) -> u8 {
+    image
+        .pixels
+        .chunks_exact(4)
+        .map(|pixel| pixel[0])
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn count_frames() {
+    let settings = RenderSettings {
+        fps: 4.0,
+        ..RenderSettings::default()
+    };
+    assert_eq!(settings.frame_count(1.0), 5);
+    assert_eq!(settings.frame_count(0.0), 1);
+    let settings = RenderSettings {
+        fps: 10.0,
+        start: 1.0,
+        duration: Some(0.5),
+        ..RenderSettings::default()
+    };
+    assert_eq!(settings.frame_count(10.0), 6);
+}
+
+#[test]
+fn render_timeline_at_fixed_frame_rate() {
+    let settings = RenderSettings {
+        demo: Some("Triangle".to_string()),
+        width: WIDTH,
+        height: HEIGHT,
+        fps: 4.0,
+        ..RenderSettings::default()
+    };
+    let Some(frames) = render_frames(animated_triangle(), &settings) else {
+        return;
+    };
+    assert_eq!(frames.len(), 5);
+    assert!(frames
+        .iter()
+        .all(|image| image.width == WIDTH && image.height == HEIGHT));
+    let brightness: Vec<u8> = frames.iter().map(brightest).collect();
+    assert!(
+        brightness.windows(2).all(|pair| pair[0] < pair[1]),
+        "{brightness:?}"
+    );
+
+    // 同じ入力からは同じフレーム
+    assert_eq!(
+        render_frames(animated_triangle(), &settings).unwrap(),
+        frames
+    );
+}
+
+#[test]
+fn step_simulations_deterministically() {
+    let mut workspace = Workspace::new();
+    workspace.add_demo("Physics", Box::<demolib::PhysicsParams>::default());
+    let state = workspace.save();
+    let settings = RenderSettings {
+        demo: Some("Physics".to_string()),
+        width: WIDTH,
+        height: HEIGHT,
+        fps: 30.0,
+        duration: Some(0.5),
+        ..RenderSettings::default()
+    };
+    let Some(frames) = render_frames(state.clone(), &settings) else {
+        return;
+    };
+    // 物体が落ちていく
+    assert_ne!(frames.first(), frames.last());
+    assert_eq!(render_frames(state, &settings).unwrap(), frames);
+}
+
+#[test]
+fn write_png_sequence() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("render-frames");
+    let _ = std::fs::remove_dir_all(&directory);
+    let mut png_sequence = PngSequence::new(&directory).unwrap();
+    let settings = RenderSettings {
+        width: WIDTH,
+        height: HEIGHT,
+        fps: 2.0,
+        ..RenderSettings::default()
+    };
+    let frame_count = render(
+        &renderer,
+        animated_triangle(),
+        &settings,
+        &mut [&mut png_sequence],
+    )
+    .unwrap();
+    assert_eq!(frame_count, 3);
+    for index in 0..frame_count {
+        let file = std::fs::File::open(png_sequence.path(index)).unwrap();
+        let image = Image::read_png(std::io::BufReader::new(file)).unwrap();
+        assert_eq!((image.width, image.height), (WIDTH, HEIGHT));
+    }
+    assert!(directory.join("frame_00002.png").exists());
+}
+
+#[test]
+fn reject_unknown_demo() {
+    let Some(renderer) = fallback_renderer() else {
+        return;
+    };
+    let settings = RenderSettings {
+        demo: Some("Teapot".to_string()),
+        ..RenderSettings::default()
+    };
+    let result = render(&renderer, animated_triangle(), &settings, &mut []);
+    assert!(matches!(result, Err(RenderError::UnknownDemo(name)) if name == "Teapot"));
+}
@@ -0,0 +1,222 @@
+// キーフレームアニメーションの評価とワークスペースへの反映
+
+use demolib::{MandelbrotParams, Model3dParams, TriangleParams};
+use portfolio::{Interpolation, ProjectFormat, Timeline, Track, Workspace};
+
+fn workspace() -> Workspace {
+    let mut workspace = Workspace::new();
+    workspace.add_demo("Triangle", Box::<TriangleParams>::default());
+    workspace.add_demo("Mandelbrot", Box::<MandelbrotParams>::default());
+    workspace.add_demo("Model 3D", Box::<Model3dParams>::default());
+    workspace
+}
+
+fn params<T: 'static>(workspace: &Workspace, index: usize) -> &T {
+    workspace
+        .get_demo_params(index)
+        .unwrap()
+        .as_any()
+        .downcast_ref()
+        .unwrap()
+}
+
+fn params_mut<T: 'static>(workspace: &mut Workspace) -> &mut T {
+    workspace
+        .get_current_demo_params_mut()
+        .unwrap()
+        .as_any_mut()
+        .downcast_mut()
+        .unwrap()
+}
+
+fn track(interpolation: Interpolation) -> Track {
+    let mut track = Track::new("Triangle", "/color");
+    track.set_key(1.0, vec![0.0, 0.0, 0.0], interpolation);
+    track.set_key(3.0, vec![1.0, 2.0, 4.0], interpolation);
+    track.set_key(5.0, vec![1.0, 0.0, 0.0], interpolation);
+    track
+}
+
+fn assert_near(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn interpolate_keyframes() {
+    let linear = track(Interpolation::Linear);
+    assert_near(&linear.evaluate(0.0).unwrap(), &[0.0, 0.0, 0.0]);
+    assert_near(&linear.evaluate(2.0).unwrap(), &[0.5, 1.0, 2.0]);
+    assert_near(&linear.evaluate(3.0).unwrap(), &[1.0, 2.0, 4.0]);
+    assert_near(&linear.evaluate(9.0).unwrap(), &[1.0, 0.0, 0.0]);
+
+    let step = track(Interpolation::Step);
+    assert_near(&step.evaluate(2.9).unwrap(), &[0.0, 0.0, 0.0]);
+    assert_near(&step.evaluate(3.1).unwrap(), &[1.0, 2.0, 4.0]);
+
+    // 最初のキーでは止まっていて、中のキーでは前後のキーを結ぶ向きに進む
+    let bezier = track(Interpolation::Bezier);
+    assert_near(&bezier.evaluate(1.0).unwrap(), &[0.0, 0.0, 0.0]);
+    assert_near(&bezier.evaluate(3.0).unwrap(), &[1.0, 2.0, 4.0]);
+    let before = bezier.evaluate(2.99).unwrap();
+    let after = bezier.evaluate(3.01).unwrap();
+    assert!(before[0] < 1.0 && after[0] > 1.0);
+    let start = bezier.evaluate(1.01).unwrap();
+    assert!(start[2] > 0.0 && start[2] < linear.evaluate(1.01).unwrap()[2]);
+
+    assert_eq!(Track::new("Triangle", "/color").evaluate(1.0), None);
+}
+
+#[test]
+fn replace_and_remove_keys() {
+    let mut track = track(Interpolation::Linear);
+    track.set_key(3.0, vec![2.0, 2.0, 2.0], Interpolation::Step);
+    track.set_key(2.0, vec![0.0, 1.0, 0.0], Interpolation::Linear);
+    let times: Vec<f64> = track
+        .keyframes
+        .iter()
+        .map(|keyframe| keyframe.time)
+        .collect();
+    assert_eq!(times, [1.0, 2.0, 3.0, 5.0]);
+    assert_eq!(track.keyframes[2].interpolation, Interpolation::Step);
+
+    assert!(track.remove_key(2.0));
+    assert!(!track.remove_key(2.0));
+    assert_eq!(track.keyframes.len(), 3);
+}
+
+#[test]
+fn advance_and_loop() {
+    let mut timeline = Timeline {
+        duration: 2.0,
+        ..Timeline::default()
+    };
+    timeline.advance(1.0);
+    assert_eq!(timeline.time, 0.0);
+
+    timeline.playing = true;
+    timeline.advance(1.5);
+    timeline.advance(1.0);
+    assert!((timeline.time - 0.5).abs() < 1e-9);
+
+    timeline.looping = false;
+    timeline.advance(3.0);
+    assert_eq!(timeline.time, 2.0);
+    assert!(!timeline.playing);
+}
+
+#[test]
+fn apply_tracks_to_current_demo() {
+    let mut workspace = workspace();
+    params_mut::<TriangleParams>(&mut workspace).color = [0.0, 0.0, 0.0];
+    assert!(workspace.add_keyframe("/color", Interpolation::Linear));
+    workspace.timeline_mut().time = 4.0;
+    params_mut::<TriangleParams>(&mut workspace).color = [1.0, 0.5, 0.25];
+    assert!(workspace.add_keyframe("/color", Interpolation::Linear));
+
+    workspace.timeline_mut().time = 1.0;
+    workspace.apply_timeline().unwrap();
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        [0.25, 0.125, 0.0625]
+    );
+
+    // マンデルブロ集合の範囲を動かす。反復回数のような整数は丸める
+    workspace.set_current_demo_index(1);
+    workspace.timeline_mut().time = 0.0;
+    assert!(workspace.add_keyframe("/center", Interpolation::Linear));
+    assert!(workspace.add_keyframe("/scale", Interpolation::Linear));
+    assert!(workspace.add_keyframe("/max_iterations", Interpolation::Linear));
+    workspace.timeline_mut().time = 2.0;
+    let mandelbrot = params_mut::<MandelbrotParams>(&mut workspace);
+    mandelbrot.center = [0.5, 1.0];
+    mandelbrot.scale = 0.5;
+    mandelbrot.max_iterations = 101;
+    assert!(workspace.add_keyframe("/center", Interpolation::Linear));
+    assert!(workspace.add_keyframe("/scale", Interpolation::Linear));
+    assert!(workspace.add_keyframe("/max_iterations", Interpolation::Linear));
+    // 選ばれていない三角形のデモはそのまま
+    workspace.timeline_mut().time = 1.0;
+    workspace.apply_timeline().unwrap();
+    let default = MandelbrotParams::default();
+    let mandelbrot = params::<MandelbrotParams>(&workspace, 1);
+    assert_eq!(mandelbrot.center, [0.0, 0.5]);
+    assert_eq!(mandelbrot.scale, 0.75);
+    assert_eq!(
+        mandelbrot.max_iterations,
+        ((default.max_iterations as f64 + 101.0) / 2.0).round() as u32
+    );
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        [0.25, 0.125, 0.0625]
+    );
+}
+
+// 手で書き換えたプロジェクトのトラックはパラメーターの型に合わないことがある
+#[test]
+fn report_tracks_that_do_not_fit() {
+    let mut workspace = workspace();
+    workspace.set_current_demo_index(1);
+    workspace
+        .timeline_mut()
+        .track_mut("Mandelbrot", "/max_iterations")
+        .set_key(0.0, vec![1.0e12], Interpolation::Step);
+    assert!(workspace.apply_timeline().is_err());
+    assert_eq!(
+        params::<MandelbrotParams>(&workspace, 1).max_iterations,
+        MandelbrotParams::default().max_iterations
+    );
+}
+
+#[test]
+fn animate_camera() {
+    let mut workspace = workspace();
+    workspace.set_current_demo_index(2);
+    let paths = workspace.animatable_paths();
+    for path in ["/camera/yaw", "/camera/distance", "/camera/target"] {
+        assert!(paths.iter().any(|x| x == path), "{path} in {paths:?}");
+    }
+    // 数値でない値にはキーを打てない
+    assert!(!workspace.add_keyframe("/scene_path", Interpolation::Linear));
+    assert!(!workspace.add_keyframe("/missing", Interpolation::Linear));
+
+    params_mut::<Model3dParams>(&mut workspace).camera.yaw = 0.0;
+    assert!(workspace.add_keyframe("/camera/yaw", Interpolation::Bezier));
+    workspace.timeline_mut().time = 1.0;
+    params_mut::<Model3dParams>(&mut workspace).camera.yaw = 2.0;
+    assert!(workspace.add_keyframe("/camera/yaw", Interpolation::Bezier));
+
+    workspace.timeline_mut().time = 0.5;
+    workspace.apply_timeline().unwrap();
+    let yaw = params::<Model3dParams>(&workspace, 2).camera.yaw;
+    assert!((yaw - 1.0).abs() < 1e-6, "{yaw}");
+}
+
+#[test]
+fn save_timeline_with_project() {
+    let mut workspace = workspace();
+    assert!(workspace.add_keyframe("/color", Interpolation::Step));
+    workspace.timeline_mut().duration = 4.0;
+    let state = workspace.save();
+
+    for format in [ProjectFormat::Ron, ProjectFormat::Json] {
+        let text = format.write(&state).unwrap();
+        assert_eq!(format.read(&text).unwrap(), state);
+
+        let mut loaded = Workspace::new();
+        loaded.add_demo("Triangle", Box::<TriangleParams>::default());
+        loaded.load(format.read(&text).unwrap()).unwrap();
+        assert_eq!(loaded.timeline(), workspace.timeline());
+    }
+
+    // タイムラインを足す前のプロジェクトファイルも読める
+    let mut legacy = serde_json::to_value(&state).unwrap();
+    legacy.as_object_mut().unwrap().remove("timeline");
+    let legacy = ProjectFormat::Json.read(&legacy.to_string()).unwrap();
+    assert_eq!(legacy.timeline, Timeline::default());
+}
@@ -0,0 +1,169 @@
+// ワークスペースの保存と復元 (eframe のストレージとプロジェクトファイル)
+
+use std::path::Path;
+
+use demolib::{
+    FractalType, LightKind, MandelbrotParams, Model3dParams, PhysicsParams, TetrisParams,
+    TriangleParams,
+};
+use portfolio::{ProjectError, ProjectFormat, Workspace, WorkspaceState, WORKSPACE_VERSION};
+
+fn workspace() -> Workspace {
+    let mut workspace = Workspace::new();
+    workspace.add_demo("Triangle", Box::<TriangleParams>::default());
+    workspace.add_demo("Mandelbrot", Box::<MandelbrotParams>::default());
+    workspace.add_demo("Model 3D", Box::<Model3dParams>::default());
+    workspace.add_demo("Physics", Box::<PhysicsParams>::default());
+    workspace.add_demo("Tetris", Box::<TetrisParams>::default());
+    workspace
+}
+
+fn params<T: 'static>(workspace: &Workspace, index: usize) -> &T {
+    workspace
+        .get_demo_params(index)
+        .unwrap()
+        .as_any()
+        .downcast_ref()
+        .unwrap()
+}
+
+fn edit<T: 'static>(workspace: &mut Workspace, index: usize, f: impl FnOnce(&mut T)) {
+    workspace.set_current_demo_index(index);
+    let params = workspace.get_current_demo_params_mut().unwrap();
+    f(params.as_any_mut().downcast_mut().unwrap());
+}
+
+fn edited_workspace() -> Workspace {
+    let mut workspace = workspace();
+    edit(&mut workspace, 0, |params: &mut TriangleParams| {
+        params.color = [0.9, 0.1, 0.4];
+    });
+    edit(&mut workspace, 1, |params: &mut MandelbrotParams| {
+        params.fractal_type = FractalType::Julia;
+        params.center = [0.1, 0.2];
+    });
+    edit(&mut workspace, 2, |params: &mut Model3dParams| {
+        params.camera.distance = 7.5;
+        params.lighting.lights[1].kind = LightKind::Spot;
+        params.shadow.cascade_count = 2;
+        params.default_material.roughness = 0.125;
+    });
+    edit(&mut workspace, 3, |params: &mut PhysicsParams| {
+        params.settings.body_count = 12;
+    });
+    edit(&mut workspace, 4, |params: &mut TetrisParams| {
+        params.seed = 42;
+    });
+    workspace.set_current_demo_index(2);
+    workspace
+}
+
+fn assert_restored(workspace: &Workspace) {
+    assert_eq!(workspace.get_current_demo_index(), 2);
+    assert_eq!(
+        params::<TriangleParams>(workspace, 0).color,
+        [0.9, 0.1, 0.4]
+    );
+    let mandelbrot = params::<MandelbrotParams>(workspace, 1);
+    assert_eq!(mandelbrot.fractal_type, FractalType::Julia);
+    assert_eq!(mandelbrot.center, [0.1, 0.2]);
+    let model_3d = params::<Model3dParams>(workspace, 2);
+    assert_eq!(model_3d.camera.distance, 7.5);
+    assert_eq!(model_3d.lighting.lights[1].kind, LightKind::Spot);
+    assert_eq!(model_3d.shadow.cascade_count, 2);
+    assert_eq!(model_3d.default_material.roughness, 0.125);
+    // 物体は設定から作り直す (床と壁の 5 つを含む)
+    let physics = params::<PhysicsParams>(workspace, 3);
+    assert_eq!(physics.settings.body_count, 12);
+    assert_eq!(physics.world.bodies().len(), 12 + 5);
+    assert_eq!(params::<TetrisParams>(workspace, 4).seed, 42);
+}
+
+#[test]
+fn round_trip_project_formats() {
+    let state = edited_workspace().save();
+    assert_eq!(state.version, WORKSPACE_VERSION);
+    assert_eq!(state.current_demo.as_deref(), Some("Model 3D"));
+
+    for format in [ProjectFormat::Ron, ProjectFormat::Json] {
+        let text = format.write(&state).unwrap();
+        assert_eq!(format.read(&text).unwrap(), state, "{format:?}");
+
+        let mut workspace = workspace();
+        workspace.load(format.read(&text).unwrap()).unwrap();
+        assert_restored(&workspace);
+    }
+}
+
+#[test]
+fn save_and_load_project_files() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("save_and_load_project_files");
+    std::fs::create_dir_all(&directory).unwrap();
+
+    for file_name in ["project.ron", "project.json"] {
+        let path = directory.join(file_name);
+        edited_workspace().save_project(&path).unwrap();
+
+        let mut workspace = workspace();
+        workspace.load_project(&path).unwrap();
+        assert_restored(&workspace);
+    }
+
+    let path = directory.join("project.toml");
+    assert!(matches!(
+        edited_workspace().save_project(&path),
+        Err(ProjectError::UnsupportedFormat(_))
+    ));
+    assert!(matches!(
+        workspace().load_project(&directory.join("missing.ron")),
+        Err(ProjectError::Io(_))
+    ));
+}
+
+#[test]
+fn migrate_legacy_storage() {
+    // 以前はデモごとに "demo/{名前}" のキーで RON の文字列を保存していた
+    let saved = MandelbrotParams {
+        max_iterations: 1000,
+        ..Default::default()
+    };
+    let legacy = ron::to_string(&saved).unwrap();
+    let mut workspace = workspace();
+    let state = WorkspaceState::from_legacy_storage(&workspace.get_demo_names(), |key| {
+        (key == "demo/Mandelbrot").then(|| legacy.clone())
+    });
+    assert_eq!(state.demos.len(), 1);
+
+    workspace.load(state).unwrap();
+    assert_eq!(params::<MandelbrotParams>(&workspace, 1), &saved);
+    assert_eq!(workspace.get_current_demo_index(), 0);
+}
+
+#[test]
+fn reject_newer_project() {
+    let mut state = edited_workspace().save();
+    state.version = WORKSPACE_VERSION + 1;
+    let text = ProjectFormat::Json.write(&state).unwrap();
+    assert!(matches!(
+        ProjectFormat::Json.read(&text),
+        Err(ProjectError::UnsupportedVersion { .. })
+    ));
+}
+
+#[test]
+fn restore_other_demos_when_one_fails() {
+    let mut state = edited_workspace().save();
+    state.demos.get_mut("Triangle").unwrap().params = serde_json::json!("broken");
+
+    let mut workspace = workspace();
+    let Err(ProjectError::Demos(errors)) = workspace.load(state) else {
+        panic!("the broken triangle state should be reported");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, "Triangle");
+    assert_eq!(
+        params::<TriangleParams>(&workspace, 0).color,
+        TriangleParams::default().color
+    );
+    assert_eq!(params::<TetrisParams>(&workspace, 4).seed, 42);
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use crate::{compile_file, Diagnostic, Manifest, ResourceKind, ShaderError, StructLayout};
+
+// build.rs から呼ぶ
+// shaders.ron にあるシェーダーをコンパイルして、OUT_DIR に次のものを書き出す
+//   {name}.wgsl, {name}.spv
+//   shaders.rs: 出力したシェーダーごとに次の形の shader! を並べた配列の式
+//     shader!(name, source, [(マクロ, 値), ...], ステージ, [(group, binding, リソース), ...], [(location, 頂点フォーマット), ...])
+//     リソースは uniform(大きさ), storage(大きさ, 読み込みのみか), texture(次元, サンプルの型, マルチサンプルか), sampler(比較するか)
+//   layouts.rs: GLSL のファイルごとのモジュールに、ブロックと構造体の大きさ (SIZE) とメンバーのオフセットを定数で並べたもの
+// 一覧と #include したファイルが変わったときだけ build.rs が実行し直されるようにする
+pub fn build(manifest_path: impl AsRef<Path>) -> Result<(), ShaderError> {
+    let manifest_path = manifest_path.as_ref();
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR is not set"));
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
+
+    let manifest = Manifest::load(manifest_path)?;
+    let directory = manifest_path.parent().unwrap_or(Path::new(""));
+    let targets = manifest.targets(directory);
+
+    let mut names = BTreeSet::new();
+    let mut dependencies = BTreeSet::new();
+    let mut diagnostics = Vec::new();
+    let mut table = String::from("&[\n");
+    // GLSL のファイルごとの構造体
+    let mut layouts: BTreeMap<&str, Vec<StructLayout>> = BTreeMap::new();
+    for target in &targets {
+        dependencies.insert(target.path.clone());
+        if !names.insert(target.name.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                manifest_path,
+                format!("duplicate shader name: {}", target.name),
+            ));
+            continue;
+        }
+
+        let compiled = match compile_file(&target.path, &target.defines) {
+            Ok(compiled) => compiled,
+            Err(ShaderError::Compile(errors)) => {
+                // 同じソースの別のマクロで同じエラーが出ても 1 回だけ表示する
+                for error in errors {
+                    if !diagnostics.contains(&error) {
+                        diagnostics.push(error);
+                    }
+                }
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        dependencies.extend(compiled.dependencies);
+
+        write(
+            &out_dir.join(format!("{}.wgsl", target.name)),
+            compiled.wgsl.as_bytes(),
+        )?;
+        let spirv: Vec<u8> = compiled
+            .spirv
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        write(&out_dir.join(format!("{}.spv", target.name)), &spirv)?;
+
+        let reflection = &compiled.reflection;
+        let defines: Vec<_> = target
+            .defines
+            .iter()
+            .map(|(name, value)| format!("({name:?}, {value:?})"))
+            .collect();
+        let stage = match reflection.stage {
+            naga::ShaderStage::Vertex => "VERTEX",
+            naga::ShaderStage::Fragment => "FRAGMENT",
+            naga::ShaderStage::Compute => "COMPUTE",
+        };
+        let resources: Vec<_> = reflection
+            .resources
+            .iter()
+            .map(|resource| {
+                let kind = match &resource.kind {
+                    ResourceKind::UniformBuffer { size } => format!("uniform({size})"),
+                    ResourceKind::StorageBuffer { size, read_only } => {
+                        format!("storage({size}, {read_only})")
+                    }
+                    ResourceKind::Texture {
+                        dimension,
+                        sample_type,
+                        multisampled,
+                    } => format!("texture({dimension:?}, {sample_type:?}, {multisampled})"),
+                    ResourceKind::Sampler { comparison } => format!("sampler({comparison})"),
+                };
+                format!("({}, {}, {kind})", resource.group, resource.binding)
+            })
+            .collect();
+        let vertex_inputs: Vec<_> = reflection
+            .vertex_inputs
+            .iter()
+            .map(|input| format!("({}, {:?})", input.location, input.format))
+            .collect();
+        writeln!(
+            table,
+            "    shader!({:?}, {:?}, [{}], {stage}, [{}], [{}]),",
+            target.name,
+            target.source,
+            defines.join(", "),
+            resources.join(", "),
+            vertex_inputs.join(", "),
+        )
+        .unwrap();
+
+        // マクロによって構造体のレイアウトが変わると Rust 側で確かめようがないのでエラーにする
+        let source_layouts = layouts.entry(target.source.as_str()).or_default();
+        for layout in &reflection.structs {
+            match source_layouts
+                .iter()
+                .find(|other| other.name == layout.name)
+            {
+                Some(other) if other != layout => diagnostics.push(Diagnostic::new(
+                    &target.path,
+                    format!(
+                        "layout of {} differs between variants ({})",
+                        layout.name, target.name
+                    ),
+                )),
+                Some(_) => {}
+                None => source_layouts.push(layout.clone()),
+            }
+        }
+    }
+    table.push_str("]\n");
+
+    for dependency in &dependencies {
+        println!("cargo:rerun-if-changed={}", dependency.display());
+    }
+    if !diagnostics.is_empty() {
+        return Err(ShaderError::Compile(diagnostics));
+    }
+    write(&out_dir.join("shaders.rs"), table.as_bytes())?;
+    write(
+        &out_dir.join("layouts.rs"),
+        layouts_source(&layouts).as_bytes(),
+    )
+}
+
+fn layouts_source(layouts: &BTreeMap<&str, Vec<StructLayout>>) -> String {
+    let mut source = String::new();
+    for (file, structs) in layouts {
+        if structs.is_empty() {
+            continue;
+        }
+        writeln!(source, "pub mod {} {{", identifier(file)).unwrap();
+        for layout in structs {
+            writeln!(source, "    pub mod {} {{", identifier(&layout.name)).unwrap();
+            writeln!(source, "        pub const SIZE: usize = {};", layout.size).unwrap();
+            for member in &layout.members {
+                writeln!(
+                    source,
+                    "        pub const {}: usize = {};",
+                    identifier(&member.name).to_uppercase(),
+                    member.offset
+                )
+                .unwrap();
+            }
+            writeln!(source, "    }}").unwrap();
+        }
+        writeln!(source, "}}").unwrap();
+    }
+    source
+}
+
+// model_3d.fs -> model_3d_fs, u_ViewProjection -> u_view_projection
+fn identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    let mut previous = '_';
+    for character in name.chars() {
+        if !character.is_ascii_alphanumeric() {
+            identifier.push('_');
+        } else if character.is_ascii_uppercase() && previous.is_ascii_lowercase() {
+            identifier.push('_');
+            identifier.push(character.to_ascii_lowercase());
+        } else {
+            identifier.push(character.to_ascii_lowercase());
+        }
+        previous = character;
+    }
+    identifier
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), ShaderError> {
+    std::fs::write(path, contents).map_err(|error| ShaderError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
@@ -0,0 +1,109 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{preprocess, reflect, Diagnostic, Preprocessed, ShaderError, ShaderReflection};
+
+pub struct CompiledShader {
+    pub stage: naga::ShaderStage,
+    pub wgsl: String,
+    pub spirv: Vec<u32>,
+    pub reflection: ShaderReflection,
+    // #include したものを含めて、読み込んだファイル
+    pub dependencies: Vec<PathBuf>,
+}
+
+// 拡張子でステージを判断する
+pub fn shader_stage(path: &Path) -> Option<naga::ShaderStage> {
+    match path.extension()?.to_str()? {
+        "vs" | "vert" => Some(naga::ShaderStage::Vertex),
+        "fs" | "frag" => Some(naga::ShaderStage::Fragment),
+        "cs" | "comp" => Some(naga::ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+// GLSL のファイルを defines のマクロを定義してコンパイルする
+pub fn compile_file(
+    path: &Path,
+    defines: &BTreeMap<String, String>,
+) -> Result<CompiledShader, ShaderError> {
+    let stage = shader_stage(path)
+        .ok_or_else(|| Diagnostic::new(path, "unknown shader stage (expected .vs, .fs or .cs)"))?;
+    let preprocessed = preprocess(path)?;
+
+    let mut options = naga::front::glsl::Options::from(stage);
+    for (name, value) in defines {
+        options.defines.insert(name.clone(), value.clone());
+    }
+    let module = naga::front::glsl::Frontend::default()
+        .parse(&options, &preprocessed.source)
+        .map_err(|errors| {
+            ShaderError::Compile(
+                errors
+                    .iter()
+                    .map(|error| diagnostic(&preprocessed, Some(error.meta), &error.kind))
+                    .collect(),
+            )
+        })?;
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| {
+        let span = error.spans().next().map(|(span, _)| *span);
+        diagnostic(&preprocessed, span, &error)
+    })?;
+
+    let wgsl = naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::all())
+        .map_err(|error| Diagnostic::new(path, format!("WGSL: {error}")))?;
+    let spirv = naga::back::spv::write_vec(
+        &module,
+        &info,
+        &naga::back::spv::Options::default(),
+        Some(&naga::back::spv::PipelineOptions {
+            shader_stage: stage,
+            entry_point: "main".to_string(),
+        }),
+    )
+    .map_err(|error| Diagnostic::new(path, format!("SPIR-V: {error}")))?;
+
+    Ok(CompiledShader {
+        stage,
+        wgsl,
+        spirv,
+        reflection: reflect(&module, stage),
+        dependencies: preprocessed.files,
+    })
+}
+
+// naga のエラーを #include を展開する前のファイルの位置に直す
+// 原因となったエラーもつなげて表示する
+fn diagnostic(
+    preprocessed: &Preprocessed,
+    span: Option<naga::Span>,
+    error: &dyn std::error::Error,
+) -> Diagnostic {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+
+    let mut diagnostic = Diagnostic::new(&preprocessed.files[0], message);
+    let Some(span) = span.filter(|span| span.is_defined()) else {
+        return diagnostic;
+    };
+    let location = span.location(&preprocessed.source);
+    if let Some((file, line)) = preprocessed.locate(location.line_number as usize) {
+        diagnostic.file = file.to_path_buf();
+        diagnostic.line = line;
+        diagnostic.column = location.line_position as usize;
+    }
+    diagnostic
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+// GLSL のファイル上の位置を指すメッセージ
+// line と column は 1 始まりで、位置が分からないときは 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(file: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            line: 0,
+            column: 0,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = self.file.display();
+        match (self.line, self.column) {
+            (0, _) => write!(f, "{file}: {}", self.message),
+            (line, 0) => write!(f, "{file}:{line}: {}", self.message),
+            (line, column) => write!(f, "{file}:{line}:{column}: {}", self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Manifest {
+        path: PathBuf,
+        error: ron::error::SpannedError,
+    },
+    Compile(Vec<Diagnostic>),
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            ShaderError::Manifest { path, error } => write!(
+                f,
+                "{}:{}:{}: {}",
+                path.display(),
+                error.position.line,
+                error.position.col,
+                error.code
+            ),
+            ShaderError::Compile(diagnostics) => {
+                for (index, diagnostic) in diagnostics.iter().enumerate() {
+                    if index != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl From<Diagnostic> for ShaderError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        ShaderError::Compile(vec![diagnostic])
+    }
+}
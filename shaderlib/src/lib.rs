@@ -0,0 +1,22 @@
+// GLSL のシェーダーを WGSL と SPIR-V に変換する
+// 各クレートの build.rs と、demolib の hot-reload から使う
+
+mod build_script;
+mod compile;
+mod error;
+mod manifest;
+mod preprocess;
+mod reflection;
+
+pub use build_script::build;
+pub use compile::{compile_file, shader_stage, CompiledShader};
+pub use error::{Diagnostic, ShaderError};
+pub use manifest::{Manifest, ManifestShader, Permutation, ShaderTarget};
+pub use preprocess::{preprocess, Preprocessed};
+pub use reflection::{
+    reflect, MemberLayout, Resource, ResourceKind, ShaderReflection, StructLayout,
+    TextureDimension, TextureSampleType, VertexFormat, VertexInput,
+};
+
+// naga::ShaderStage などを使う側が naga に依存しなくて済むように
+pub use naga;
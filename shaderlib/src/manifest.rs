@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::ShaderError;
+
+// コンパイルする GLSL の一覧 (shaders.ron)
+//
+// (
+//     shaders: [
+//         (source: "triangle.vs"),
+//         (source: "mandelbrot.fs", permutations: [
+//             (name: "julia.fs", defines: {"FRACTAL_JULIA": "1"}),
+//         ]),
+//         (source: "model_3d.fs", axes: {"SHADOWS": ["0", "1"]}),
+//     ],
+// )
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub shaders: Vec<ManifestShader>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ManifestShader {
+    // shaders.ron からの相対パス
+    pub source: String,
+    // 空ならマクロを定義せずに source と同じ名前で 1 つだけ出力する
+    #[serde(default)]
+    pub permutations: Vec<Permutation>,
+    // マクロごとに取りうる値
+    // すべての組み合わせを model_3d.fs[SHADOWS=1] のような名前で出力する
+    #[serde(default)]
+    pub axes: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Permutation {
+    pub name: String,
+    #[serde(default)]
+    pub defines: BTreeMap<String, String>,
+}
+
+// 出力するシェーダー 1 つぶん
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderTarget {
+    pub name: String,
+    pub source: String,
+    pub path: PathBuf,
+    pub defines: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, ShaderError> {
+        let text = std::fs::read_to_string(path).map_err(|error| ShaderError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        ron::from_str(&text).map_err(|error| ShaderError::Manifest {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    // directory は shaders.ron のあるディレクトリ
+    pub fn targets(&self, directory: &Path) -> Vec<ShaderTarget> {
+        let mut targets = Vec::new();
+        for shader in &self.shaders {
+            let mut bases: Vec<_> = shader
+                .permutations
+                .iter()
+                .map(|permutation| (permutation.name.as_str(), &permutation.defines))
+                .collect();
+            let no_defines = BTreeMap::new();
+            if bases.is_empty() {
+                bases.push((shader.source.as_str(), &no_defines));
+            }
+
+            let combinations = axis_combinations(&shader.axes);
+            for (name, defines) in bases {
+                for combination in &combinations {
+                    let mut defines = defines.clone();
+                    defines.extend(combination.clone());
+                    targets.push(ShaderTarget {
+                        name: variant_name(name, combination),
+                        source: shader.source.clone(),
+                        path: directory.join(&shader.source),
+                        defines,
+                    });
+                }
+            }
+        }
+        targets
+    }
+}
+
+// 各軸から 1 つずつ値を選んだすべての組み合わせ
+// 軸がなければ空の組み合わせ 1 つ
+fn axis_combinations(axes: &BTreeMap<String, Vec<String>>) -> Vec<BTreeMap<String, String>> {
+    let mut combinations = vec![BTreeMap::new()];
+    for (define, values) in axes {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(define.clone(), value.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+fn variant_name(name: &str, combination: &BTreeMap<String, String>) -> String {
+    if combination.is_empty() {
+        return name.to_string();
+    }
+    let defines: Vec<_> = combination
+        .iter()
+        .map(|(define, value)| format!("{define}={value}"))
+        .collect();
+    format!("{name}[{}]", defines.join(","))
+}
@@ -0,0 +1,92 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::{Diagnostic, ShaderError};
+
+// #include を展開した GLSL
+pub struct Preprocessed {
+    pub source: String,
+    // 展開に使ったファイル (先頭が元のファイル)
+    pub files: Vec<PathBuf>,
+    // 展開後の行ごとの (files の添字, 元のファイルでの行番号)
+    lines: Vec<(usize, usize)>,
+}
+
+impl Preprocessed {
+    // 展開後の行番号 (1 始まり) を元のファイルと行番号に戻す
+    pub fn locate(&self, line: usize) -> Option<(&Path, usize)> {
+        let (file, line) = *self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+}
+
+// #include "file" をそのファイルの中身に置き換える
+// パスは #include を書いたファイルからの相対パスで、同じファイルは一度しか展開しない
+pub fn preprocess(path: &Path) -> Result<Preprocessed, ShaderError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ShaderError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let mut preprocessed = Preprocessed {
+        source: String::new(),
+        files: vec![path.to_path_buf()],
+        lines: Vec::new(),
+    };
+    expand(&mut preprocessed, 0, &source)?;
+    Ok(preprocessed)
+}
+
+fn expand(preprocessed: &mut Preprocessed, file: usize, source: &str) -> Result<(), ShaderError> {
+    for (index, line) in source.lines().enumerate() {
+        let Some(argument) = line.trim_start().strip_prefix("#include") else {
+            preprocessed.source.push_str(line);
+            preprocessed.source.push('\n');
+            preprocessed.lines.push((file, index + 1));
+            continue;
+        };
+
+        let path = &preprocessed.files[file];
+        let diagnostic = |message: String| Diagnostic {
+            file: path.clone(),
+            line: index + 1,
+            column: line.len() - line.trim_start().len() + 1,
+            message,
+        };
+        let Some(name) = argument
+            .trim()
+            .strip_prefix('"')
+            .and_then(|argument| argument.strip_suffix('"'))
+        else {
+            return Err(diagnostic("expected #include \"file\"".to_string()).into());
+        };
+
+        let include = normalize(&path.parent().unwrap_or(Path::new("")).join(name));
+        if preprocessed.files.contains(&include) {
+            continue;
+        }
+        let included = std::fs::read_to_string(&include)
+            .map_err(|error| diagnostic(format!("cannot include {name}: {error}")))?;
+        preprocessed.files.push(include);
+        expand(preprocessed, preprocessed.files.len() - 1, &included)?;
+    }
+    Ok(())
+}
+
+// 同じファイルを別のパスで #include しても同じものと分かるように、. と .. を取り除く
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
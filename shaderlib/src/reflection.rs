@@ -0,0 +1,293 @@
+// naga のモジュールから、パイプラインを作るのに必要な情報を取り出す
+
+// 名前は wgpu の同名の列挙子に合わせている
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureDimension {
+    D1,
+    D2,
+    D2Array,
+    Cube,
+    CubeArray,
+    D3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureSampleType {
+    Float,
+    Depth,
+    Sint,
+    Uint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexFormat {
+    Float32,
+    Float32x2,
+    Float32x3,
+    Float32x4,
+    Sint32,
+    Sint32x2,
+    Sint32x3,
+    Sint32x4,
+    Uint32,
+    Uint32x2,
+    Uint32x3,
+    Uint32x4,
+}
+
+impl VertexFormat {
+    pub fn size(&self) -> u32 {
+        match self {
+            VertexFormat::Float32 | VertexFormat::Sint32 | VertexFormat::Uint32 => 4,
+            VertexFormat::Float32x2 | VertexFormat::Sint32x2 | VertexFormat::Uint32x2 => 8,
+            VertexFormat::Float32x3 | VertexFormat::Sint32x3 | VertexFormat::Uint32x3 => 12,
+            VertexFormat::Float32x4 | VertexFormat::Sint32x4 | VertexFormat::Uint32x4 => 16,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResourceKind {
+    // size はブロックの大きさ (std140)
+    UniformBuffer {
+        size: u32,
+    },
+    StorageBuffer {
+        size: u32,
+        read_only: bool,
+    },
+    Texture {
+        dimension: TextureDimension,
+        sample_type: TextureSampleType,
+        multisampled: bool,
+    },
+    Sampler {
+        comparison: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resource {
+    pub group: u32,
+    pub binding: u32,
+    // ブロックなら型の名前、それ以外は変数の名前
+    pub name: String,
+    pub kind: ResourceKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub name: String,
+    pub format: VertexFormat,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberLayout {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+// バッファーのブロックと、その中で使われている構造体のレイアウト
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructLayout {
+    pub name: String,
+    pub size: u32,
+    pub members: Vec<MemberLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderReflection {
+    pub stage: naga::ShaderStage,
+    // group, binding の順
+    pub resources: Vec<Resource>,
+    // location の順 (頂点シェーダーのみ)
+    pub vertex_inputs: Vec<VertexInput>,
+    pub structs: Vec<StructLayout>,
+}
+
+impl ShaderReflection {
+    pub fn find_struct(&self, name: &str) -> Option<&StructLayout> {
+        self.structs.iter().find(|layout| layout.name == name)
+    }
+}
+
+pub fn reflect(module: &naga::Module, stage: naga::ShaderStage) -> ShaderReflection {
+    let mut resources = Vec::new();
+    let mut structs = Vec::new();
+    for (_, variable) in module.global_variables.iter() {
+        let Some(binding) = &variable.binding else {
+            continue;
+        };
+        let ty = &module.types[variable.ty];
+        let kind = match (variable.space, &ty.inner) {
+            (naga::AddressSpace::Uniform, inner) => ResourceKind::UniformBuffer {
+                size: inner.size(module.to_ctx()),
+            },
+            (naga::AddressSpace::Storage { access }, inner) => ResourceKind::StorageBuffer {
+                size: inner.size(module.to_ctx()),
+                read_only: !access.contains(naga::StorageAccess::STORE),
+            },
+            (
+                naga::AddressSpace::Handle,
+                naga::TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class,
+                },
+            ) => {
+                let (sample_type, multisampled) = match *class {
+                    naga::ImageClass::Sampled { kind, multi } => (
+                        match kind {
+                            naga::ScalarKind::Sint => TextureSampleType::Sint,
+                            naga::ScalarKind::Uint => TextureSampleType::Uint,
+                            _ => TextureSampleType::Float,
+                        },
+                        multi,
+                    ),
+                    naga::ImageClass::Depth { multi } => (TextureSampleType::Depth, multi),
+                    // ストレージテクスチャーは使っていない
+                    naga::ImageClass::Storage { .. } => continue,
+                };
+                let dimension = match (dim, arrayed) {
+                    (naga::ImageDimension::D1, _) => TextureDimension::D1,
+                    (naga::ImageDimension::D2, false) => TextureDimension::D2,
+                    (naga::ImageDimension::D2, true) => TextureDimension::D2Array,
+                    (naga::ImageDimension::D3, _) => TextureDimension::D3,
+                    (naga::ImageDimension::Cube, false) => TextureDimension::Cube,
+                    (naga::ImageDimension::Cube, true) => TextureDimension::CubeArray,
+                };
+                ResourceKind::Texture {
+                    dimension,
+                    sample_type,
+                    multisampled,
+                }
+            }
+            (naga::AddressSpace::Handle, naga::TypeInner::Sampler { comparison }) => {
+                ResourceKind::Sampler {
+                    comparison: *comparison,
+                }
+            }
+            _ => continue,
+        };
+        if matches!(
+            kind,
+            ResourceKind::UniformBuffer { .. } | ResourceKind::StorageBuffer { .. }
+        ) {
+            collect_structs(module, variable.ty, &mut structs);
+        }
+        resources.push(Resource {
+            group: binding.group,
+            binding: binding.binding,
+            name: ty
+                .name
+                .clone()
+                .or_else(|| variable.name.clone())
+                .unwrap_or_default(),
+            kind,
+        });
+    }
+    resources.sort_by_key(|resource| (resource.group, resource.binding));
+
+    let mut vertex_inputs = Vec::new();
+    if stage == naga::ShaderStage::Vertex {
+        let entry_points = module
+            .entry_points
+            .iter()
+            .filter(|entry_point| entry_point.stage == stage);
+        for argument in entry_points.flat_map(|entry_point| &entry_point.function.arguments) {
+            let Some(naga::Binding::Location { location, .. }) = argument.binding else {
+                continue;
+            };
+            let Some(format) = vertex_format(&module.types[argument.ty].inner) else {
+                continue;
+            };
+            vertex_inputs.push(VertexInput {
+                location,
+                name: argument.name.clone().unwrap_or_default(),
+                format,
+            });
+        }
+        vertex_inputs.sort_by_key(|input| input.location);
+    }
+
+    ShaderReflection {
+        stage,
+        resources,
+        vertex_inputs,
+        structs,
+    }
+}
+
+// ty とその中で使われている構造体を、外側から順に集める
+fn collect_structs(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+    structs: &mut Vec<StructLayout>,
+) {
+    let ty = &module.types[ty];
+    let members = match &ty.inner {
+        naga::TypeInner::Struct { members, span } => {
+            let name = ty.name.clone().unwrap_or_default();
+            if structs.iter().any(|layout| layout.name == name) {
+                return;
+            }
+            structs.push(StructLayout {
+                name,
+                size: *span,
+                members: members
+                    .iter()
+                    .map(|member| MemberLayout {
+                        name: member.name.clone().unwrap_or_default(),
+                        offset: member.offset,
+                        size: module.types[member.ty].inner.size(module.to_ctx()),
+                    })
+                    .collect(),
+            });
+            members
+        }
+        naga::TypeInner::Array { base, .. } | naga::TypeInner::BindingArray { base, .. } => {
+            collect_structs(module, *base, structs);
+            return;
+        }
+        _ => return,
+    };
+    for member in members {
+        collect_structs(module, member.ty, structs);
+    }
+}
+
+fn vertex_format(inner: &naga::TypeInner) -> Option<VertexFormat> {
+    let (kind, size) = match *inner {
+        naga::TypeInner::Scalar { kind, width: 4 } => (kind, 1),
+        naga::TypeInner::Vector {
+            size,
+            kind,
+            width: 4,
+        } => (kind, size as u32),
+        _ => return None,
+    };
+    let formats = match kind {
+        naga::ScalarKind::Float => [
+            VertexFormat::Float32,
+            VertexFormat::Float32x2,
+            VertexFormat::Float32x3,
+            VertexFormat::Float32x4,
+        ],
+        naga::ScalarKind::Sint => [
+            VertexFormat::Sint32,
+            VertexFormat::Sint32x2,
+            VertexFormat::Sint32x3,
+            VertexFormat::Sint32x4,
+        ],
+        naga::ScalarKind::Uint => [
+            VertexFormat::Uint32,
+            VertexFormat::Uint32x2,
+            VertexFormat::Uint32x3,
+            VertexFormat::Uint32x4,
+        ],
+        naga::ScalarKind::Bool => return None,
+    };
+    Some(formats[size as usize - 1])
+}
@@ -0,0 +1,274 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use shaderlib::{
+    compile_file, preprocess, Manifest, ResourceKind, ShaderError, TextureDimension,
+    TextureSampleType, VertexFormat,
+};
+
+// テストごとのディレクトリに files を書き出す
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    for (file, contents) in files {
+        let path = directory.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    directory
+}
+
+const COLOR_FS: &str = "#version 450
+#include \"common/color.glsl\"
+layout(location = 0) out vec4 o_Color;
+void main() {
+    o_Color = vec4(color(), 1.0);
+}
+";
+
+#[test]
+fn compile_with_include() {
+    let directory = write_files(
+        "compile_with_include",
+        &[
+            ("color.fs", COLOR_FS),
+            (
+                "common/color.glsl",
+                "#include \"../common/color.glsl\"\nvec3 color() { return vec3(0.25, 0.5, 1.0); }\n",
+            ),
+        ],
+    );
+    let compiled = compile_file(&directory.join("color.fs"), &BTreeMap::new()).unwrap();
+    assert_eq!(compiled.stage, shaderlib::naga::ShaderStage::Fragment);
+    assert!(compiled.wgsl.contains("fn color()"), "{}", compiled.wgsl);
+    // SPIR-V のマジックナンバー
+    assert_eq!(compiled.spirv[0], 0x0723_0203);
+    assert_eq!(compiled.dependencies.len(), 2);
+    assert!(compiled.dependencies[1].ends_with("common/color.glsl"));
+}
+
+#[test]
+fn report_error_in_included_file() {
+    let directory = write_files(
+        "report_error_in_included_file",
+        &[
+            ("color.fs", COLOR_FS),
+            (
+                "common/color.glsl",
+                "// 色\nvec3 color() { return vec3(0.25, 0.5, undefined_value); }\n",
+            ),
+        ],
+    );
+    let Err(ShaderError::Compile(diagnostics)) =
+        compile_file(&directory.join("color.fs"), &BTreeMap::new())
+    else {
+        panic!("expected a compile error");
+    };
+    let diagnostic = &diagnostics[0];
+    assert!(diagnostic.file.ends_with("common/color.glsl"));
+    assert_eq!(diagnostic.line, 2);
+    assert!(diagnostic.column > 1);
+    assert!(
+        diagnostic.to_string().contains("color.glsl:2:"),
+        "{diagnostic}"
+    );
+}
+
+#[test]
+fn report_missing_include() {
+    let directory = write_files("report_missing_include", &[("color.fs", COLOR_FS)]);
+    let Err(ShaderError::Compile(diagnostics)) = preprocess(&directory.join("color.fs")) else {
+        panic!("expected a missing include");
+    };
+    assert!(diagnostics[0].file.ends_with("color.fs"));
+    assert_eq!(diagnostics[0].line, 2);
+}
+
+#[test]
+fn compile_permutations() {
+    let directory = write_files(
+        "compile_permutations",
+        &[
+            (
+                "value.fs",
+                "#version 450
+layout(location = 0) out vec4 o_Color;
+void main() {
+#ifdef USE_RED
+    o_Color = vec4(1.0, 0.0, 0.0, 1.0);
+#else
+    o_Color = vec4(0.0, 0.0, VALUE, 1.0);
+#endif
+}
+",
+            ),
+            (
+                "shaders.ron",
+                r#"(
+    shaders: [
+        (source: "value.fs", permutations: [
+            (name: "red.fs", defines: {"USE_RED": "1"}),
+            (name: "blue.fs", defines: {"VALUE": "0.75"}),
+        ]),
+    ],
+)"#,
+            ),
+        ],
+    );
+    let manifest = Manifest::load(&directory.join("shaders.ron")).unwrap();
+    let targets = manifest.targets(&directory);
+    let names: Vec<_> = targets.iter().map(|target| target.name.as_str()).collect();
+    assert_eq!(names, ["red.fs", "blue.fs"]);
+
+    let red = compile_file(&targets[0].path, &targets[0].defines).unwrap();
+    let blue = compile_file(&targets[1].path, &targets[1].defines).unwrap();
+    assert!(!red.wgsl.contains("0.75"));
+    assert!(blue.wgsl.contains("0.75"), "{}", blue.wgsl);
+
+    // VALUE を定義しないとエラーになる
+    assert!(compile_file(&targets[0].path, &BTreeMap::new()).is_err());
+}
+
+#[test]
+fn report_manifest_error() {
+    let directory = write_files(
+        "report_manifest_error",
+        &[("shaders.ron", "(shaders: [(sorce: \"a.vs\")])")],
+    );
+    let error = Manifest::load(&directory.join("shaders.ron")).unwrap_err();
+    assert!(matches!(error, ShaderError::Manifest { .. }));
+    assert!(error.to_string().contains("shaders.ron:1:"), "{error}");
+}
+
+#[test]
+fn expand_axes() {
+    let manifest: Manifest = ron::from_str(
+        r#"(
+    shaders: [
+        (source: "lit.fs", axes: {"SHADOWS": ["0", "1"], "QUALITY": ["LOW", "HIGH"]}),
+        (source: "value.fs", permutations: [(name: "red.fs", defines: {"USE_RED": "1"})], axes: {"SHADOWS": ["1"]}),
+    ],
+)"#,
+    )
+    .unwrap();
+    let targets = manifest.targets(Path::new("shaders"));
+    let names: Vec<_> = targets.iter().map(|target| target.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "lit.fs[QUALITY=LOW,SHADOWS=0]",
+            "lit.fs[QUALITY=LOW,SHADOWS=1]",
+            "lit.fs[QUALITY=HIGH,SHADOWS=0]",
+            "lit.fs[QUALITY=HIGH,SHADOWS=1]",
+            "red.fs[SHADOWS=1]",
+        ]
+    );
+    assert_eq!(targets[2].path, Path::new("shaders/lit.fs"));
+    assert_eq!(
+        targets[4].defines,
+        BTreeMap::from([
+            ("SHADOWS".to_string(), "1".to_string()),
+            ("USE_RED".to_string(), "1".to_string()),
+        ])
+    );
+}
+
+#[test]
+fn reflect_resources_and_layouts() {
+    let directory = write_files(
+        "reflect_resources_and_layouts",
+        &[(
+            "lit.fs",
+            "#version 450
+layout(location = 0) in vec3 v_Normal;
+layout(location = 0) out vec4 o_Color;
+struct Light {
+    vec3 direction;
+    float intensity;
+};
+layout(set = 0, binding = 0) uniform Material {
+    vec3 u_Color;
+    float u_Roughness;
+    Light u_Light;
+};
+layout(set = 1, binding = 0) uniform texture2D t_Albedo;
+layout(set = 1, binding = 1) uniform sampler s_Albedo;
+void main() {
+    float diffuse = max(dot(v_Normal, -u_Light.direction), 0.0) * u_Light.intensity;
+    vec3 albedo = texture(sampler2D(t_Albedo, s_Albedo), v_Normal.xy).rgb;
+    o_Color = vec4(albedo * u_Color * diffuse + u_Roughness, 1.0);
+}
+",
+        )],
+    );
+    let compiled = compile_file(&directory.join("lit.fs"), &BTreeMap::new()).unwrap();
+    let reflection = &compiled.reflection;
+
+    assert_eq!(
+        reflection
+            .resources
+            .iter()
+            .map(|resource| (resource.group, resource.binding, resource.kind.clone()))
+            .collect::<Vec<_>>(),
+        [
+            (0, 0, ResourceKind::UniformBuffer { size: 32 }),
+            (
+                1,
+                0,
+                ResourceKind::Texture {
+                    dimension: TextureDimension::D2,
+                    sample_type: TextureSampleType::Float,
+                    multisampled: false,
+                }
+            ),
+            (1, 1, ResourceKind::Sampler { comparison: false }),
+        ]
+    );
+    // フラグメントシェーダーの入力は頂点バッファーではない
+    assert!(reflection.vertex_inputs.is_empty());
+
+    let material = reflection.find_struct("Material").unwrap();
+    assert_eq!(material.size, 32);
+    assert_eq!(
+        material
+            .members
+            .iter()
+            .map(|member| (member.name.as_str(), member.offset))
+            .collect::<Vec<_>>(),
+        [("u_Color", 0), ("u_Roughness", 12), ("u_Light", 16)]
+    );
+    assert_eq!(reflection.find_struct("Light").unwrap().size, 16);
+}
+
+#[test]
+fn reflect_vertex_inputs() {
+    let directory = write_files(
+        "reflect_vertex_inputs",
+        &[(
+            "mesh.vs",
+            "#version 450
+layout(location = 0) in vec3 i_Position;
+layout(location = 2) in vec2 i_TexCoord;
+layout(location = 1) in uvec4 i_Joints;
+void main() {
+    gl_Position = vec4(i_Position + vec3(i_TexCoord, float(i_Joints.x)), 1.0);
+}
+",
+        )],
+    );
+    let compiled = compile_file(&directory.join("mesh.vs"), &BTreeMap::new()).unwrap();
+    assert_eq!(
+        compiled
+            .reflection
+            .vertex_inputs
+            .iter()
+            .map(|input| (input.location, input.name.as_str(), input.format))
+            .collect::<Vec<_>>(),
+        [
+            (0, "i_Position", VertexFormat::Float32x3),
+            (1, "i_Joints", VertexFormat::Uint32x4),
+            (2, "i_TexCoord", VertexFormat::Float32x2),
+        ]
+    );
+}